@@ -1,15 +1,20 @@
-#![feature(test)]
-
 extern crate mpd;
-extern crate test;
 
+use std::hint::black_box;
 use std::os::unix::net::UnixStream;
-use test::{black_box, Bencher};
+use std::time::Instant;
+
+const ITERATIONS: u32 = 1000;
 
-#[bench]
-fn status(b: &mut Bencher) {
+fn status() {
     let mut mpd = mpd::Client::<UnixStream>::new(UnixStream::connect("/var/run/mpd/socket").unwrap()).unwrap();
-    b.iter(|| {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
         black_box(mpd.status()).unwrap();
-    });
+    }
+    println!("status: {:?}/iter", start.elapsed() / ITERATIONS);
+}
+
+fn main() {
+    status();
 }
@@ -0,0 +1,103 @@
+extern crate mpd;
+
+use std::hint::black_box;
+use std::io::{self, Cursor, Read, Write};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 30;
+const SONGS: usize = 11_000;
+
+/// In-memory stream replaying a canned server response
+struct Canned(Cursor<Vec<u8>>);
+
+impl Read for Canned {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for Canned {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A `listallinfo` response of 100k lines
+fn listallinfo_fixture() -> String {
+    let mut out = String::from("OK MPD 0.23.5\n");
+    for i in 0..SONGS {
+        if i % 10 == 0 {
+            out.push_str(&format!("directory: Artist {}/Album {}\nLast-Modified: 2023-01-01T00:00:00Z\n", i / 100, i / 10));
+        }
+        out.push_str(&format!(
+            "file: Artist {a}/Album {b}/{i:02} - Track.flac\nLast-Modified: 2023-01-01T00:00:00Z\nFormat: 44100:16:2\n\
+             Artist: Artist {a}\nAlbumArtist: Artist {a}\nTitle: Track {i}\nAlbum: Album {b}\nTrack: {t}\n\
+             duration: 215.120\n",
+            a = i / 100,
+            b = i / 10,
+            i = i,
+            t = i % 10 + 1,
+        ));
+    }
+    out.push_str("OK\n");
+    out
+}
+
+/// Best time of several runs, which is the least affected by noise
+fn best_of<F: FnMut()>(mut f: F) -> Duration {
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn listallinfo(fixture: &str) {
+    let time = best_of(|| {
+        let mut mpd = mpd::Client::new(Canned(Cursor::new(fixture.as_bytes().to_vec()))).unwrap();
+        black_box(mpd.listallinfo()).unwrap();
+    });
+    println!("listallinfo ({} lines): {:?}", fixture.lines().count(), time);
+}
+
+fn listallinfo_entries(fixture: &str) {
+    let time = best_of(|| {
+        let mut mpd = mpd::Client::new(Canned(Cursor::new(fixture.as_bytes().to_vec()))).unwrap();
+        for entry in mpd.listallinfo_entries("").unwrap() {
+            black_box(entry).unwrap();
+        }
+    });
+    println!("listallinfo_entries ({} lines): {:?}", fixture.lines().count(), time);
+}
+
+fn listfiles(fixture: &str) {
+    let time = best_of(|| {
+        let mut mpd = mpd::Client::new(Canned(Cursor::new(fixture.as_bytes().to_vec()))).unwrap();
+        black_box(mpd.listfiles("")).unwrap();
+    });
+    println!("listfiles ({} lines): {:?}", fixture.lines().count(), time);
+}
+
+fn reply(fixture: &str) {
+    let time = best_of(|| {
+        for line in fixture.lines() {
+            let _ = black_box(line.parse::<mpd::reply::Reply>());
+        }
+    });
+    println!("Reply::from_str: {:?}", time);
+}
+
+fn main() {
+    let fixture = listallinfo_fixture();
+    listallinfo(&fixture);
+    listallinfo_entries(&fixture);
+    listfiles(&fixture);
+    reply(&fixture);
+}
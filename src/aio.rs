@@ -0,0 +1,548 @@
+//! The module defines runtime-agnostic asynchronous client
+//!
+//! [`Client`] works on top of any stream implementing `futures` [`AsyncRead`] and
+//! [`AsyncWrite`] traits, so it can be used with async-std, smol or any other
+//! runtime. Tokio users can use [`mpd::tokio`](crate::tokio) module instead.
+//!
+//! [`Client`] mirrors playback, queue, stored playlist and database search methods
+//! of the blocking [`Client`](crate::Client). Streaming iterators, binary responses
+//! (album art), stickers, outputs and partitions are not covered yet.
+//!
+//! Protocol machinery is exposed with [`AsyncProto`] trait, which can be used
+//! to run commands not covered by [`Client`] methods yet.
+//!
+//! ```rust,no_run
+//! # async fn run<S: futures_util::io::AsyncRead + futures_util::io::AsyncWrite + Send + Unpin>(stream: S) -> mpd::error::Result<()> {
+//! use mpd::aio::{AsyncProto, Client};
+//!
+//! let mut conn = Client::new(stream).await?;
+//! conn.play().await?;
+//! let urls: Vec<String> = conn.read_list("urlhandlers", (), "handler").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::codec;
+use crate::convert::{FromIter, ToPlaylistName, ToQueuePlace, ToQueueRange, ToQueueRangeOrPlace, ToSeconds, ToSongId, ToSongPath, ToSongRange};
+use crate::error::{Error, ParseError, Result};
+use crate::idle::Subsystem;
+use crate::playlist::{Playlist, SaveMode};
+use crate::proto::ToArguments;
+use crate::search::{AddOptions, Query, Sort, Window};
+use crate::song::{Id, PosIdChange, QueuePosition, Song};
+use crate::stats::{Count, Stats};
+use crate::status::Status;
+use crate::trace;
+use crate::update::UpdateJob;
+use crate::version::Version;
+
+use futures_util::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use futures_util::stream::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+
+/// Asynchronous MPD protocol transport
+///
+/// Implementors only need to send encoded requests and collect responses
+/// (see [`codec`] module for helpers), other methods run
+/// commands and parse results on top of that.
+pub trait AsyncProto: Send {
+    /// Send encoded request and collect the whole response, including the terminating `OK` or `ACK` line
+    fn request(&mut self, request: &str) -> impl Future<Output = Result<String>> + Send;
+
+    /// Run command, expecting no data in response
+    fn command<A: ToArguments>(&mut self, command: &str, arguments: A) -> impl Future<Output = Result<()>> + Send {
+        let request = codec::encode_command(command, arguments);
+        async move { codec::parse_ok(&self.request(&request).await?) }
+    }
+
+    /// Run command and parse response into a single structure
+    fn read_struct<T: FromIter, A: ToArguments>(&mut self, command: &str, arguments: A) -> impl Future<Output = Result<T>> + Send {
+        let request = codec::encode_command(command, arguments);
+        async move { codec::parse_response(&self.request(&request).await?) }
+    }
+
+    /// Run command and parse response into a list of structures, each starting with `key` field
+    fn read_structs<T: FromIter, A: ToArguments>(&mut self, command: &str, arguments: A, key: &str) -> impl Future<Output = Result<Vec<T>>> + Send {
+        let request = codec::encode_command(command, arguments);
+        async move { codec::parse_structs(&self.request(&request).await?, key) }
+    }
+
+    /// Run command and collect values of all fields named `key`
+    fn read_list<A: ToArguments>(&mut self, command: &str, arguments: A, key: &str) -> impl Future<Output = Result<Vec<String>>> + Send {
+        let request = codec::encode_command(command, arguments);
+        async move { codec::parse_list(&self.request(&request).await?, key) }
+    }
+
+    /// Run command and parse a single field named `key` from response
+    fn read_field<T: FromStr, A: ToArguments>(&mut self, command: &str, arguments: A, key: &'static str) -> impl Future<Output = Result<T>> + Send
+    where ParseError: From<T::Err> {
+        let request = codec::encode_command(command, arguments);
+        async move { codec::parse_field(&self.request(&request).await?, key) }
+    }
+}
+
+/// Asynchronous client connection
+#[derive(Debug)]
+pub struct Client<S>
+where S: AsyncRead + AsyncWrite + Send + Unpin
+{
+    socket: BufReader<S>,
+    /// MPD protocol version
+    pub version: Version,
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin> AsyncProto for Client<S> {
+    async fn request(&mut self, request: &str) -> Result<String> {
+        let span = trace::command_span(request.split([' ', '\n']).next().unwrap_or_default());
+        for line in request.lines() {
+            trace::sent(&span, line, &());
+        }
+        self.socket.write_all(request.as_bytes()).await?;
+        self.socket.flush().await?;
+
+        let mut response = String::new();
+        loop {
+            let start = response.len();
+            if self.socket.read_line(&mut response).await? == 0 {
+                return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by server")));
+            }
+            trace::received(&span, &response[start..]);
+            if codec::is_response_end(response[start..].trim_end_matches('\n')) {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin> Client<S> {
+    // Constructors {{{
+    /// Create client from some arbitrary pre-connected socket
+    pub async fn new(socket: S) -> Result<Client<S>> {
+        let mut socket = BufReader::new(socket);
+
+        let mut banner = String::new();
+        socket.read_line(&mut banner).await?;
+        let version = codec::parse_banner(&banner)?;
+
+        Ok(Client { socket, version })
+    }
+
+    /// Release underlying socket
+    pub fn into_inner(self) -> S {
+        self.socket.into_inner()
+    }
+    // }}}
+
+    // Playback options & status {{{
+    /// Get MPD status
+    pub async fn status(&mut self) -> Result<Status> {
+        let request = codec::encode_command_list(&[("status", ()), ("replay_gain_status", ())]);
+        codec::parse_response(&self.request(&request).await?)
+    }
+
+    /// Get MPD playing statistics
+    pub async fn stats(&mut self) -> Result<Stats> {
+        self.read_struct("stats", ()).await
+    }
+
+    /// Set volume
+    pub async fn volume(&mut self, volume: i8) -> Result<()> {
+        self.command("setvol", volume).await
+    }
+
+    /// Set repeat state
+    pub async fn repeat(&mut self, value: bool) -> Result<()> {
+        self.command("repeat", value).await
+    }
+
+    /// Set random state
+    pub async fn random(&mut self, value: bool) -> Result<()> {
+        self.command("random", value).await
+    }
+
+    /// Set single state
+    pub async fn single(&mut self, value: bool) -> Result<()> {
+        self.command("single", value).await
+    }
+
+    /// Set consume state
+    pub async fn consume(&mut self, value: bool) -> Result<()> {
+        self.command("consume", value).await
+    }
+    // }}}
+
+    // Playback control {{{
+    /// Start playback
+    pub async fn play(&mut self) -> Result<()> {
+        self.command("play", ()).await
+    }
+
+    /// Start playback from given song in a queue
+    pub async fn switch<T: ToQueuePlace>(&mut self, place: T) -> Result<()> {
+        let command = if T::is_id() { "playid" } else { "play" };
+        self.command(command, place.to_place()).await
+    }
+
+    /// Switch to a next song in queue
+    #[allow(clippy::should_implement_trait)]
+    pub async fn next(&mut self) -> Result<()> {
+        self.command("next", ()).await
+    }
+
+    /// Switch to a previous song in queue
+    pub async fn prev(&mut self) -> Result<()> {
+        self.command("previous", ()).await
+    }
+
+    /// Stop playback
+    pub async fn stop(&mut self) -> Result<()> {
+        self.command("stop", ()).await
+    }
+
+    /// Toggle pause state
+    pub async fn toggle_pause(&mut self) -> Result<()> {
+        self.command("pause", ()).await
+    }
+
+    /// Set pause state
+    pub async fn pause(&mut self, value: bool) -> Result<()> {
+        self.command("pause", value).await
+    }
+
+    /// Seek to a given place (in seconds) in a given song
+    pub async fn seek<T: ToSeconds, P: ToQueuePlace>(&mut self, place: P, pos: T) -> Result<()> {
+        let command = if P::is_id() { "seekid" } else { "seek" };
+        self.command(command, (place.to_place(), pos.to_seconds())).await
+    }
+
+    /// Seek to a given place (in seconds) in the current song
+    pub async fn rewind<T: ToSeconds>(&mut self, pos: T) -> Result<()> {
+        self.command("seekcur", pos.to_seconds()).await
+    }
+    // }}}
+
+    // Queue control {{{
+    /// Get info of given song in a play queue
+    pub async fn songs<T: ToQueueRangeOrPlace>(&mut self, pos: T) -> Result<Vec<Song>> {
+        let command = if T::is_id() { "playlistid" } else { "playlistinfo" };
+        self.read_structs(command, pos.to_range(), "file").await
+    }
+
+    /// List all songs or range of songs in a play queue
+    pub async fn queue<W: Into<Window>>(&mut self, window: W) -> Result<Vec<Song>> {
+        let mut prefixless_window: Window = window.into();
+        prefixless_window.include_prefix = false;
+        self.read_structs("playlistinfo", prefixless_window, "file").await
+    }
+
+    /// Get current playing song
+    pub async fn currentsong(&mut self) -> Result<Option<Song>> {
+        self.read_struct::<Song, _>("currentsong", ()).await.map(|s| if s.place.is_none() { None } else { Some(s) })
+    }
+
+    /// Get queued song by its ID
+    pub async fn playlistid(&mut self, id: Id) -> Result<Option<Song>> {
+        self.read_struct::<Song, _>("playlistid", id).await.map(|s| if s.place.is_none() { None } else { Some(s) })
+    }
+
+    /// Clear current queue
+    pub async fn clear(&mut self) -> Result<()> {
+        self.command("clear", ()).await
+    }
+
+    /// List all changes in a queue since given version
+    pub async fn changes<W: Into<Window>>(&mut self, version: u32, window: W) -> Result<Vec<Song>> {
+        let mut prefixless_window: Window = window.into();
+        prefixless_window.include_prefix = false;
+        self.read_structs("plchanges", (version, prefixless_window), "file").await
+    }
+
+    /// List positions and ids of songs changed in a queue since given version
+    ///
+    /// See [`Client::changesposid()`](crate::Client::changesposid) for details.
+    pub async fn changesposid<W: Into<Window>>(&mut self, version: u32, window: W) -> Result<Vec<PosIdChange>> {
+        let mut prefixless_window: Window = window.into();
+        prefixless_window.include_prefix = false;
+        self.read_structs("plchangesposid", (version, prefixless_window), "cpos").await
+    }
+
+    /// Append a song into a queue
+    pub async fn push<P: ToSongPath>(&mut self, path: P) -> Result<Id> {
+        self.read_field("addid", path, "Id").await.map(Id)
+    }
+
+    /// Append multiple songs into a queue with a single command list
+    pub async fn push_multiple<I>(&mut self, paths: I) -> Result<Vec<Id>>
+    where I: IntoIterator, I::Item: ToSongPath {
+        let commands: Vec<(&str, I::Item)> = paths.into_iter().map(|p| ("addid", p)).collect();
+        let request = codec::encode_command_list(&commands);
+        let ids = codec::parse_list(&self.request(&request).await?, "Id")?;
+        ids.iter().map(|id| id.parse().map(Id).map_err(|e| Error::Parse(From::from(e)))).collect()
+    }
+
+    /// Insert a song into a given position in a queue
+    ///
+    /// The position is either absolute, or relative to the current song (see [`QueuePosition`]).
+    pub async fn insert<P: ToSongPath, Q: Into<QueuePosition>>(&mut self, path: P, pos: Q) -> Result<usize> {
+        self.read_field("addid", (path, pos.into()), "Id").await
+    }
+
+    /// Delete a song (at some position) or several songs (in a range) from a queue
+    pub async fn delete<T: ToQueueRangeOrPlace>(&mut self, pos: T) -> Result<()> {
+        let command = if T::is_id() { "deleteid" } else { "delete" };
+        self.command(command, pos.to_range()).await
+    }
+
+    /// Move a song (at a some position) or several songs (in a range) to other position in queue
+    pub async fn shift<T: ToQueueRangeOrPlace, Q: Into<QueuePosition>>(&mut self, from: T, to: Q) -> Result<()> {
+        let command = if T::is_id() { "moveid" } else { "move" };
+        self.command(command, (from.to_range(), to.into())).await
+    }
+
+    /// Swap to songs in a queue
+    pub async fn swap<T: ToQueuePlace>(&mut self, one: T, two: T) -> Result<()> {
+        let command = if T::is_id() { "swapid" } else { "swap" };
+        self.command(command, (one.to_place(), two.to_place())).await
+    }
+
+    /// Shuffle queue in a given range (use `..` to shuffle full queue)
+    pub async fn shuffle<T: ToQueueRange>(&mut self, range: T) -> Result<()> {
+        self.command("shuffle", range.to_range()).await
+    }
+
+    /// Set song priority in a queue
+    pub async fn priority<T: ToQueueRangeOrPlace>(&mut self, pos: T, prio: u8) -> Result<()> {
+        let command = if T::is_id() { "prioid" } else { "prio" };
+        self.command(command, (prio, pos.to_range())).await
+    }
+
+    /// Set song range (in seconds) to play, uses `rangeid` command
+    pub async fn range<T: ToSongId, R: ToSongRange>(&mut self, song: T, range: R) -> Result<()> {
+        self.command("rangeid", (song.to_song_id(), range.to_range())).await
+    }
+    // }}}
+
+    // Playlist methods {{{
+    /// List all playlists
+    pub async fn playlists(&mut self) -> Result<Vec<Playlist>> {
+        self.read_structs("listplaylists", (), "playlist").await
+    }
+
+    /// List all songs in a playlist, or only songs in given range (MPD 0.24+)
+    pub async fn playlist<T: ToQueueRange, N: ToPlaylistName>(&mut self, name: N, range: Option<T>) -> Result<Vec<Song>> {
+        self.read_structs("listplaylistinfo", (name.to_name(), range.map(ToQueueRange::to_range)), "file").await
+    }
+
+    /// Load playlist into queue
+    pub async fn load<T: ToQueueRange, N: ToPlaylistName>(&mut self, name: N, range: T) -> Result<()> {
+        self.command("load", (name.to_name(), range.to_range())).await
+    }
+
+    /// Save current queue into playlist
+    pub async fn save<N: ToPlaylistName>(&mut self, name: N, mode: Option<SaveMode>) -> Result<()> {
+        self.command("save", (name.to_name(), mode.map(|mode| mode.as_str()))).await
+    }
+
+    /// Rename playlist
+    pub async fn pl_rename<N: ToPlaylistName, M: AsRef<str>>(&mut self, name: N, newname: M) -> Result<()> {
+        self.command("rename", (name.to_name(), newname.as_ref())).await
+    }
+
+    /// Clear playlist
+    pub async fn pl_clear<N: ToPlaylistName>(&mut self, name: N) -> Result<()> {
+        self.command("playlistclear", name.to_name()).await
+    }
+
+    /// Delete playlist
+    pub async fn pl_remove<N: ToPlaylistName>(&mut self, name: N) -> Result<()> {
+        self.command("rm", name.to_name()).await
+    }
+
+    /// Add new songs to a playlist
+    pub async fn pl_push<N: ToPlaylistName, P: ToSongPath>(&mut self, name: N, path: P) -> Result<()> {
+        self.command("playlistadd", (name.to_name(), path)).await
+    }
+
+    /// Delete a song at a given position in a playlist
+    pub async fn pl_delete<N: ToPlaylistName>(&mut self, name: N, pos: u32) -> Result<()> {
+        self.command("playlistdelete", (name.to_name(), pos)).await
+    }
+
+    /// Move song in a playlist from one position into another
+    pub async fn pl_shift<N: ToPlaylistName, T: AsRef<str>>(&mut self, name: N, from: u32, to: T) -> Result<()> {
+        self.command("playlistmove", (name.to_name(), from, to.as_ref())).await
+    }
+    // }}}
+
+    // Database methods {{{
+    /// Run database rescan, i.e. remove non-existing files from DB
+    /// as well as add new files to DB
+    pub async fn rescan(&mut self) -> Result<UpdateJob> {
+        self.read_field("rescan", (), "updating_db").await.map(|id| UpdateJob { id })
+    }
+
+    /// Run database update, i.e. remove non-existing files from DB
+    pub async fn update(&mut self) -> Result<UpdateJob> {
+        self.read_field("update", (), "updating_db").await.map(|id| UpdateJob { id })
+    }
+    // }}}
+
+    // Database search {{{
+    /// Find songs matching Query conditions.
+    pub async fn find<W: Into<Window>>(&mut self, query: &Query<'_>, window: W) -> Result<Vec<Song>> {
+        self.read_structs("find", (query, window.into()), "file").await
+    }
+
+    /// Find songs matching Query conditions, sorted by given key
+    pub async fn find_sorted<W: Into<Window>>(&mut self, query: &Query<'_>, sort: &Sort<'_>, window: W) -> Result<Vec<Song>> {
+        self.read_structs("find", (query, sort, window.into()), "file").await
+    }
+
+    /// Case-insensitively search for songs matching Query conditions.
+    pub async fn search<W: Into<Window>>(&mut self, query: &Query<'_>, window: W) -> Result<Vec<Song>> {
+        self.read_structs("search", (query, window.into()), "file").await
+    }
+
+    /// Case-insensitively search for songs matching Query conditions, sorted by given key
+    pub async fn search_sorted<W: Into<Window>>(&mut self, query: &Query<'_>, sort: &Sort<'_>, window: W) -> Result<Vec<Song>> {
+        self.read_structs("search", (query, sort, window.into()), "file").await
+    }
+
+    /// Count songs matching Query conditions and their total playtime
+    pub async fn count(&mut self, query: &Query<'_>) -> Result<Count> {
+        self.read_struct("count", query).await
+    }
+
+    /// Find all songs in the db that match query and adds them to current playlist.
+    pub async fn findadd(&mut self, query: &Query<'_>) -> Result<()> {
+        self.findadd_with(query, &AddOptions::new()).await
+    }
+
+    /// Find all songs in the db that match query and adds them to current playlist, with given options.
+    pub async fn findadd_with(&mut self, query: &Query<'_>, options: &AddOptions<'_>) -> Result<()> {
+        self.command("findadd", (query, options)).await
+    }
+
+    /// Case-insensitively search for songs matching Query conditions and add them to current playlist.
+    pub async fn searchadd(&mut self, query: &Query<'_>) -> Result<()> {
+        self.searchadd_with(query, &AddOptions::new()).await
+    }
+
+    /// Case-insensitively search for songs matching Query conditions and add them to current playlist, with given options.
+    pub async fn searchadd_with(&mut self, query: &Query<'_>, options: &AddOptions<'_>) -> Result<()> {
+        self.command("searchadd", (query, options)).await
+    }
+    // }}}
+
+    // Connection settings {{{
+    /// Just pings MPD server, does nothing
+    pub async fn ping(&mut self) -> Result<()> {
+        self.command("ping", ()).await
+    }
+
+    /// Send password to authenticate on MPD server
+    pub async fn login<P: AsRef<str>>(&mut self, password: P) -> Result<()> {
+        self.command("password", password.as_ref()).await
+    }
+
+    /// Close MPD connection
+    pub async fn close(&mut self) -> Result<()> {
+        self.command("close", ()).await
+    }
+    // }}}
+
+    // Idle {{{
+    /// Wait for events from a set of subsystems and return list of affected subsystems
+    ///
+    /// If empty subsystems slice is given, wait for all event from any subsystem.
+    ///
+    /// Dropping the future before it completes leaves the connection in "idle" mode,
+    /// so the client must not be used for anything else after that.
+    pub async fn wait(&mut self, subsystems: &[Subsystem]) -> Result<Vec<Subsystem>> {
+        let changed = self.read_list("idle", subsystems, "changed").await?;
+        changed.into_iter().map(|b| b.parse().map_err(From::from)).collect()
+    }
+
+    /// Turn the client into a stream of idle events from a set of subsystems
+    ///
+    /// If empty subsystems slice is given, listen for events from any subsystem.
+    pub fn into_idle_stream(self, subsystems: &[Subsystem]) -> IdleStream<S>
+    where S: 'static {
+        IdleStream { subsystems: subsystems.to_vec(), events: VecDeque::new(), state: IdleState::Ready(self) }
+    }
+    // }}}
+}
+
+type IdleFuture<S> = Pin<Box<dyn Future<Output = (Client<S>, Result<Vec<Subsystem>>)> + Send>>;
+
+enum IdleState<S: AsyncRead + AsyncWrite + Send + Unpin> {
+    Ready(Client<S>),
+    Waiting(IdleFuture<S>),
+    Closed,
+}
+
+/// Stream of idle events
+///
+/// The stream issues `idle` command again after each batch of events, so it never ends
+/// by itself. If the connection fails, the error is yielded and the stream ends.
+///
+/// The connection is busy waiting for events all the time, so a separate client is needed
+/// to run commands in reaction to them.
+pub struct IdleStream<S: AsyncRead + AsyncWrite + Send + Unpin> {
+    subsystems: Vec<Subsystem>,
+    events: VecDeque<Subsystem>,
+    state: IdleState<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin + 'static> IdleStream<S> {
+    /// Release underlying client, unless it's waiting for events at the moment
+    ///
+    /// Events already received but not yet yielded by the stream are lost.
+    pub fn into_client(self) -> Option<Client<S>> {
+        match self.state {
+            IdleState::Ready(client) => Some(client),
+            _ => None,
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin + 'static> Stream for IdleStream<S> {
+    type Item = Result<Subsystem>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Subsystem>>> {
+        let this = &mut *self;
+        loop {
+            if let Some(subsystem) = this.events.pop_front() {
+                return Poll::Ready(Some(Ok(subsystem)));
+            }
+
+            match mem::replace(&mut this.state, IdleState::Closed) {
+                IdleState::Ready(mut client) => {
+                    let subsystems = this.subsystems.clone();
+                    this.state = IdleState::Waiting(Box::pin(async move {
+                        let result = client.wait(&subsystems).await;
+                        (client, result)
+                    }));
+                }
+                IdleState::Waiting(mut future) => match future.as_mut().poll(cx) {
+                    Poll::Ready((client, Ok(events))) => {
+                        this.events.extend(events);
+                        this.state = IdleState::Ready(client);
+                    }
+                    Poll::Ready((_, Err(e))) => return Poll::Ready(Some(Err(e))),
+                    Poll::Pending => {
+                        this.state = IdleState::Waiting(future);
+                        return Poll::Pending;
+                    }
+                },
+                IdleState::Closed => return Poll::Ready(None),
+            }
+        }
+    }
+}
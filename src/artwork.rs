@@ -0,0 +1,187 @@
+//! The module defines helpers for MPD binary responses used to transfer artwork
+//!
+//! Commands like `albumart` return data in chunks: each response carries the
+//! total `size`, the `binary` length of the chunk, and then raw chunk bytes,
+//! so the client has to repeat the command with increasing offset until all
+//! the data is received.
+//!
+//! Embedded pictures read with [`readpicture()`](Client::readpicture) also have
+//! MIME type reported, so they are returned as [`Picture`].
+//!
+//! Large images don't have to be kept in memory as a whole: [`Chunks`] iterator
+//! yields each piece of data as soon as it's received, and can also copy all of
+//! them into any [`Write`] sink.
+//!
+//! ```rust,no_run
+//! # use mpd::{Client, Song};
+//! # use std::fs::File;
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let song = conn.currentsong().unwrap().unwrap();
+//! let mut file = File::create("cover").unwrap();
+//! conn.albumart_chunks(&song).write_to(&mut file).unwrap();
+//! ```
+
+use crate::client::Client;
+use crate::convert::ToSongPath;
+use crate::error::{Error, ProtoError, Result};
+use crate::proto::Proto;
+use crate::reply::Reply;
+
+use std::io::{self, Read, Write};
+
+/// Picture embedded into a song file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Picture {
+    /// picture bytes
+    pub data: Vec<u8>,
+    /// picture MIME type (like `image/jpeg`), if known
+    pub mime: Option<String>,
+}
+
+/// Single chunk of a binary response
+pub(crate) struct Chunk {
+    /// total size of the data
+    pub size: usize,
+    /// data MIME type, if reported by the server
+    pub mime: Option<String>,
+    /// chunk bytes
+    pub data: Vec<u8>,
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Fetch a chunk of binary data starting at given offset
+    ///
+    /// Returns `None` if the server responded with an empty `OK`.
+    pub(crate) fn read_chunk<P: ToSongPath>(&mut self, command: &str, path: &P, offset: usize) -> Result<Option<Chunk>> {
+        self.run_command(command, (path, offset))?;
+
+        let mut size = None;
+        let mut mime = None;
+        let length = loop {
+            match self.read_line()?.parse::<Reply>()? {
+                Reply::Ok => return Ok(None),
+                Reply::Ack(e) => return Err(Error::Server(e)),
+                Reply::Pair(key, value) => match &*key {
+                    "size" => size = Some(value.parse()?),
+                    "type" => mime = Some(value),
+                    "binary" => break value.parse::<usize>()?,
+                    _ => (),
+                },
+            }
+        };
+
+        let data = self.read_bytes(length)?;
+        if data.len() < length {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by server")));
+        }
+        // Binary data is followed by a newline
+        self.read_line()?;
+        self.expect_ok()?;
+
+        let size = size.ok_or(ProtoError::NoField("size"))?;
+        Ok(Some(Chunk { size, mime, data }))
+    }
+
+    /// Fetch all chunks of binary data, returning `None` on empty response
+    pub(crate) fn read_binary<P: ToSongPath>(&mut self, command: &'static str, path: &P) -> Result<Option<(Vec<u8>, Option<String>)>> {
+        let mut chunks = Chunks::new(self, command, path);
+        let mut buf = Vec::new();
+        while let Some(chunk) = chunks.next() {
+            if buf.is_empty() {
+                buf.reserve_exact(chunks.size().unwrap_or_default());
+            }
+            buf.extend_from_slice(&chunk?);
+        }
+        Ok(chunks.size.map(|_| (buf, chunks.mime)))
+    }
+
+    /// Iterate over album art chunks as they are received, without buffering the whole image
+    ///
+    /// See [`albumart()`](Client::albumart) for details.
+    pub fn albumart_chunks<'a, P: ToSongPath>(&'a mut self, path: &'a P) -> Chunks<'a, S, P> {
+        Chunks::new(self, "albumart", path)
+    }
+
+    /// Iterate over embedded picture chunks as they are received, without buffering the whole image
+    ///
+    /// The iterator is empty if the file has no embedded pictures.
+    /// See [`readpicture()`](Client::readpicture) for details.
+    pub fn readpicture_chunks<'a, P: ToSongPath>(&'a mut self, path: &'a P) -> Chunks<'a, S, P> {
+        Chunks::new(self, "readpicture", path)
+    }
+}
+
+/// Iterator over chunks of binary response
+///
+/// Each item is the next piece of data, as sent by the server. The iterator
+/// stops after the first error.
+pub struct Chunks<'a, S: 'a + Read + Write, P: 'a> {
+    client: &'a mut Client<S>,
+    command: &'static str,
+    path: &'a P,
+    offset: usize,
+    size: Option<usize>,
+    mime: Option<String>,
+    done: bool,
+}
+
+impl<'a, S: 'a + Read + Write, P: 'a + ToSongPath> Chunks<'a, S, P> {
+    fn new(client: &'a mut Client<S>, command: &'static str, path: &'a P) -> Chunks<'a, S, P> {
+        Chunks { client, command, path, offset: 0, size: None, mime: None, done: false }
+    }
+
+    /// Total size of the data, known after the first chunk is received
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+
+    /// MIME type of the data, if reported by the server with the first chunk
+    pub fn mime(&self) -> Option<&str> {
+        self.mime.as_deref()
+    }
+
+    /// Write all the remaining chunks into the writer, returning total number of bytes written
+    pub fn write_to<W: Write>(&mut self, out: &mut W) -> Result<usize> {
+        let mut written = 0;
+        for chunk in self {
+            let chunk = chunk?;
+            out.write_all(&chunk)?;
+            written += chunk.len();
+        }
+        Ok(written)
+    }
+}
+
+impl<'a, S: 'a + Read + Write, P: 'a + ToSongPath> Iterator for Chunks<'a, S, P> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Result<Vec<u8>>> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.client.read_chunk(self.command, self.path, self.offset);
+        self.done = true;
+        let chunk = match result {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) if self.size.is_none() => return None,
+            Ok(None) => return Some(Err(Error::Proto(ProtoError::NoField("binary")))),
+            Err(e) => return Some(Err(e)),
+        };
+
+        if self.size.is_none() {
+            self.size = Some(chunk.size);
+            self.mime = chunk.mime;
+        }
+        self.offset += chunk.data.len();
+
+        if self.offset < chunk.size {
+            // The server should never send empty chunks before the end of data
+            if chunk.data.is_empty() {
+                return Some(Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "empty chunk in binary response"))));
+            }
+            self.done = false;
+        }
+        Some(Ok(chunk.data))
+    }
+}
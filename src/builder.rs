@@ -0,0 +1,170 @@
+//! The module defines client builder, which performs connection setup
+//!
+//! Most applications need to do the same things right after connecting to MPD:
+//! send a password, limit binary chunk size, select tag types to receive and
+//! switch to a partition. [`ClientBuilder`] does all of that in the right order.
+//!
+//! ```rust,no_run
+//! use mpd::ClientBuilder;
+//! use std::time::Duration;
+//!
+//! let mut conn = ClientBuilder::from_env()
+//!     .unwrap()
+//!     .connect_timeout(Duration::from_secs(5))
+//!     .read_timeout(Duration::from_secs(10))
+//!     .tagtypes(&["artist", "album", "title"])
+//!     .connect()
+//!     .unwrap();
+//! println!("Status: {:?}", conn.status());
+//! ```
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::socket::{Endpoint, Socket};
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+/// Client connection builder
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    endpoint: Endpoint,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    binary_limit: Option<usize>,
+    tagtypes: Option<Vec<String>>,
+    partition: Option<String>,
+    lenient: bool,
+    capacities: Option<(usize, usize)>,
+}
+
+impl ClientBuilder {
+    /// Create builder connecting to given endpoint
+    pub fn new(endpoint: Endpoint) -> ClientBuilder {
+        ClientBuilder {
+            endpoint,
+            connect_timeout: None,
+            read_timeout: None,
+            write_timeout: None,
+            binary_limit: None,
+            tagtypes: None,
+            partition: None,
+            lenient: false,
+            capacities: None,
+        }
+    }
+
+    /// Create builder connecting to endpoint given by `MPD_HOST` and `MPD_PORT` environment variables
+    pub fn from_env() -> Result<ClientBuilder> {
+        Endpoint::from_env().map(ClientBuilder::new)
+    }
+
+    /// Create builder connecting to endpoint given by connection URL
+    pub fn from_url(url: &str) -> Result<ClientBuilder> {
+        Endpoint::from_url(url).map(ClientBuilder::new)
+    }
+
+    /// Set password to login with, overriding the one from endpoint
+    pub fn password(mut self, password: &str) -> ClientBuilder {
+        self.endpoint.password = Some(password.to_owned());
+        self
+    }
+
+    /// Set timeout for establishing TCP connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set timeout for reading server responses
+    ///
+    /// Note the timeout applies to `idle` command as well.
+    pub fn read_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Set timeout for sending commands
+    pub fn write_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Set maximum size of binary chunks sent by the server (MPD 0.22.4+)
+    pub fn binary_limit(mut self, limit: usize) -> ClientBuilder {
+        self.binary_limit = Some(limit);
+        self
+    }
+
+    /// Receive only given tag types in song information, instead of all of them
+    pub fn tagtypes(mut self, tagtypes: &[&str]) -> ClientBuilder {
+        self.tagtypes = Some(tagtypes.iter().map(|&tag| tag.to_owned()).collect());
+        self
+    }
+
+    /// Switch to given partition after connecting (MPD 0.22+)
+    pub fn partition(mut self, name: &str) -> ClientBuilder {
+        self.partition = Some(name.to_owned());
+        self
+    }
+
+    /// Tolerate malformed fields in responses, see [`Client::set_lenient()`]
+    pub fn lenient(mut self, lenient: bool) -> ClientBuilder {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Set read and write buffer sizes of the connection, see [`Client::with_capacities()`]
+    pub fn buffer_capacities(mut self, read_capacity: usize, write_capacity: usize) -> ClientBuilder {
+        self.capacities = Some((read_capacity, write_capacity));
+        self
+    }
+
+    /// Connect to the endpoint and set the connection up
+    pub fn connect(&self) -> Result<Client<Socket>> {
+        let socket = match self.connect_timeout {
+            Some(timeout) => self.endpoint.address.connect_timeout(timeout)?,
+            None => self.endpoint.address.connect()?,
+        };
+        socket.set_read_timeout(self.read_timeout)?;
+        socket.set_write_timeout(self.write_timeout)?;
+
+        let client = match self.capacities {
+            Some((read_capacity, write_capacity)) => Client::with_capacities(socket, read_capacity, write_capacity)?,
+            None => Client::new(socket)?,
+        };
+        self.setup(client)
+    }
+
+    /// Set up already connected client (timeouts are not applied)
+    pub fn setup<S: Read + Write>(&self, mut client: Client<S>) -> Result<Client<S>> {
+        client.set_lenient(self.lenient);
+        if let Some(ref password) = self.endpoint.password {
+            client.login(password)?;
+        }
+        if let Some(limit) = self.binary_limit {
+            client.binarylimit(limit)?;
+        }
+        self.setup_tagtypes(&mut client)?;
+        self.setup_partition(&mut client)?;
+        Ok(client)
+    }
+
+    pub(crate) fn setup_tagtypes<S: Read + Write>(&self, client: &mut Client<S>) -> Result<()> {
+        if let Some(ref tagtypes) = self.tagtypes {
+            client.tagtypes_clear()?;
+            if !tagtypes.is_empty() {
+                client.tagtypes_enable(tagtypes)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn setup_partition<S: Read + Write>(&self, client: &mut Client<S>) -> Result<()> {
+        match self.partition {
+            Some(ref partition) => client.switch_partition(partition),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! The module defines server capabilities data structure
+//!
+//! [`ServerCapabilities`] collects everything needed to decide if some command or
+//! feature can be used on the current connection: protocol version, available commands,
+//! enabled tag types and available protocol features. The client fetches it once
+//! with [`capabilities()`](crate::Client::capabilities), and refetches it only after
+//! something affecting it (like sending a password) is done on the connection.
+//!
+//! ```rust,no_run
+//! # use mpd::Client;
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! if conn.capabilities().unwrap().has_command("albumart") {
+//!     // show covers
+//! }
+//! ```
+
+use crate::error::Error;
+use crate::version::{Feature, ProtocolFeature, Version};
+
+use std::collections::BTreeSet;
+
+/// Commands, tag types and features available on the connection
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerCapabilities {
+    /// protocol version
+    pub version: Version,
+    /// commands available to the connection
+    pub commands: BTreeSet<String>,
+    /// tag types enabled for the connection
+    pub tagtypes: BTreeSet<String>,
+    /// protocol features supported by the server (MPD 0.24+)
+    pub protocol_features: Vec<ProtocolFeature>,
+}
+
+impl ServerCapabilities {
+    /// Check if the command is available
+    pub fn has_command(&self, command: &str) -> bool {
+        self.commands.contains(command)
+    }
+
+    /// Check if the tag type is enabled, tag names are case-insensitive
+    pub fn has_tagtype(&self, tagtype: &str) -> bool {
+        self.tagtypes.iter().any(|t| t.eq_ignore_ascii_case(tagtype))
+    }
+
+    /// Check if the protocol feature is supported by the server
+    pub fn has_protocol_feature(&self, feature: &ProtocolFeature) -> bool {
+        self.protocol_features.contains(feature)
+    }
+
+    /// Check if the server supports given feature, judging by its protocol version
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.version.supports(feature)
+    }
+
+    /// Parse replies to `commands`, `tagtypes` and `protocol available` commands
+    pub(crate) fn from_pairs<I: Iterator<Item = Result<(String, String), Error>>>(version: Version, iter: I) -> Result<ServerCapabilities, Error> {
+        let mut result = ServerCapabilities { version, commands: BTreeSet::new(), tagtypes: BTreeSet::new(), protocol_features: Vec::new() };
+
+        for res in iter {
+            let line = res?;
+            match &*line.0 {
+                "command" => {
+                    result.commands.insert(line.1);
+                }
+                "tagtype" => {
+                    result.tagtypes.insert(line.1);
+                }
+                "feature" => result.protocol_features.push(line.1.parse()?),
+                _ => (),
+            }
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,216 @@
+//! The module defines transports for recording and replaying protocol sessions
+//!
+//! [`Recorder`] wraps any stream and copies all the bytes sent and received into
+//! a capture file. [`Replay`] plays such a capture back as a server: it checks
+//! that the client sends the same requests and responds with recorded responses,
+//! so odd server behaviour can be reproduced without the server.
+//!
+//! ```rust,no_run
+//! use mpd::capture::{Recorder, Replay};
+//! use mpd::Client;
+//! use std::fs::File;
+//! use std::net::TcpStream;
+//!
+//! let stream = TcpStream::connect("127.0.0.1:6600").unwrap();
+//! let mut conn = Client::new(Recorder::new(stream, File::create("session.cap").unwrap())).unwrap();
+//! conn.status().unwrap();
+//! drop(conn);
+//!
+//! let mut conn = Client::new(Replay::open("session.cap").unwrap()).unwrap();
+//! conn.status().unwrap();
+//! ```
+//!
+//! The capture is a sequence of records, each one is a direction marker (`C` for
+//! data sent by the client, `S` for data sent by the server), a space, data length,
+//! a newline, the data itself and another newline. For text protocol this keeps
+//! captures readable and easy to edit by hand.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// Stream wrapper writing all the traffic into a capture
+#[derive(Debug)]
+pub struct Recorder<S, W: Write> {
+    stream: S,
+    sink: W,
+}
+
+impl<S: Read + Write, W: Write> Recorder<S, W> {
+    /// Record traffic of the stream into the sink
+    pub fn new(stream: S, sink: W) -> Recorder<S, W> {
+        Recorder { stream, sink }
+    }
+
+    /// Release underlying stream and capture sink
+    pub fn into_inner(self) -> (S, W) {
+        (self.stream, self.sink)
+    }
+
+    fn record(&mut self, direction: u8, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.sink, "{} {}", direction as char, data.len())?;
+        self.sink.write_all(data)?;
+        self.sink.write_all(b"\n")
+    }
+}
+
+impl<S: Read + Write, W: Write> Read for Recorder<S, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.stream.read(buf)?;
+        self.record(b'S', &buf[..len])?;
+        Ok(len)
+    }
+}
+
+impl<S: Read + Write, W: Write> Write for Recorder<S, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.stream.write(buf)?;
+        self.record(b'C', &buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush().and_then(|_| self.sink.flush())
+    }
+}
+
+/// Stream playing back a recorded session
+///
+/// Writes are checked against recorded client data, and fail with
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) error if the client sends
+/// something else. Reads return recorded server data, and report end of stream
+/// at the end of the capture.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    records: Vec<(u8, Vec<u8>)>,
+    index: usize,
+    offset: usize,
+}
+
+impl Replay {
+    /// Load capture from the reader
+    pub fn from_reader<R: Read>(reader: R) -> io::Result<Replay> {
+        let mut reader = BufReader::new(reader);
+        let mut records: Vec<(u8, Vec<u8>)> = Vec::new();
+        let mut header = String::new();
+        while reader.read_line(&mut header)? != 0 {
+            let (direction, len) = match header.trim_end().split_once(' ') {
+                Some(("C", len)) => (b'C', len),
+                Some(("S", len)) => (b'S', len),
+                _ => return Err(bad_capture("bad record header")),
+            };
+            let len: usize = len.parse().map_err(|_| bad_capture("bad record length"))?;
+            let mut data = vec![0; len + 1];
+            reader.read_exact(&mut data)?;
+            if data.pop() != Some(b'\n') {
+                return Err(bad_capture("missing newline after record data"));
+            }
+
+            // Merge records split by stream reads and writes
+            match records.last_mut() {
+                Some((last, buf)) if *last == direction => buf.extend_from_slice(&data),
+                _ => records.push((direction, data)),
+            }
+            header.clear();
+        }
+        Ok(Replay { records, index: 0, offset: 0 })
+    }
+
+    /// Load capture from the file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Replay> {
+        File::open(path).and_then(Replay::from_reader)
+    }
+
+    /// Check if the whole session was played back
+    pub fn is_finished(&self) -> bool {
+        self.index == self.records.len()
+    }
+
+    /// Remaining data of the current record, if it goes in the given direction
+    fn current(&self, direction: u8) -> Option<&[u8]> {
+        match self.records.get(self.index) {
+            Some((dir, data)) if *dir == direction => Some(&data[self.offset..]),
+            _ => None,
+        }
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.offset += len;
+        if self.offset == self.records[self.index].1.len() {
+            self.index += 1;
+            self.offset = 0;
+        }
+    }
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.is_finished() {
+            return Ok(0);
+        }
+        let data = self.current(b'S').ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "replay expects a request before reading"))?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.advance(len);
+        Ok(len)
+    }
+}
+
+impl Write for Replay {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let expected = self.current(b'C').unwrap_or_default();
+        let len = expected.len().min(buf.len());
+        if len == 0 || expected[..len] != buf[..len] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unexpected request {:?}, expected {:?}", String::from_utf8_lossy(buf), String::from_utf8_lossy(expected)),
+            ));
+        }
+        self.advance(len);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn bad_capture(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid capture: {}", message))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Recorder, Replay};
+    use crate::Client;
+
+    const CAPTURE: &[u8] = b"S 14\nOK MPD 0.23.5\n\nC 5\nping\n\nS 3\nOK\n\nC 12\nsetvol \"50\"\n\nS 3\nOK\n\n";
+
+    #[test]
+    fn replay() {
+        let mut conn = Client::new(Replay::from_reader(CAPTURE).unwrap()).unwrap();
+        conn.ping().unwrap();
+        assert!(conn.volume(20).is_err());
+    }
+
+    #[test]
+    fn tiny_buffers() {
+        let mut conn = Client::with_capacities(Replay::from_reader(CAPTURE).unwrap(), 1, 1).unwrap();
+        conn.ping().unwrap();
+        conn.volume(50).unwrap();
+    }
+
+    #[test]
+    fn record_replay() {
+        let mut capture = Vec::new();
+        let mut conn = Client::new(Recorder::new(Replay::from_reader(CAPTURE).unwrap(), &mut capture)).unwrap();
+        conn.ping().unwrap();
+        conn.volume(50).unwrap();
+        drop(conn);
+
+        assert_eq!(Replay::from_reader(&capture[..]).unwrap().records, Replay::from_reader(CAPTURE).unwrap().records);
+    }
+}
@@ -6,30 +6,46 @@
 
 use bufstream::BufStream;
 
+use crate::artwork::Picture;
+use crate::capabilities::ServerCapabilities;
+use crate::command_list::{CommandList, CommandReply};
+use crate::config::Config;
 use crate::convert::*;
-use crate::error::{Error, ProtoError, Result};
-use crate::lsinfo::LsInfoEntry;
-use crate::message::{Channel, Message};
-use crate::mount::{Mount, Neighbor};
+use crate::error::{CommandIoError, Error, ErrorCode, ParseWarning, ProtoError, Result, ServerError};
+use crate::idle::Nonblocking;
+use crate::iter::StructIter;
+use crate::lsinfo::{ENTRY_KEYS, ListAllEntry, LsInfoEntries, LsInfoEntry};
+use crate::message::{Channel, Message, Subscription};
+use crate::mount::{Mount, Neighbor, ToStorageUri};
+use crate::update::UpdateJob;
 use crate::output::Output;
-use crate::list::{GroupedValues};
+use crate::list::{GroupNode, GroupedValues};
 use crate::playlist::{Playlist, EditAction, SaveMode};
 use crate::plugin::Plugin;
 use crate::proto::*;
-use crate::search::{Query, Term, Window};
-use crate::song::{Id, PosIdChange, Song};
-use crate::stats::Stats;
-use crate::status::{ReplayGain, Status};
-use crate::sticker::Sticker;
-use crate::version::Version;
-
+use crate::search::{AddOptions, Query, Sort, Term, Window};
+use crate::song::{Id, PosIdChange, QueuePosition, Song};
+use crate::stats::{Count, Stats};
+use crate::status::{ReplayGain, SeekMode, State, Status};
+use crate::sticker::{Sticker, StickerOp};
+use crate::trace::{self, CommandSpan};
+use crate::version::{Feature, ProtocolFeature, Version};
+
+use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::convert::From;
-use std::io::{BufRead, Lines, Read, Write};
-use std::net::{TcpStream, ToSocketAddrs};
+use std::io::{BufRead, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+#[cfg(feature = "net")]
+use std::net::ToSocketAddrs;
 
 // Client {{{
 
+/// Capacity of the response buffer kept between commands, the buffer is shrunk back
+/// to it after big responses, like a listing of the whole library
+const RESPONSE_CAPACITY: usize = 64 * 1024;
+
 /// Client connection
 #[derive(Debug)]
 pub struct Client<S = TcpStream>
@@ -38,14 +54,27 @@ where S: Read + Write
     socket: BufStream<S>,
     /// MPD protocol version
     pub version: Version,
+    /// name of the last command sent, for error reporting
+    command: String,
+    capabilities: Option<ServerCapabilities>,
+    /// reusable buffer for responses parsed as a whole
+    response: String,
+    /// reusable buffer for responses read line by line
+    line: Vec<u8>,
+    /// span of the last command, for protocol tracing
+    span: CommandSpan,
+    /// skipped response pairs, if the client is lenient
+    warnings: Option<Vec<ParseWarning>>,
 }
 
+#[cfg(feature = "net")]
 impl Default for Client<TcpStream> {
     fn default() -> Client<TcpStream> {
         Client::<TcpStream>::connect("127.0.0.1:6600").unwrap()
     }
 }
 
+#[cfg(feature = "net")]
 impl Client<TcpStream> {
     /// Connect client to some IP address
     pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Client<TcpStream>> {
@@ -57,18 +86,59 @@ impl<S: Read + Write> Client<S> {
     // Constructors {{{
     /// Create client from some arbitrary pre-connected socket
     pub fn new(socket: S) -> Result<Client<S>> {
-        let mut socket = BufStream::new(socket);
+        Client::from_buffered(BufStream::new(socket))
+    }
+
+    /// Create client from some arbitrary pre-connected socket, with given read and write buffer sizes
+    ///
+    /// Default buffers hold 8 KiB each. Larger read buffer speeds up big responses,
+    /// like long queue listings or album art with large binary limit, and smaller
+    /// buffers save memory on tiny targets.
+    pub fn with_capacities(socket: S, read_capacity: usize, write_capacity: usize) -> Result<Client<S>> {
+        Client::from_buffered(BufStream::with_capacities(read_capacity, write_capacity, socket))
+    }
 
+    fn from_buffered(mut socket: BufStream<S>) -> Result<Client<S>> {
         let mut banner = String::new();
         socket.read_line(&mut banner)?;
 
-        if !banner.starts_with("OK MPD ") {
-            return Err(From::from(ProtoError::BadBanner));
+        let version = crate::codec::parse_banner(&banner)?;
+
+        Ok(Client { socket, version, command: String::new(), capabilities: None, response: String::new(), line: Vec::new(), span: trace::no_span(), warnings: None })
+    }
+    // }}}
+
+    // Lenient parsing {{{
+    /// Tolerate malformed fields in responses, instead of failing the whole call
+    ///
+    /// Third-party servers sometimes send values in unexpected format. A lenient client
+    /// skips such fields, as if they were not sent at all, and collects warnings about them,
+    /// to be retrieved with [`take_warnings()`](Client::take_warnings). Lines which are not
+    /// fields at all are skipped the same way. The call still fails if a required field is
+    /// skipped. Unknown fields are always ignored.
+    ///
+    /// Values are checked one field at a time, so this applies to responses parsed into
+    /// structures like [`Status`] or [`Song`], whether collected or iterated over.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        match (lenient, &self.warnings) {
+            (true, None) => self.warnings = Some(Vec::new()),
+            (false, _) => self.warnings = None,
+            _ => (),
         }
+    }
+
+    /// Check if the client is lenient, see [`set_lenient()`](Client::set_lenient)
+    pub fn is_lenient(&self) -> bool {
+        self.warnings.is_some()
+    }
 
-        let version = banner[7..].trim().parse::<Version>()?;
+    /// Take warnings about fields skipped since the last call
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        self.warnings.as_mut().map(std::mem::take).unwrap_or_default()
+    }
 
-        Ok(Client { socket, version })
+    pub(crate) fn warnings(&mut self) -> Option<&mut Vec<ParseWarning>> {
+        self.warnings.as_mut()
     }
     // }}}
 
@@ -105,22 +175,22 @@ impl<S: Read + Write> Client<S> {
 
     /// Set repeat state
     pub fn repeat(&mut self, value: bool) -> Result<()> {
-        self.run_command("repeat", value as u8).and_then(|_| self.expect_ok())
+        self.run_command("repeat", value).and_then(|_| self.expect_ok())
     }
 
     /// Set random state
     pub fn random(&mut self, value: bool) -> Result<()> {
-        self.run_command("random", value as u8).and_then(|_| self.expect_ok())
+        self.run_command("random", value).and_then(|_| self.expect_ok())
     }
 
     /// Set single state
     pub fn single(&mut self, value: bool) -> Result<()> {
-        self.run_command("single", value as u8).and_then(|_| self.expect_ok())
+        self.run_command("single", value).and_then(|_| self.expect_ok())
     }
 
     /// Set consume state
     pub fn consume(&mut self, value: bool) -> Result<()> {
-        self.run_command("consume", value as u8).and_then(|_| self.expect_ok())
+        self.run_command("consume", value).and_then(|_| self.expect_ok())
     }
 
     /// Set crossfade time in seconds
@@ -157,7 +227,7 @@ impl<S: Read + Write> Client<S> {
     }
 
     /// Switch to a next song in queue
-    #[cfg_attr(feature = "cargo-clippy", allow(clippy::should_implement_trait))]
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<()> {
         self.run_command("next", ()).and_then(|_| self.expect_ok())
     }
@@ -179,7 +249,7 @@ impl<S: Read + Write> Client<S> {
 
     /// Set pause state
     pub fn pause(&mut self, value: bool) -> Result<()> {
-        self.run_command("pause", value as u8).and_then(|_| self.expect_ok())
+        self.run_command("pause", value).and_then(|_| self.expect_ok())
     }
 
     /// Seek to a given place (in seconds) in a given song
@@ -192,6 +262,11 @@ impl<S: Read + Write> Client<S> {
     pub fn rewind<T: ToSeconds>(&mut self, pos: T) -> Result<()> {
         self.run_command("seekcur", pos.to_seconds()).and_then(|_| self.expect_ok())
     }
+
+    /// Seek to a given place (in seconds) in the current song, or by a given offset from current place
+    pub fn seek_current<T: ToSeconds>(&mut self, mode: SeekMode, pos: T) -> Result<()> {
+        self.run_command("seekcur", mode.position(pos.to_seconds())).and_then(|_| self.expect_ok())
+    }
     // }}}
 
     // Queue control {{{
@@ -208,9 +283,12 @@ impl<S: Read + Write> Client<S> {
         self.run_command("playlistinfo", prefixless_window).and_then(|_| self.read_structs("file"))
     }
 
-    /// Lists all songs in the database
-    pub fn listall(&mut self) -> Result<Vec<Song>> {
-        self.run_command("listall", ()).and_then(|_| self.read_structs("file"))
+    /// Iterate over songs in play queue, parsing them as they are received
+    ///
+    /// Unlike [`queue()`](Client::queue), the queue is never kept in memory as a whole.
+    pub fn queue_iter(&mut self) -> Result<StructIter<'_, S, Song>> {
+        self.run_command("playlistinfo", ())?;
+        Ok(StructIter::new(self, &["file"]))
     }
 
     /// Lists all songs in the database with metadata
@@ -218,6 +296,23 @@ impl<S: Read + Write> Client<S> {
         self.run_command("listallinfo", ()).and_then(|_| self.read_structs("file"))
     }
 
+    /// Lists all songs, directories and playlists under the given path, without metadata
+    ///
+    /// Use empty path to list the whole database.
+    pub fn listall<P: AsRef<str>>(&mut self, path: P) -> Result<Vec<ListAllEntry>> {
+        self.run_command("listall", path.as_ref()).and_then(|_| self.read_pairs().map(|p| p.and_then(ListAllEntry::from_pair)).collect())
+    }
+
+    /// Lists all songs, directories and playlists under the given path with metadata, parsing entries
+    /// as they are received
+    ///
+    /// Unlike [`listallinfo()`](Client::listallinfo), the response is never kept in memory as a whole,
+    /// which matters for big libraries. Use empty path to list the whole database.
+    pub fn listallinfo_entries<P: AsRef<str>>(&mut self, path: P) -> Result<LsInfoEntries<'_, S>> {
+        self.run_command("listallinfo", path.as_ref())?;
+        Ok(StructIter::new(self, ENTRY_KEYS))
+    }
+
     /// Get current playing song
     pub fn currentsong(&mut self) -> Result<Option<Song>> {
         self.run_command("currentsong", ())
@@ -232,6 +327,21 @@ impl<S: Read + Write> Client<S> {
             .map(|s| if s.place.is_none() { None } else { Some(s) })
     }
 
+    /// Get queued song by its ID, returning `None` if there is no such song in the queue
+    pub fn song_by_id(&mut self, id: Id) -> Result<Option<Song>> {
+        match self.playlistid(id) {
+            Err(Error::Server(ServerError { code: ErrorCode::NoExist, .. })) => Ok(None),
+            result => result,
+        }
+    }
+
+    /// List all songs in a play queue with `playlistid` command
+    ///
+    /// Returns the same songs as [`queue()`](Client::queue) without window.
+    pub fn queue_by_id(&mut self) -> Result<Vec<Song>> {
+        self.run_command("playlistid", ()).and_then(|_| self.read_structs("file"))
+    }
+
     /// Clear current queue
     pub fn clear(&mut self) -> Result<()> {
         self.run_command("clear", ()).and_then(|_| self.expect_ok())
@@ -255,6 +365,8 @@ impl<S: Read + Write> Client<S> {
     }
 
     /// Append a song into the queue
+    ///
+    /// Use [`insert()`](Client::insert) to add the song at a given position instead.
     pub fn push<P: ToSongPath>(&mut self, path: P) -> Result<Id> {
         self.run_command("addid", path).and_then(|_| self.read_field("Id")).map(Id)
     }
@@ -262,49 +374,116 @@ impl<S: Read + Write> Client<S> {
     /// Append multiple songs into the queue. This is more efficient than calling push() multiple times
     /// as it makes use of the command list interface, sending all commands at once and receiving a
     /// single reply. It will also only trigger a single idle message to other clients.
-    pub fn push_multiple<P: ToSongPath>(&mut self, paths: &[P]) -> Result<Vec<Id>> {
+    pub fn push_multiple<I>(&mut self, paths: I) -> Result<Vec<Id>>
+    where I: IntoIterator, I::Item: ToSongPath {
         self.run_command_list(
             &paths
-                .iter()
-                .map(|p: &P| {("addid", p)})
-                .collect::<Vec<(&str, &P)>>()
+                .into_iter()
+                .map(|p| {("addid", p)})
+                .collect::<Vec<(&str, I::Item)>>()
         ).and_then(|_| self.read_fields::<u32>("Id")).map(
             |ids| {ids.iter().map(|id| {Id(*id)}).collect()}
         )
     }
 
     /// Insert a song into a given position in a queue
-    pub fn insert<P: ToSongPath>(&mut self, path: P, pos: usize) -> Result<usize> {
-        self.run_command("addid", (path, pos)).and_then(|_| self.read_field("Id"))
+    ///
+    /// The position is either absolute, or relative to the current song (see [`QueuePosition`]).
+    pub fn insert<P: ToSongPath, Q: Into<QueuePosition>>(&mut self, path: P, pos: Q) -> Result<usize> {
+        self.run_command("addid", (path, pos.into())).and_then(|_| self.read_field("Id"))
     }
 
-    /// Insert multiple songs into a given position in a queue
-    pub fn insert_multiple<P: ToSongPath>(&mut self, paths: &[P], pos: usize) -> Result<Vec<usize>> {
-        let mut pos = pos;
+    /// Insert multiple songs into a given position in a queue, keeping their order
+    pub fn insert_multiple<I, Q>(&mut self, paths: I, pos: Q) -> Result<Vec<usize>>
+    where I: IntoIterator, I::Item: ToSongPath, Q: Into<QueuePosition> {
+        let mut pos = pos.into();
         self.run_command_list(
             &paths
-                .iter()
-                .map(|p: &P| {
+                .into_iter()
+                .map(|p| {
                     let current_pos = pos;
-                    pos += 1;
+                    pos = pos.next();
                     ("addid", (p, current_pos))
                 })
-                .collect::<Vec<(&str, (&P, usize))>>()
+                .collect::<Vec<(&str, (I::Item, QueuePosition))>>()
         ).and_then(|_| self.read_fields::<u32>("Id")).map(
             |ids| {ids.iter().map(|id| {*id as usize}).collect()}
         )
     }
 
+    /// Insert a song right after the current one, so it's played next
+    ///
+    /// If there is no current song, the song is inserted at the head of the queue.
+    /// Servers before MPD 0.23 don't support relative positions, so the song is
+    /// appended and then moved into place there.
+    pub fn queue_next<P: ToSongPath>(&mut self, path: P) -> Result<Id> {
+        let place = self.status()?.song;
+        match place {
+            Some(_) if self.supports(Feature::PositionOnAdd) => self.add_at(path, QueuePosition::AfterCurrent(0)),
+            Some(place) => {
+                let id = self.push(path)?;
+                self.shift(id, place.pos + 1).map(|_| id)
+            }
+            None => self.add_at(path, QueuePosition::Absolute(0)),
+        }
+    }
+
+    /// Insert a song right after the current one and start playing it at once
+    ///
+    /// See [`queue_next()`](Client::queue_next) for details.
+    pub fn play_now<P: ToSongPath>(&mut self, path: P) -> Result<Id> {
+        let id = self.queue_next(path)?;
+        self.switch(id).map(|_| id)
+    }
+
+    fn add_at<P: ToSongPath>(&mut self, path: P, pos: QueuePosition) -> Result<Id> {
+        self.run_command("addid", (path, pos)).and_then(|_| self.read_field("Id")).map(Id)
+    }
+
+    /// Replace the whole queue with given songs in a single command list, so other
+    /// clients never observe an empty queue in between.
+    ///
+    /// If `keep_current` is set and a song is playing (or paused), it is left untouched
+    /// at the head of the new queue and playback continues without a gap; the new songs
+    /// are appended after it. Otherwise the queue is cleared first.
+    pub fn replace_queue<I>(&mut self, songs: I, keep_current: bool) -> Result<Vec<Id>>
+    where I: IntoIterator, I::Item: ToSongPath {
+        let songs = songs.into_iter();
+        let status = self.status()?;
+        let mut commands: Vec<(&str, Vec<String>)> = Vec::with_capacity(songs.size_hint().0 + 2);
+
+        match status.song {
+            Some(place) if keep_current && status.state != State::Stop => {
+                if place.pos + 1 < status.queue_len {
+                    commands.push(("delete", vec![ToQueueRange::to_range(place.pos + 1..status.queue_len)]));
+                }
+                if place.pos > 0 {
+                    commands.push(("delete", vec![ToQueueRange::to_range(0..place.pos)]));
+                }
+            }
+            _ => commands.push(("clear", Vec::new())),
+        }
+
+        commands.extend(songs.map(|song| ("addid", vec![song.to_song_path().to_owned()])));
+
+        self.run_command_list(&commands).and_then(|_| self.read_fields::<u32>("Id")).map(|ids| ids.into_iter().map(Id).collect())
+    }
+
     /// Delete a song (at some position) or several songs (in a range) from a queue
+    ///
+    /// Any range of positions (like `2..10`, `..=5` or `3..`) is deleted with a single command.
     pub fn delete<T: ToQueueRangeOrPlace>(&mut self, pos: T) -> Result<()> {
         let command = if T::is_id() { "deleteid" } else { "delete" };
         self.run_command(command, pos.to_range()).and_then(|_| self.expect_ok())
     }
 
     /// Move a song (at a some position) or several songs (in a range) to other position in queue
-    pub fn shift<T: ToQueueRangeOrPlace>(&mut self, from: T, to: &str) -> Result<()> {
+    ///
+    /// A range is moved as a block, so that its first song ends up at the given position.
+    /// The position is either absolute, or relative to the current song (see [`QueuePosition`]).
+    pub fn shift<T: ToQueueRangeOrPlace, Q: Into<QueuePosition>>(&mut self, from: T, to: Q) -> Result<()> {
         let command = if T::is_id() { "moveid" } else { "move" };
-        self.run_command(command, (from.to_range(), to)).and_then(|_| self.expect_ok())
+        self.run_command(command, (from.to_range(), to.into())).and_then(|_| self.expect_ok())
     }
 
     /// Swap to songs in a queue
@@ -318,27 +497,48 @@ impl<S: Read + Write> Client<S> {
         self.run_command("shuffle", range.to_range()).and_then(|_| self.expect_ok())
     }
 
+    /// Shuffle songs after the current one, or the whole queue if nothing is playing
+    pub fn shuffle_remaining(&mut self) -> Result<()> {
+        let status = self.status()?;
+        match status.song {
+            Some(place) if status.state != State::Stop => self.shuffle(place.pos + 1..),
+            _ => self.shuffle(..),
+        }
+    }
+
     /// Set song priority in a queue
+    ///
+    /// Accepts a queue position or range (uses `prio` command), or a song [`Id`] (uses `prioid`).
+    /// Songs with higher priority are played first in random mode.
     pub fn priority<T: ToQueueRangeOrPlace>(&mut self, pos: T, prio: u8) -> Result<()> {
         let command = if T::is_id() { "prioid" } else { "prio" };
         self.run_command(command, (prio, pos.to_range())).and_then(|_| self.expect_ok())
     }
 
-    /// Set song range (in seconds) to play
+    /// Set song range (in seconds) to play, uses `rangeid` command
     ///
+    /// Pass `..` to clear the range and play the whole song again.
     /// Doesn't work for currently playing song.
     pub fn range<T: ToSongId, R: ToSongRange>(&mut self, song: T, range: R) -> Result<()> {
         self.run_command("rangeid", (song.to_song_id(), range.to_range())).and_then(|_| self.expect_ok())
     }
 
-    /// Add tag to a song
-    pub fn tag<T: ToSongId>(&mut self, song: T, tag: &str, value: &str) -> Result<()> {
-        self.run_command("addtagid", (song.to_song_id(), tag, value)).and_then(|_| self.expect_ok())
+    /// Add tag to a queued song
+    ///
+    /// MPD only allows this for remote songs (streams), e.g. to set title and artist
+    /// for an untagged http input. The tags are kept in the queue only.
+    pub fn tag<T: ToSongId, N: AsRef<str>, V: AsRef<str>>(&mut self, song: T, tag: N, value: V) -> Result<()> {
+        self.run_command("addtagid", (song.to_song_id(), tag.as_ref(), value.as_ref())).and_then(|_| self.expect_ok())
+    }
+
+    /// Delete tag from a queued song, see [`tag()`](Client::tag) for details
+    pub fn untag<T: ToSongId, N: AsRef<str>>(&mut self, song: T, tag: N) -> Result<()> {
+        self.run_command("cleartagid", (song.to_song_id(), tag.as_ref())).and_then(|_| self.expect_ok())
     }
 
-    /// Delete tag from a song
-    pub fn untag<T: ToSongId>(&mut self, song: T, tag: &str) -> Result<()> {
-        self.run_command("cleartagid", (song.to_song_id(), tag)).and_then(|_| self.expect_ok())
+    /// Delete all tags from a queued song, see [`tag()`](Client::tag) for details
+    pub fn untag_all<T: ToSongId>(&mut self, song: T) -> Result<()> {
+        self.run_command("cleartagid", song.to_song_id()).and_then(|_| self.expect_ok())
     }
     // }}}
 
@@ -359,8 +559,14 @@ impl<S: Read + Write> Client<S> {
     }
 
     /// Login to MPD server with given password
-    pub fn login(&mut self, password: &str) -> Result<()> {
-        self.run_command("password", password).and_then(|_| self.expect_ok())
+    pub fn login<P: AsRef<str>>(&mut self, password: P) -> Result<()> {
+        self.capabilities = None;
+        self.run_command("password", password.as_ref()).and_then(|_| self.expect_ok())
+    }
+
+    /// Set maximum size of binary chunks (like album art) sent by the server
+    pub fn binarylimit(&mut self, limit: usize) -> Result<()> {
+        self.run_command("binarylimit", limit).and_then(|_| self.expect_ok())
     }
     // }}}
 
@@ -402,8 +608,8 @@ impl<S: Read + Write> Client<S> {
     }
 
     /// Rename playlist
-    pub fn pl_rename<N: ToPlaylistName>(&mut self, name: N, newname: &str) -> Result<()> {
-        self.run_command("rename", (name.to_name(), newname)).and_then(|_| self.expect_ok())
+    pub fn pl_rename<N: ToPlaylistName, M: AsRef<str>>(&mut self, name: N, newname: M) -> Result<()> {
+        self.run_command("rename", (name.to_name(), newname.as_ref())).and_then(|_| self.expect_ok())
     }
 
     /// Clear playlist
@@ -426,30 +632,38 @@ impl<S: Read + Write> Client<S> {
         self.run_command("playlistdelete", (name.to_name(), pos)).and_then(|_| self.expect_ok())
     }
 
+    /// Delete songs in a given range of positions in a playlist (MPD 0.23.3+)
+    pub fn pl_delete_range<N: ToPlaylistName, T: ToQueueRange>(&mut self, name: N, range: T) -> Result<()> {
+        self.run_command("playlistdelete", (name.to_name(), range.to_range())).and_then(|_| self.expect_ok())
+    }
+
     /// Move song in a playlist from one position into another
-    pub fn pl_shift<N: ToPlaylistName>(&mut self, name: N, from: u32, to: &str) -> Result<()> {
-        self.run_command("playlistmove", (name.to_name(), from, to)).and_then(|_| self.expect_ok())
+    pub fn pl_shift<N: ToPlaylistName, T: AsRef<str>>(&mut self, name: N, from: u32, to: T) -> Result<()> {
+        self.run_command("playlistmove", (name.to_name(), from, to.as_ref())).and_then(|_| self.expect_ok())
     }
 
     /// Convenience method to pack multiple playlist edit actions into one command list.
     /// They will be executed sequentially but will only result in one idle message being
     /// sent out to clients, avoiding repeated refreshes.
     pub fn pl_edit(&mut self, actions: &[EditAction]) -> Result<()> {
+        self.track_command("command_list_begin");
+        trace::sent(&self.span, "command_list_begin", &());
         self.socket.write_all("command_list_begin".as_bytes())
             .and_then(|_| self.socket.write(&[0x0a]))
             .and_then(|_| self.socket.flush())?;
 
         for action in actions {
+            trace::sent(&self.span, action.command(), action);
             self.socket
                 .write_all(action.command().as_bytes())
                 .and_then(|_| action.to_arguments(&mut |arg| write!(self.socket, " {}", Quoted(arg))))
                 .and_then(|_| self.socket.write(&[0x0a]))
                 .and_then(|_| self.socket.flush())?;
         }
+        trace::sent(&self.span, "command_list_end", &());
         self.socket.write_all("command_list_end".as_bytes())
             .and_then(|_| self.socket.write(&[0x0a]))
             .and_then(|_| self.socket.flush())
-            .inspect(|r| {println!("{:?}", &r)})
             .map_err(From::from)
             .and_then(|_| self.expect_ok())
     }
@@ -458,94 +672,95 @@ impl<S: Read + Write> Client<S> {
     // Database methods {{{
     /// Run database rescan, i.e. remove non-existing files from DB
     /// as well as add new files to DB
-    pub fn rescan(&mut self) -> Result<u32> {
-        self.run_command("rescan", ()).and_then(|_| self.read_field("updating_db"))
+    ///
+    /// The rescan runs in background, use returned job to wait until it's finished.
+    pub fn rescan(&mut self) -> Result<UpdateJob> {
+        self.run_command("rescan", ()).and_then(|_| self.read_field("updating_db")).map(|id| UpdateJob { id })
     }
 
     /// Run database update, i.e. remove non-existing files from DB
-    pub fn update(&mut self) -> Result<u32> {
-        self.run_command("update", ()).and_then(|_| self.read_field("updating_db"))
+    ///
+    /// The update runs in background, use returned job to wait until it's finished.
+    pub fn update(&mut self) -> Result<UpdateJob> {
+        self.run_command("update", ()).and_then(|_| self.read_field("updating_db")).map(|id| UpdateJob { id })
     }
     // }}}
 
     // Database search {{{
-    // TODO: count tag needle [...] [group] [grouptag], find type what [...] [window start:end]
-    // TODO: search type what [...] [window start:end], searchadd type what [...]
-    // TODO: listfiles [uri]
     // TODO: list type [filtertype] [filterwhat] [...] [group] [grouptype] [...]
-    // TODO: searchaddpl name type what [...]
+    // TODO: count ... [group] [grouptag]
+    // TODO: listfiles [uri]
 
     /// List all songs/directories in directory
-    pub fn listfiles(&mut self, song_path: &str) -> Result<Vec<(String, String)>> {
-        self.run_command("listfiles", song_path).and_then(|_| self.read_pairs().collect())
+    pub fn listfiles<P: AsRef<str>>(&mut self, song_path: P) -> Result<Vec<(String, String)>> {
+        self.run_command("listfiles", song_path.as_ref()).and_then(|_| self.read_pairs().collect())
     }
 
     /// Find songs matching Query conditions.
     pub fn find<W>(&mut self, query: &Query, window: W) -> Result<Vec<Song>>
     where W: Into<Window> {
-        self.find_generic("find", query, window.into())
+        self.find_generic("find", query, None, window.into())
+    }
+
+    /// Find songs matching Query conditions, sorted by given key
+    pub fn find_sorted<W>(&mut self, query: &Query, sort: &Sort, window: W) -> Result<Vec<Song>>
+    where W: Into<Window> {
+        self.find_generic("find", query, Some(sort), window.into())
     }
 
     /// Find album art for file
+    ///
+    /// The cover is read from `cover.png`, `cover.jpg` or similar file in the song's directory,
+    /// fetching it in as many chunks as needed.
     pub fn albumart<P: ToSongPath>(&mut self, path: &P) -> Result<Vec<u8>> {
-        let mut buf = vec![];
-        loop {
-            self.run_command("albumart", (path, &*format!("{}", buf.len())))?;
-            let (_, size) = self.read_pair()?;
-            let (_, bytes) = self.read_pair()?;
-            let mut chunk = self.read_bytes(bytes.parse()?)?;
-            buf.append(&mut chunk);
-            // Read empty newline
-            let _ = self.read_line()?;
-            let result = self.read_line()?;
-            if result != "OK" {
-                return Err(ProtoError::NotOk)?;
-            }
-
-            if size.parse::<usize>()? == buf.len() {
-                break;
-            }
-        }
-        Ok(buf)
+        self.read_binary("albumart", path).map(|data| data.map(|(data, _)| data).unwrap_or_default())
     }
-    /// Read embedded album art
-    pub fn readpicture<P: ToSongPath>(&mut self, path: &P) -> Result<Vec<u8>> {
-        let mut buf = vec![];
-        loop {
-            self.run_command("readpicture", (path, &*format!("{}", buf.len())))?;
-            let (_, size) = self.read_pair()?;
-            let (_, _mime) = self.read_pair()?;
-            let (_, bytes) = self.read_pair()?;
-            let mut chunk = self.read_bytes(bytes.parse()?)?;
-            buf.append(&mut chunk);
-            // Read empty newline
-            let _ = self.read_line()?;
-            let result = self.read_line()?;
-            if result != "OK" {
-                return Err(ProtoError::NotOk)?;
-            }
 
-            if size.parse::<usize>()? == buf.len() {
-                break;
-            }
-        }
-        Ok(buf)
+    /// Read picture embedded into the song file
+    ///
+    /// Returns `None` if the file has no embedded pictures.
+    pub fn readpicture<P: ToSongPath>(&mut self, path: &P) -> Result<Option<Picture>> {
+        self.read_binary("readpicture", path).map(|data| data.map(|(data, mime)| Picture { data, mime }))
     }
 
     /// Case-insensitively search for songs matching Query conditions.
     pub fn search<W>(&mut self, query: &Query, window: W) -> Result<Vec<Song>>
     where W: Into<Window> {
-        self.find_generic("search", query, window.into())
+        self.find_generic("search", query, None, window.into())
     }
 
-    fn find_generic(&mut self, cmd: &str, query: &Query, window: Window) -> Result<Vec<Song>> {
-        self.run_command(cmd, (query, window)).and_then(|_| self.read_structs("file"))
+    /// Case-insensitively search for songs matching Query conditions, sorted by given key
+    pub fn search_sorted<W>(&mut self, query: &Query, sort: &Sort, window: W) -> Result<Vec<Song>>
+    where W: Into<Window> {
+        self.find_generic("search", query, Some(sort), window.into())
     }
 
-    /// Lists unique tags values of the specified type for songs matching the given query. For now
-    /// at most one group condition is supported.
-    // TODO: list type [filtertype] [filterwhat] [...] [group] [grouptype] [...]
-    // It isn't clear if or how `group` works
+    /// Count songs matching Query conditions and their total playtime
+    pub fn count(&mut self, query: &Query) -> Result<Count> {
+        self.run_command("count", query).and_then(|_| self.read_struct())
+    }
+
+    /// Find songs matching Query conditions, parsing them as they are received
+    pub fn find_iter<W>(&mut self, query: &Query, window: W) -> Result<StructIter<'_, S, Song>>
+    where W: Into<Window> {
+        self.run_command("find", (query, window.into()))?;
+        Ok(StructIter::new(self, &["file"]))
+    }
+
+    /// Case-insensitively search for songs matching Query conditions, parsing them as they are received
+    pub fn search_iter<W>(&mut self, query: &Query, window: W) -> Result<StructIter<'_, S, Song>>
+    where W: Into<Window> {
+        self.run_command("search", (query, window.into()))?;
+        Ok(StructIter::new(self, &["file"]))
+    }
+
+    fn find_generic(&mut self, cmd: &str, query: &Query, sort: Option<&Sort>, window: Window) -> Result<Vec<Song>> {
+        self.run_command(cmd, (query, sort, window)).and_then(|_| self.read_structs("file"))
+    }
+
+    /// Lists unique tags values of the specified type for songs matching the given query,
+    /// optionally grouped by another tag. Use [`list_groups()`](Client::list_groups) to group
+    /// by several tags.
     pub fn list(&mut self, term: &Term, query: &Query, group: Option<&str>) -> Result<GroupedValues> {
         if let Some(group) = group {
             self.run_command("list", (term, query, "group", group)).and_then(|_| GroupedValues::from_pairs_with_sep(&mut self.read_pairs(), group.to_lowercase().as_str()))
@@ -563,15 +778,53 @@ impl<S: Read + Write> Client<S> {
         }
     }
 
+    /// Lists unique tags values of the specified type, grouped by several tags
+    ///
+    /// Unlike [`list()`](Client::list), which supports a single group, this returns
+    /// the whole hierarchy, e.g. album artists, then their albums, then album titles.
+    pub fn list_groups<I>(&mut self, term: &Term, query: &Query, groups: I) -> Result<Vec<GroupNode>>
+    where I: IntoIterator, I::Item: AsRef<str> {
+        let groups: Vec<I::Item> = groups.into_iter().collect();
+        let groups: Vec<(&str, &str)> = groups.iter().map(|group| ("group", group.as_ref())).collect();
+        self.run_command("list", (term, query, groups)).and_then(|_| GroupNode::from_pairs(&mut self.read_pairs()))
+    }
+
     /// Find all songs in the db that match query and adds them to current playlist.
     pub fn findadd(&mut self, query: &Query) -> Result<()> {
-        self.run_command("findadd", query).and_then(|_| self.expect_ok())
+        self.findadd_with(query, &AddOptions::new())
+    }
+
+    /// Find all songs in the db that match query and adds them to current playlist, with given options.
+    ///
+    /// Use [`AddOptions`] to add only a part of the results, sort them or insert them at given position.
+    pub fn findadd_with(&mut self, query: &Query, options: &AddOptions) -> Result<()> {
+        self.run_command("findadd", (query, options)).and_then(|_| self.expect_ok())
+    }
+
+    /// Case-insensitively search for songs matching Query conditions and add them to current playlist.
+    pub fn searchadd(&mut self, query: &Query) -> Result<()> {
+        self.searchadd_with(query, &AddOptions::new())
+    }
+
+    /// Case-insensitively search for songs matching Query conditions and add them to current playlist, with given options.
+    ///
+    /// Use [`AddOptions`] to add only a part of the results, sort them or insert them at given position.
+    pub fn searchadd_with(&mut self, query: &Query, options: &AddOptions) -> Result<()> {
+        self.run_command("searchadd", (query, options)).and_then(|_| self.expect_ok())
+    }
+
+    /// Case-insensitively search for songs matching Query conditions and add them to a stored playlist
+    ///
+    /// The playlist is created if it doesn't exist. If position is given, the songs are
+    /// inserted at that position instead of being appended (MPD 0.23.4+).
+    pub fn search_add_to_playlist<N: ToPlaylistName>(&mut self, name: N, query: &Query, position: Option<u32>) -> Result<()> {
+        let position = position.map(|pos| ("position", pos));
+        self.run_command("searchaddpl", (name.to_name(), query, position)).and_then(|_| self.expect_ok())
     }
 
     /// Lists the contents of a directory.
     pub fn lsinfo<P: ToSongPath>(&mut self, path: P) -> Result<Vec<LsInfoEntry>> {
-        // TODO: add playlist support
-        self.run_command("lsinfo", path).and_then(|_| self.read_multisep_structs(&["file", "directory"]))
+        self.run_command("lsinfo", path).and_then(|_| self.read_multisep_structs(ENTRY_KEYS))
     }
 
     /// Returns raw metadata for file
@@ -611,20 +864,70 @@ impl<S: Read + Write> Client<S> {
     pub fn out_toggle<T: ToOutputId>(&mut self, id: T) -> Result<()> {
         self.run_command("toggleoutput", id.to_output_id()).and_then(|_| self.expect_ok())
     }
+
+    /// Set runtime attribute of given output, like `dop` or `allowed_formats` for ALSA outputs
+    pub fn output_set<T: ToOutputId, A: AsRef<str>, V: AsRef<str>>(&mut self, id: T, attribute: A, value: V) -> Result<()> {
+        self.run_command("outputset", (id.to_output_id(), attribute.as_ref(), value.as_ref())).and_then(|_| self.expect_ok())
+    }
+    // }}}
+
+    // Partition methods {{{
+    /// Switch the connection to given partition (MPD 0.22+)
+    pub fn switch_partition<N: AsRef<str>>(&mut self, name: N) -> Result<()> {
+        self.run_command("partition", name.as_ref()).and_then(|_| self.expect_ok())
+    }
+
+    /// List names of all partitions
+    pub fn partitions(&mut self) -> Result<Vec<String>> {
+        self.run_command("listpartitions", ()).and_then(|_| self.read_list("partition"))
+    }
+
+    /// Create new partition with given name
+    pub fn new_partition<N: AsRef<str>>(&mut self, name: N) -> Result<()> {
+        self.run_command("newpartition", name.as_ref()).and_then(|_| self.expect_ok())
+    }
+
+    /// Delete partition with given name
+    ///
+    /// The partition must not have any clients attached, and the default partition can't be deleted.
+    pub fn delete_partition<N: AsRef<str>>(&mut self, name: N) -> Result<()> {
+        self.run_command("delpartition", name.as_ref()).and_then(|_| self.expect_ok())
+    }
+
+    /// Run commands on given partition, then switch the connection back to the current one
+    ///
+    /// The connection is switched back even if the closure fails, and the closure's error is
+    /// returned in that case.
+    pub fn with_partition<T, N, F>(&mut self, name: N, f: F) -> Result<T>
+    where N: AsRef<str>, F: FnOnce(&mut Self) -> Result<T> {
+        let current = self.status()?.partition.unwrap_or_else(|| "default".to_owned());
+        self.switch_partition(name)?;
+        let result = f(self);
+        let restored = self.switch_partition(&current);
+        result.and_then(|value| restored.map(|_| value))
+    }
     // }}}
 
     // Reflection methods {{{
+    /// Get server configuration, only allowed for clients connected via local socket
+    pub fn config(&mut self) -> Result<Config> {
+        self.run_command("config", ()).and_then(|_| self.read_struct())
+    }
+
     /// Get current music directory
     pub fn music_directory(&mut self) -> Result<String> {
         self.run_command("config", ()).and_then(|_| self.read_field("music_directory"))
     }
 
-    /// List all available commands
+    /// List all commands available to the current connection
+    ///
+    /// The list depends on the permissions granted to the connection, e.g. some commands
+    /// may be missing until the [password](Client::login) is sent.
     pub fn commands(&mut self) -> Result<Vec<String>> {
         self.run_command("commands", ()).and_then(|_| self.read_list("command"))
     }
 
-    /// List all forbidden commands
+    /// List all commands which exist but are forbidden for the current connection
     pub fn notcommands(&mut self) -> Result<Vec<String>> {
         self.run_command("notcommands", ()).and_then(|_| self.read_list("command"))
     }
@@ -640,25 +943,105 @@ impl<S: Read + Write> Client<S> {
     }
 
     /// Enable these tag types in future responses
-    pub fn tagtypes_enable(&mut self, tagtypes: &[&str]) -> Result<()> {
+    pub fn tagtypes_enable<I>(&mut self, tagtypes: I) -> Result<()>
+    where I: IntoIterator, I::Item: AsRef<str> {
+        let tagtypes: Vec<I::Item> = tagtypes.into_iter().collect();
+        let tagtypes: Vec<&str> = tagtypes.iter().map(AsRef::as_ref).collect();
+        self.capabilities = None;
         self.run_command("tagtypes enable", tagtypes).and_then(|_| self.expect_ok())
     }
 
     /// Enable all tag types in future responses
     pub fn tagtypes_all(&mut self) -> Result<()> {
+        self.capabilities = None;
         self.run_command("tagtypes all", ()).and_then(|_| self.expect_ok())
     }
 
     /// Disable these tag types in future responses
-    pub fn tagtypes_disable(&mut self, tagtypes: &[&str]) -> Result<()> {
+    pub fn tagtypes_disable<I>(&mut self, tagtypes: I) -> Result<()>
+    where I: IntoIterator, I::Item: AsRef<str> {
+        let tagtypes: Vec<I::Item> = tagtypes.into_iter().collect();
+        let tagtypes: Vec<&str> = tagtypes.iter().map(AsRef::as_ref).collect();
+        self.capabilities = None;
         self.run_command("tagtypes disable", tagtypes).and_then(|_| self.expect_ok())
     }
 
     /// Disable all tag types in future responses
     pub fn tagtypes_clear(&mut self) -> Result<()> {
+        self.capabilities = None;
         self.run_command("tagtypes clear", ()).and_then(|_| self.expect_ok())
     }
 
+    /// Check if the server supports given feature, judging by its protocol version
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.version.supports(feature)
+    }
+
+    /// Get server capabilities for this connection, fetching them on first use
+    ///
+    /// The result is cached, and is fetched again only after the password is sent
+    /// or tag types are changed with this client.
+    pub fn capabilities(&mut self) -> Result<&ServerCapabilities> {
+        if self.capabilities.is_none() {
+            let capabilities = self.fetch_capabilities()?;
+            self.capabilities = Some(capabilities);
+        }
+        Ok(self.capabilities.as_ref().unwrap())
+    }
+
+    /// Drop cached server capabilities, so they are fetched again on next use
+    pub fn invalidate_capabilities(&mut self) {
+        self.capabilities = None;
+    }
+
+    fn fetch_capabilities(&mut self) -> Result<ServerCapabilities> {
+        let mut commands = vec!["commands", "tagtypes"];
+        if self.supports(Feature::ProtocolFeatures) {
+            commands.push("protocol available");
+        }
+        let commands: Vec<(&str, ())> = commands.into_iter().map(|command| (command, ())).collect();
+        let version = self.version;
+        self.run_command_list(&commands).and_then(|_| ServerCapabilities::from_pairs(version, self.read_pairs()))
+    }
+
+    /// List protocol features enabled for this connection (MPD 0.24+)
+    pub fn protocol(&mut self) -> Result<Vec<ProtocolFeature>> {
+        self.run_command("protocol", ()).and_then(|_| self.read_protocol_features())
+    }
+
+    /// List all protocol features supported by the server (MPD 0.24+)
+    pub fn protocol_available(&mut self) -> Result<Vec<ProtocolFeature>> {
+        self.run_command("protocol available", ()).and_then(|_| self.read_protocol_features())
+    }
+
+    /// Enable these protocol features for this connection (MPD 0.24+)
+    pub fn protocol_enable<I>(&mut self, features: I) -> Result<()>
+    where I: IntoIterator, I::Item: Borrow<ProtocolFeature> {
+        let features: Vec<ProtocolFeature> = features.into_iter().map(|feature| feature.borrow().clone()).collect();
+        self.run_command("protocol enable", features).and_then(|_| self.expect_ok())
+    }
+
+    /// Enable all available protocol features for this connection (MPD 0.24+)
+    pub fn protocol_all(&mut self) -> Result<()> {
+        self.run_command("protocol all", ()).and_then(|_| self.expect_ok())
+    }
+
+    /// Disable these protocol features for this connection (MPD 0.24+)
+    pub fn protocol_disable<I>(&mut self, features: I) -> Result<()>
+    where I: IntoIterator, I::Item: Borrow<ProtocolFeature> {
+        let features: Vec<ProtocolFeature> = features.into_iter().map(|feature| feature.borrow().clone()).collect();
+        self.run_command("protocol disable", features).and_then(|_| self.expect_ok())
+    }
+
+    /// Disable all protocol features for this connection (MPD 0.24+)
+    pub fn protocol_clear(&mut self) -> Result<()> {
+        self.run_command("protocol clear", ()).and_then(|_| self.expect_ok())
+    }
+
+    fn read_protocol_features(&mut self) -> Result<Vec<ProtocolFeature>> {
+        self.read_list("feature").and_then(|v| v.into_iter().map(|f| f.parse().map_err(From::from)).collect())
+    }
+
     /// List all available decoder plugins
     pub fn decoders(&mut self) -> Result<Vec<Plugin>> {
         self.run_command("decoders", ()).and_then(|_| self.read_struct())
@@ -678,9 +1061,23 @@ impl<S: Read + Write> Client<S> {
         self.run_command("readmessages", ()).and_then(|_| self.read_structs("channel"))
     }
 
+    /// Read queued messages from subscribed channels, grouped by channel
+    ///
+    /// Channels are listed in order of their first message, and messages keep their order within each channel.
+    pub fn readmessages_by_channel(&mut self) -> Result<Vec<(Channel, Vec<Message>)>> {
+        let mut grouped: Vec<(Channel, Vec<Message>)> = Vec::new();
+        for message in self.readmessages()? {
+            match grouped.iter_mut().find(|(channel, _)| *channel == message.channel) {
+                Some((_, messages)) => messages.push(message),
+                None => grouped.push((message.channel.clone(), vec![message])),
+            }
+        }
+        Ok(grouped)
+    }
+
     /// Send a message to a channel
-    pub fn sendmessage(&mut self, channel: &Channel, message: &str) -> Result<()> {
-        self.run_command("sendmessage", (channel, message)).and_then(|_| self.expect_ok())
+    pub fn sendmessage<M: AsRef<str>>(&mut self, channel: &Channel, message: M) -> Result<()> {
+        self.run_command("sendmessage", (channel, message.as_ref())).and_then(|_| self.expect_ok())
     }
 
     /// Subscribe to a channel
@@ -692,6 +1089,12 @@ impl<S: Read + Write> Client<S> {
     pub fn unsubscribe(&mut self, channel: &Channel) -> Result<()> {
         self.run_command("unsubscribe", channel).and_then(|_| self.expect_ok())
     }
+
+    /// Subscribe to a channel until the returned guard is dropped
+    pub fn subscription(&mut self, channel: &Channel) -> Result<Subscription<'_, S>> {
+        self.subscribe(channel)?;
+        Ok(Subscription { client: self, channel: channel.clone() })
+    }
     // }}}
 
     // Mount methods {{{
@@ -707,87 +1110,88 @@ impl<S: Read + Write> Client<S> {
         self.run_command("listneighbors", ()).and_then(|_| self.read_structs("neighbor"))
     }
 
-    /// Mount given neighbor to a mount point
+    /// Mount given neighbor or storage URI to a mount point
     ///
+    /// The URI is either a string or a [`Storage`](crate::Storage) with mount parameters.
     /// The mount exists inside MPD process only, thus it can work without root permissions.
-    pub fn mount(&mut self, path: &str, uri: &str) -> Result<()> {
-        self.run_command("mount", (path, uri)).and_then(|_| self.expect_ok())
+    pub fn mount<P: AsRef<str>, U: ToStorageUri>(&mut self, path: P, uri: U) -> Result<()> {
+        self.run_command("mount", (path.as_ref(), &*uri.to_storage_uri())).and_then(|_| self.expect_ok())
     }
 
     /// Unmount given active (virtual) mount
     ///
     /// The mount exists inside MPD process only, thus it can work without root permissions.
-    pub fn unmount(&mut self, path: &str) -> Result<()> {
-        self.run_command("unmount", path).and_then(|_| self.expect_ok())
+    pub fn unmount<P: AsRef<str>>(&mut self, path: P) -> Result<()> {
+        self.run_command("unmount", path.as_ref()).and_then(|_| self.expect_ok())
     }
     // }}}
 
     // Sticker methods {{{
     /// Show sticker value for a given object, identified by type and uri
-    pub fn sticker(&mut self, typ: &str, uri: &str, name: &str) -> Result<String> {
-        self.run_command("sticker get", (typ, uri, name))
-            // TODO: This should parse to a `Sticker` type.
+    pub fn sticker<T: AsRef<str>, U: AsRef<str>, N: AsRef<str>>(&mut self, typ: T, uri: U, name: N) -> Result<String> {
+        self.run_command("sticker get", (typ.as_ref(), uri.as_ref(), name.as_ref()))
             .and_then(|_| self.read_field::<Sticker>("sticker"))
             .map(|s| s.value)
     }
 
     /// Set sticker value for a given object, identified by type and uri
-    pub fn set_sticker(&mut self, typ: &str, uri: &str, name: &str, value: &str) -> Result<()> {
-        self.run_command("sticker set", (typ, uri, name, value)).and_then(|_| self.expect_ok())
+    pub fn set_sticker<T, U, N, V>(&mut self, typ: T, uri: U, name: N, value: V) -> Result<()>
+    where T: AsRef<str>, U: AsRef<str>, N: AsRef<str>, V: AsRef<str> {
+        self.run_command("sticker set", (typ.as_ref(), uri.as_ref(), name.as_ref(), value.as_ref())).and_then(|_| self.expect_ok())
     }
 
     /// Delete sticker from a given object, identified by type and uri
-    pub fn delete_sticker(&mut self, typ: &str, uri: &str, name: &str) -> Result<()> {
-        self.run_command("sticker delete", (typ, uri, name)).and_then(|_| self.expect_ok())
+    pub fn delete_sticker<T: AsRef<str>, U: AsRef<str>, N: AsRef<str>>(&mut self, typ: T, uri: U, name: N) -> Result<()> {
+        self.run_command("sticker delete", (typ.as_ref(), uri.as_ref(), name.as_ref())).and_then(|_| self.expect_ok())
     }
 
     /// Remove all stickers from a given object, identified by type and uri
-    pub fn clear_stickers(&mut self, typ: &str, uri: &str) -> Result<()> {
-        self.run_command("sticker delete", (typ, uri)).and_then(|_| self.expect_ok())
+    pub fn clear_stickers<T: AsRef<str>, U: AsRef<str>>(&mut self, typ: T, uri: U) -> Result<()> {
+        self.run_command("sticker delete", (typ.as_ref(), uri.as_ref())).and_then(|_| self.expect_ok())
     }
 
     /// List all stickers from a given object, identified by type and uri
-    pub fn stickers(&mut self, typ: &str, uri: &str) -> Result<Vec<(String, String)>> {
-        self.run_command("sticker list", (typ, uri))
+    pub fn stickers<T: AsRef<str>, U: AsRef<str>>(&mut self, typ: T, uri: U) -> Result<Vec<(String, String)>> {
+        self.sticker_list(typ, uri).map(|v| v.into_iter().map(|s| (s.name, s.value)).collect())
+    }
+
+    /// List all stickers from a given object as [`Sticker`]s, identified by type and uri
+    ///
+    /// Stickers are listed in the order the server reports them. Use
+    /// [`stickers_map()`](Client::stickers_map) to get them as a map from name to value.
+    pub fn sticker_list<T: AsRef<str>, U: AsRef<str>>(&mut self, typ: T, uri: U) -> Result<Vec<Sticker>> {
+        self.run_command("sticker list", (typ.as_ref(), uri.as_ref()))
             .and_then(|_| self.read_list("sticker"))
-            .map(|v| v.into_iter().map(|b| b.split_once('=').map(|x| (x.0.to_owned(), x.1.to_owned())).unwrap()).collect())
+            .and_then(|v| v.iter().map(|s| s.parse().map_err(Error::Parse)).collect())
     }
 
     /// List all stickers from a given object in a map, identified by type and uri
-    pub fn stickers_map(&mut self, typ: &str, uri: &str) -> Result<HashMap<String, String>> {
-        self.run_command("sticker list", (typ, uri)).and_then(|_| self.read_list("sticker")).map(|v| {
-            v.into_iter()
-                .map(|b| {
-                    let mut iter = b.splitn(2, '=');
-
-                    (iter.next().unwrap().to_owned(), iter.next().unwrap().to_owned())
-                })
-                .collect()
-        })
+    pub fn stickers_map<T: AsRef<str>, U: AsRef<str>>(&mut self, typ: T, uri: U) -> Result<HashMap<String, String>> {
+        self.stickers(typ, uri).map(|v| v.into_iter().collect())
     }
 
-    /// List all (file, sticker) pairs for sticker name and objects of given type
+    /// List all (file, sticker value) pairs for sticker name and objects of given type
     /// from given directory (identified by uri)
-    pub fn find_sticker<W: Into<Window>>(&mut self, typ: &str, uri: &str, name: &str, window: W) -> Result<Vec<(String, String)>> {
-        let lower_typ = typ.to_lowercase();
+    pub fn find_sticker<T, U, N, W>(&mut self, typ: T, uri: U, name: N, window: W) -> Result<Vec<(String, String)>>
+    where T: AsRef<str>, U: AsRef<str>, N: AsRef<str>, W: Into<Window> {
+        let lower_typ = typ.as_ref().to_lowercase();
         let delim = if &lower_typ == "song" {
             &String::from("file")
         } else {
             &lower_typ
         };
-        self.run_command("sticker find", (&lower_typ, uri, name, window.into())).and_then(|_| {
+        self.run_command("sticker find", (&lower_typ, uri.as_ref(), name.as_ref(), window.into())).and_then(|_| {
             self.read_pairs()
                 .split(delim)
                 .map(|rmap| {
-                    rmap.map(|map| {
-                        (
-                            map.iter().find_map(|(k, v)| if &k.to_lowercase() == delim { Some(v.to_owned()) } else { None }).unwrap(),
-                            map.iter()
-                                .find_map(|(k, v)| if k == "sticker" { Some(v.to_owned()) } else { None })
-                                .and_then(|s| s.split_once('=').map(|x| x.1.to_owned()))
-                                .unwrap(),
-                        )
-                    })
+                    let map = rmap?;
+                    let uri = map.iter().find(|(k, _)| k.eq_ignore_ascii_case(delim)).map(|(_, v)| v.to_owned());
+                    let sticker = map.iter().find(|(k, _)| k == "sticker").map(|(_, v)| v.parse::<Sticker>()).transpose()?;
+                    match (uri, sticker) {
+                        (Some(uri), Some(sticker)) => Ok((uri, sticker.value)),
+                        (None, _) => Err(Error::Proto(ProtoError::NoField("file"))),
+                        (_, None) => Err(Error::Proto(ProtoError::NoField("sticker"))),
+                    }
                 })
                 .collect()
         })
@@ -795,33 +1199,47 @@ impl<S: Read + Write> Client<S> {
 
     /// List all files of a given type under given directory (identified by uri)
     /// with a sticker set to given value
-    pub fn find_sticker_eq<W: Into<Window>>(&mut self, typ: &str, uri: &str, name: &str, value: &str, window: W) -> Result<Vec<String>> {
-        let lower_typ = typ.to_lowercase();
-        let delim = if &lower_typ == "song" {
-            String::from("file")
-        } else {
-            lower_typ
-        };
-        self.run_command("sticker find", (typ, uri, name, "=", value, window.into())).and_then(|_| self.read_list(&delim))
+    pub fn find_sticker_eq<T, U, N, V, W>(&mut self, typ: T, uri: U, name: N, value: V, window: W) -> Result<Vec<String>>
+    where T: AsRef<str>, U: AsRef<str>, N: AsRef<str>, V: AsRef<str>, W: Into<Window> {
+        self.find_sticker_op(typ, uri, name, StickerOp::Eq, value, window)
     }
 
     /// List all files of a given type under given directory (identified by uri)
     /// with a sticker matching a certain condition. This is more general than
     /// find_sticker_eq in that it allows for operators other than "=".
-    pub fn find_sticker_op<W: Into<Window>>(&mut self, typ: &str, uri: &str, name: &str, op: &str, value: &str, window: W) -> Result<Vec<String>> {
-        let lower_typ = typ.to_lowercase();
+    ///
+    /// The operator is either a [`StickerOp`] or its protocol form as a string, like `">"`.
+    pub fn find_sticker_op<T, U, N, O, V, W>(&mut self, typ: T, uri: U, name: N, op: O, value: V, window: W) -> Result<Vec<String>>
+    where T: AsRef<str>, U: AsRef<str>, N: AsRef<str>, O: ToArguments, V: AsRef<str>, W: Into<Window> {
+        let lower_typ = typ.as_ref().to_lowercase();
         let delim = if &lower_typ == "song" {
             String::from("file")
         } else {
             lower_typ
         };
-        self.run_command("sticker find", (typ, uri, name, op, value, window.into())).and_then(|_| self.read_list(&delim))
+        self.run_command("sticker find", (typ.as_ref(), uri.as_ref(), name.as_ref(), op, value.as_ref(), window.into())).and_then(|_| self.read_list(&delim))
+    }
+
+    /// Adds a sticker value to the specified object. If a sticker item with
+    /// that name already exists, it is incremented by supplied value (MPD 0.24+).
+    ///
+    /// The change is done by the server atomically, so it's safe for counters
+    /// updated by several clients, like play counts.
+    pub fn sticker_inc<T: AsRef<str>, U: AsRef<str>, N: AsRef<str>>(&mut self, typ: T, uri: U, name: N, by: u32) -> Result<()> {
+        self.run_command("sticker inc", (typ.as_ref(), uri.as_ref(), name.as_ref(), by)).and_then(|_| self.expect_ok())
+    }
+
+    /// Adds a sticker value to the specified object. If a sticker item with
+    /// that name already exists, it is decremented by supplied value (MPD 0.24+).
+    pub fn sticker_dec<T: AsRef<str>, U: AsRef<str>, N: AsRef<str>>(&mut self, typ: T, uri: U, name: N, by: u32) -> Result<()> {
+        self.run_command("sticker dec", (typ.as_ref(), uri.as_ref(), name.as_ref(), by)).and_then(|_| self.expect_ok())
     }
 
     /// Adds a sticker value to the specified object. If a sticker item with
     /// that name already exists, it is incremented by supplied value.
     ///
     /// `value` should be numeric, formatted as a string.
+    #[deprecated(note = "use `sticker_inc()` taking a number instead")]
     pub fn inc_sticker(&mut self, typ: &str, uri: &str, name: &str, value: &str) -> Result<()> {
         self.run_command("sticker inc", (typ, uri, name, value)).and_then(|_| self.expect_ok())
     }
@@ -830,11 +1248,56 @@ impl<S: Read + Write> Client<S> {
     /// that name already exists, it is decremented by supplied value.
     ///
     /// `value` should be numeric, formatted as a string.
+    #[deprecated(note = "use `sticker_dec()` taking a number instead")]
     pub fn dec_sticker(&mut self, typ: &str, uri: &str, name: &str, value: &str) -> Result<()> {
         self.run_command("sticker dec", (typ, uri, name, value)).and_then(|_| self.expect_ok())
     }
 
     // }}}
+
+    // Raw commands {{{
+    /// Run any command, including ones not wrapped by the client yet, and return response pairs
+    ///
+    /// Arguments are quoted the same way as for other commands, so e.g. a tuple of strings
+    /// and numbers can be passed. Commands with binary responses (like `albumart`) are not supported.
+    pub fn command_raw<A: ToArguments>(&mut self, command: &str, arguments: A) -> Result<Vec<(String, String)>> {
+        self.run_command(command, arguments).and_then(|_| self.read_pairs().collect())
+    }
+
+    /// Run any command and parse the response into a structure, like [`Song`] or [`Status`]
+    pub fn command_as<T: FromIter, A: ToArguments>(&mut self, command: &str, arguments: A) -> Result<T> {
+        self.run_command(command, arguments).and_then(|_| self.read_struct())
+    }
+
+    /// Run a list of arbitrary commands at once, returning reply to each command separately
+    ///
+    /// See [`command_list`](crate::command_list) module for details.
+    pub fn command_list(&mut self, list: &CommandList) -> Result<Vec<CommandReply>> {
+        if list.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.track_command("command_list_ok_begin");
+        trace::sent(&self.span, "command_list_ok_begin", &());
+        for line in list.encoded().lines() {
+            trace::sent(&self.span, line, &());
+        }
+        trace::sent(&self.span, "command_list_end", &());
+        self.socket
+            .write_all(b"command_list_ok_begin\n")
+            .and_then(|_| self.socket.write_all(list.encoded().as_bytes()))
+            .and_then(|_| self.socket.write_all(b"command_list_end\n"))
+            .and_then(|_| self.socket.flush())
+            .map_err(|e| self.io_error(e))?;
+
+        let mut replies = Vec::with_capacity(list.len());
+        for _ in 0..list.len() {
+            // Each reply is terminated by `list_OK`
+            replies.push(CommandReply(self.read_pairs().collect::<Result<_>>()?));
+        }
+        self.expect_ok().map(|_| replies)
+    }
+    // }}}
 }
 
 // Helper methods {{{
@@ -844,23 +1307,50 @@ impl<S: Read + Write> Proto for Client<S> {
     fn read_bytes(&mut self, bytes: usize) -> Result<Vec<u8>> {
         let mut buf = Vec::with_capacity(bytes);
         let mut chunk = (&mut self.socket).take(bytes as u64);
-        chunk.read_to_end(&mut buf)?;
+        chunk.read_to_end(&mut buf).map_err(|e| self.io_error(e))?;
+        trace::received_binary(&self.span, buf.len());
         Ok(buf)
     }
 
-    fn read_line(&mut self) -> Result<String> {
-        let mut buf = Vec::new();
-        self.socket.read_until(b'\n', &mut buf)?;
-        if buf.ends_with(&[b'\n']) {
-            buf.pop();
+    fn read_line(&mut self) -> Result<&str> {
+        match read_line_into(&mut self.socket, &mut self.line) {
+            Ok(Some(line)) => {
+                trace::received(&self.span, line);
+                Ok(line)
+            }
+            Ok(None) => Err(Error::Io(CommandIoError::wrap(
+                &self.command,
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "connection closed by server"),
+            ))),
+            Err(e) => Err(Error::Io(CommandIoError::wrap(&self.command, e))),
+        }
+    }
+
+    fn read_response(&mut self) -> Result<ResponsePairs<'_>> {
+        self.response.clear();
+        self.response.shrink_to(RESPONSE_CAPACITY);
+        loop {
+            let start = self.response.len();
+            if self.socket.read_line(&mut self.response).map_err(|e| self.io_error(e))? == 0 {
+                return Err(self.io_error(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "connection closed by server")));
+            }
+            let line = self.response[start..].trim_end_matches('\n');
+            trace::received(&self.span, line);
+            if line == "OK" || line == "list_OK" || line.starts_with("ACK ") {
+                return Ok(ResponsePairs::new(&self.response).lenient(self.warnings.as_mut()));
+            }
         }
-        let str = String::from_utf8(buf)
-            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))?;
-        Ok(str)
     }
 
-    fn read_pairs(&mut self) -> Pairs<Lines<&mut BufStream<S>>> {
-        Pairs((&mut self.socket).lines())
+    fn read_pairs(&mut self) -> Pairs<CommandLines<'_, S>> {
+        Pairs::new(CommandLines {
+            socket: &mut self.socket,
+            line: &mut self.line,
+            command: &self.command,
+            span: &self.span,
+            warnings: self.warnings.as_mut(),
+            done: false,
+        })
     }
 
     // fn read_pair(&mut self) -> Result<(String, String)> {
@@ -873,31 +1363,101 @@ impl<S: Read + Write> Proto for Client<S> {
 
     fn run_command_list<I>(&mut self, commands_args: &[(&str, I)]) -> Result<()>
     where I: ToArguments {
-        self.socket.write_all("command_list_begin".as_bytes())
+        self.write_command_list("command_list_begin", commands_args)
+    }
+
+    fn run_command_list_ok<I>(&mut self, commands_args: &[(&str, I)]) -> Result<()>
+    where I: ToArguments {
+        self.write_command_list("command_list_ok_begin", commands_args)
+    }
+
+    fn run_command<I>(&mut self, command: &str, arguments: I) -> Result<()>
+    where I: ToArguments {
+        self.track_command(command);
+        trace::sent(&self.span, command, &arguments);
+        self.socket
+            .write_all(command.as_bytes())
+            .and_then(|_| arguments.to_arguments(&mut |arg| write!(self.socket, " {}", Quoted(arg))))
+            .and_then(|_| self.socket.write(&[0x0a]))
+            .and_then(|_| self.socket.flush())
+            .map_err(|e| self.io_error(e))
+    }
+}
+
+impl<S: Read + Write + Nonblocking> Client<S> {
+    /// Wait for some response data up to the given timeout, zero timeout means no waiting at all
+    pub(crate) fn wait_for_data(&mut self, timeout: Duration) -> Result<bool> {
+        let stream = self.socket.get_ref();
+        let previous = if timeout.is_zero() {
+            stream.set_nonblocking(true).map(|_| None)
+        } else {
+            stream.read_timeout().and_then(|previous| stream.set_read_timeout(Some(timeout)).map(|_| Some(previous)))
+        }
+        .map_err(|e| self.io_error(e))?;
+
+        let ready = match self.socket.fill_buf() {
+            // Empty buffer means the connection is closed, which is reported by the next read
+            Ok(_) => Ok(true),
+            Err(ref e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => Ok(false),
+            Err(e) => Err(e),
+        };
+
+        let stream = self.socket.get_ref();
+        let restored = match previous {
+            None => stream.set_nonblocking(false),
+            Some(previous) => stream.set_read_timeout(previous),
+        };
+        ready.and_then(|ready| restored.map(|_| ready)).map_err(|e| self.io_error(e))
+    }
+}
+
+#[cfg(unix)]
+impl<S: Read + Write + std::os::unix::io::AsRawFd> std::os::unix::io::AsRawFd for Client<S> {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.socket.get_ref().as_raw_fd()
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Remember the command for error reporting and tracing
+    ///
+    /// Command lists are tracked by their begin command only, as MPD reports
+    /// the index of the failed command itself.
+    fn track_command(&mut self, command: &str) {
+        self.command.clear();
+        self.command.push_str(command);
+        self.span = trace::command_span(command);
+    }
+
+    fn io_error(&self, e: std::io::Error) -> Error {
+        Error::Io(CommandIoError::wrap(&self.command, e))
+    }
+
+    fn write_command_list<I>(&mut self, begin: &str, commands_args: &[(&str, I)]) -> Result<()>
+    where I: ToArguments {
+        self.track_command(begin);
+        self.write_command_list_inner(begin, commands_args).map_err(|e| self.io_error(e))
+    }
+
+    fn write_command_list_inner<I>(&mut self, begin: &str, commands_args: &[(&str, I)]) -> std::io::Result<()>
+    where I: ToArguments {
+        trace::sent(&self.span, begin, &());
+        self.socket.write_all(begin.as_bytes())
             .and_then(|_| self.socket.write(&[0x0a]))
             .and_then(|_| self.socket.flush())?;
 
         for tup in commands_args {
+            trace::sent(&self.span, tup.0, &tup.1);
             self.socket
                 .write_all(tup.0.as_bytes())
                 .and_then(|_| tup.1.to_arguments(&mut |arg| write!(self.socket, " {}", Quoted(arg))))
                 .and_then(|_| self.socket.write(&[0x0a]))
                 .and_then(|_| self.socket.flush())?;
         }
+        trace::sent(&self.span, "command_list_end", &());
         self.socket.write_all("command_list_end".as_bytes())
             .and_then(|_| self.socket.write(&[0x0a]))
             .and_then(|_| self.socket.flush())
-            .map_err(From::from)
-    }
-
-    fn run_command<I>(&mut self, command: &str, arguments: I) -> Result<()>
-    where I: ToArguments {
-        self.socket
-            .write_all(command.as_bytes())
-            .and_then(|_| arguments.to_arguments(&mut |arg| write!(self.socket, " {}", Quoted(arg))))
-            .and_then(|_| self.socket.write(&[0x0a]))
-            .and_then(|_| self.socket.flush())
-            .map_err(From::from)
     }
 }
 // }}}
@@ -0,0 +1,98 @@
+//! This module exposes MPD protocol encoding and parsing without any I/O
+//!
+//! It's meant for environments where [`Client`](crate::Client) can't own a blocking
+//! stream, like `wasm32-unknown-unknown` frontends talking to MPD through
+//! a WebSocket-to-TCP bridge: the caller sends encoded commands over its own
+//! transport, collects response lines until [`is_response_end()`] returns `true`,
+//! and then parses collected text with one of `parse_*` functions.
+//!
+//! ```rust
+//! use mpd::codec;
+//! use mpd::Status;
+//!
+//! assert_eq!(codec::encode_command("setvol", 50u8), "setvol \"50\"\n");
+//!
+//! let response = "volume: 50\nstate: play\nOK\n";
+//! assert!(response.lines().last().is_some_and(codec::is_response_end));
+//! let status: Status = codec::parse_response(response).unwrap();
+//! assert_eq!(status.volume, 50);
+//! ```
+
+use crate::convert::FromIter;
+use crate::error::{Error, ParseError, ProtoError, Result};
+use crate::proto::{Pairs, Quoted, ResponsePairs, ToArguments};
+use crate::version::Version;
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Encode command with arguments into a protocol line (with trailing newline)
+pub fn encode_command<A: ToArguments>(command: &str, arguments: A) -> String {
+    let mut line = String::new();
+    push_command(&mut line, command, &arguments);
+    line
+}
+
+/// Encode a command list, which is executed by the server as a whole, with single `OK` at the end
+pub fn encode_command_list<A: ToArguments>(commands: &[(&str, A)]) -> String {
+    let mut list = String::from("command_list_begin\n");
+    for (command, arguments) in commands {
+        push_command(&mut list, command, arguments);
+    }
+    list.push_str("command_list_end\n");
+    list
+}
+
+/// Parse server greeting line (`OK MPD x.y.z`), returning protocol version
+pub fn parse_banner(line: &str) -> Result<Version> {
+    match line.strip_prefix("OK MPD ") {
+        Some(version) => version.trim().parse().map_err(From::from),
+        None => Err(Error::Proto(ProtoError::BadBanner)),
+    }
+}
+
+/// Check if the line terminates a command response (either with success or with an error)
+pub fn is_response_end(line: &str) -> bool {
+    line == "OK" || line.starts_with("ACK ")
+}
+
+/// Parse a complete response into a single structure, like [`Status`](crate::Status)
+pub fn parse_response<T: FromIter>(response: &str) -> Result<T> {
+    FromIter::from_iter(ResponsePairs::new(response))
+}
+
+/// Parse a complete response into a list of structures, each starting with `key` field
+/// (e.g. `file` for songs)
+pub fn parse_structs<T: FromIter>(response: &str, key: &str) -> Result<Vec<T>> {
+    ResponsePairs::new(response).parse_structs(&[key])
+}
+
+/// Parse a complete response into a list of values of fields named `key`
+pub fn parse_list(response: &str, key: &str) -> Result<Vec<String>> {
+    pairs(response).filter(|r| r.as_ref().map(|(a, _)| a.eq_ignore_ascii_case(key)).unwrap_or(true)).map(|r| r.map(|(_, b)| b)).collect()
+}
+
+/// Parse a complete response consisting of a single field named `key`
+pub fn parse_field<T: FromStr>(response: &str, key: &'static str) -> Result<T>
+where ParseError: From<T::Err> {
+    match pairs(response).next() {
+        Some(Ok((a, b))) if a == key => b.parse::<T>().map_err(|e| Error::Parse(e.into())),
+        Some(Err(e)) => Err(e),
+        _ => Err(Error::Proto(ProtoError::NoField(key))),
+    }
+}
+
+/// Parse a complete response expecting no data, only `OK`
+pub fn parse_ok(response: &str) -> Result<()> {
+    pairs(response).next().map_or(Ok(()), |r| r.and(Err(Error::Proto(ProtoError::NotOk))))
+}
+
+pub(crate) fn push_command<A: ToArguments>(buf: &mut String, command: &str, arguments: &A) {
+    buf.push_str(command);
+    let _ = arguments.to_arguments::<_, std::fmt::Error>(&mut |arg| write!(buf, " {}", Quoted(arg)));
+    buf.push('\n');
+}
+
+fn pairs(response: &str) -> Pairs<std::str::Lines<'_>> {
+    Pairs::new(response.lines())
+}
@@ -0,0 +1,129 @@
+//! The module defines command lists with separate replies for each command
+//!
+//! [`CommandList`] collects arbitrary commands, which are then sent to the server
+//! at once with [`command_list()`](crate::Client::command_list). The server runs them
+//! as a whole, and the reply to each command is returned separately as [`CommandReply`],
+//! which can be parsed into a corresponding structure.
+//!
+//! If any command fails, the rest of the list is not executed, and the returned
+//! [`ServerError`](crate::error::ServerError) has `pos` set to the index of failed command.
+//!
+//! ```rust,no_run
+//! # use mpd::{Client, Song, Status};
+//! # use mpd::command_list::CommandList;
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let mut list = CommandList::new();
+//! list.push("setvol", 50u8).push("status", ()).push("currentsong", ());
+//! let mut replies = conn.command_list(&list).unwrap().into_iter().skip(1);
+//! let status: Status = replies.next().unwrap().parse().unwrap();
+//! let song: Song = replies.next().unwrap().parse().unwrap();
+//! ```
+
+use crate::codec;
+use crate::convert::FromIter;
+use crate::error::{Error, ParseError, ProtoError, Result};
+use crate::proto::ToArguments;
+
+use std::str::FromStr;
+
+/// List of commands to be run at once
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandList {
+    encoded: String,
+    len: usize,
+}
+
+impl CommandList {
+    /// Create an empty command list
+    pub fn new() -> CommandList {
+        CommandList::default()
+    }
+
+    /// Add a command with arguments to the list
+    pub fn push<A: ToArguments>(&mut self, command: &str, arguments: A) -> &mut CommandList {
+        codec::push_command(&mut self.encoded, command, &arguments);
+        self.len += 1;
+        self
+    }
+
+    /// Number of commands in the list
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the list has no commands
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Encoded commands, one per line, without list framing
+    pub(crate) fn encoded(&self) -> &str {
+        &self.encoded
+    }
+}
+
+/// Reply to a single command from a command list
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandReply(pub Vec<(String, String)>);
+
+impl CommandReply {
+    /// Parse the reply into a single structure, like [`Status`](crate::Status)
+    pub fn parse<T: FromIter>(self) -> Result<T> {
+        FromIter::from_iter(self.0.into_iter().map(Ok))
+    }
+
+    /// Parse the reply into a list of structures, each starting with `key` field
+    /// (e.g. `file` for songs)
+    pub fn parse_structs<T: FromIter>(self, key: &str) -> Result<Vec<T>> {
+        let mut result = Vec::new();
+        let mut current: Vec<(String, String)> = Vec::new();
+        for (k, v) in self.0 {
+            if k.eq_ignore_ascii_case(key) && !current.is_empty() {
+                result.push(FromIter::from_iter(current.drain(..).map(Ok))?);
+            }
+            current.push((k, v));
+        }
+        if !current.is_empty() {
+            result.push(FromIter::from_iter(current.into_iter().map(Ok))?);
+        }
+        Ok(result)
+    }
+
+    /// Parse the value of the first field named `key`
+    pub fn field<T: FromStr>(&self, key: &'static str) -> Result<T>
+    where ParseError: From<T::Err> {
+        match self.0.iter().find(|(k, _)| k == key) {
+            Some((_, v)) => v.parse().map_err(|e| Error::Parse(ParseError::from(e))),
+            None => Err(Error::Proto(ProtoError::NoField(key))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::song::Song;
+
+    #[test]
+    fn encode() {
+        let mut list = CommandList::new();
+        list.push("setvol", 50u8).push("find", ("(Artist == \"A\")", "window", "0:2"));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.encoded(), "setvol \"50\"\nfind \"(Artist == \\\"A\\\")\" \"window\" \"0:2\"\n");
+    }
+
+    #[test]
+    fn reply_parse() {
+        let reply = CommandReply(vec![
+            ("file".into(), "a.flac".into()),
+            ("Title".into(), "A".into()),
+            ("file".into(), "b.flac".into()),
+            ("Id".into(), "3".into()),
+        ]);
+        assert_eq!(reply.field::<u32>("Id").unwrap(), 3);
+        let songs: Vec<Song> = reply.parse_structs("file").unwrap();
+        assert_eq!(songs.len(), 2);
+        assert_eq!(songs[0].title.as_deref(), Some("A"));
+        assert_eq!(songs[1].file, "b.flac");
+    }
+}
@@ -0,0 +1,37 @@
+//! The module defines server configuration data structures
+//!
+//! The configuration is only available to clients connected via local socket,
+//! and is returned by [`config()`](crate::Client::config) method.
+
+use crate::convert::{FromIter, Text};
+use crate::error::Error;
+
+/// Server configuration values exposed to local clients
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Config {
+    /// absolute path to the music directory
+    pub music_directory: Option<String>,
+    /// absolute path to the playlist directory
+    pub playlist_directory: Option<String>,
+    /// whether the server supports regular expressions in filters
+    pub pcre: bool,
+}
+
+impl FromIter for Config {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Config, Error> {
+        let mut result = Config::default();
+
+        for res in iter {
+            let line = res?;
+            match line.0.as_ref() {
+                "music_directory" => result.music_directory = Some(line.1.into()),
+                "playlist_directory" => result.playlist_directory = Some(line.1.into()),
+                "pcre" => result.pcre = line.1.as_ref() == "1",
+                _ => (),
+            }
+        }
+
+        Ok(result)
+    }
+}
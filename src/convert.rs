@@ -7,7 +7,7 @@ use crate::playlist::Playlist;
 use crate::proto::ToArguments;
 use crate::song::{self, Id, Song};
 use std::collections::BTreeMap;
-use std::ops::{Range, RangeFrom, RangeFull, RangeTo};
+use std::ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive};
 
 use std::time::Duration;
 
@@ -16,17 +16,29 @@ pub trait FromMap: Sized {
     fn from_map(map: BTreeMap<String, String>) -> Result<Self, Error>;
 }
 
+/// Field name or value in a response pair, either borrowed from the response or owned
+#[doc(hidden)]
+pub trait Text: AsRef<str> + Into<String> {}
+
+impl<T: AsRef<str> + Into<String>> Text for T {}
+
 #[doc(hidden)]
 pub trait FromIter: Sized {
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Self, Error>;
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Self, Error>;
 }
 
 impl<T: FromMap> FromIter for T {
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Self, Error> {
-        iter.collect::<Result<BTreeMap<_, _>, _>>().and_then(FromMap::from_map)
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Self, Error> {
+        iter.map(|res| res.map(|(k, v)| (k.into(), v.into()))).collect::<Result<BTreeMap<_, _>, _>>().and_then(FromMap::from_map)
     }
 }
 
+/// Parse ISO 8601 timestamp as sent by MPD, e.g. `2024-01-31T12:00:00Z`
+#[cfg(feature = "chrono")]
+pub(crate) fn parse_timestamp(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&chrono::Utc))
+}
+
 // Playlist name polymorphisms {{{
 pub trait ToPlaylistName {
     fn to_name(&self) -> &str;
@@ -38,19 +50,19 @@ impl ToPlaylistName for Playlist {
     }
 }
 
-impl<'a> ToPlaylistName for &'a Playlist {
+impl ToPlaylistName for &Playlist {
     fn to_name(&self) -> &str {
         &self.name
     }
 }
 
-impl<'a> ToPlaylistName for &'a String {
+impl ToPlaylistName for &String {
     fn to_name(&self) -> &str {
         self
     }
 }
 
-impl<'a> ToPlaylistName for &'a str {
+impl ToPlaylistName for &str {
     fn to_name(&self) -> &str {
         self
     }
@@ -139,6 +151,37 @@ impl ToQueueRangeOrPlace for RangeTo<u32> {
     }
 }
 
+impl ToQueueRange for RangeInclusive<u32> {
+    fn to_range(self) -> String {
+        // The last possible position can only be included by an open range
+        match self.end().checked_add(1) {
+            Some(end) => format!("{}:{}", self.start(), end),
+            None => format!("{}:", self.start()),
+        }
+    }
+}
+
+impl ToQueueRangeOrPlace for RangeInclusive<u32> {
+    fn to_range(self) -> String {
+        ToQueueRange::to_range(self)
+    }
+}
+
+impl ToQueueRange for RangeToInclusive<u32> {
+    fn to_range(self) -> String {
+        match self.end.checked_add(1) {
+            Some(end) => format!(":{}", end),
+            None => ToQueueRange::to_range(..),
+        }
+    }
+}
+
+impl ToQueueRangeOrPlace for RangeToInclusive<u32> {
+    fn to_range(self) -> String {
+        ToQueueRange::to_range(self)
+    }
+}
+
 impl ToQueueRange for RangeFrom<u32> {
     fn to_range(self) -> String {
         format!("{}:", self.start)
@@ -183,6 +226,8 @@ impl IsId for u32 {}
 impl IsId for Range<u32> {}
 impl IsId for RangeTo<u32> {}
 impl IsId for RangeFrom<u32> {}
+impl IsId for RangeInclusive<u32> {}
+impl IsId for RangeToInclusive<u32> {}
 impl IsId for RangeFull {}
 impl IsId for Id {
     fn is_id() -> bool {
@@ -228,6 +273,11 @@ impl ToOutputId for Output {
         self.id
     }
 }
+impl ToOutputId for &Output {
+    fn to_output_id(self) -> u32 {
+        self.id
+    }
+}
 // }}}
 
 // Song play range polymorphisms {{{
@@ -301,7 +351,7 @@ impl ToSongPath for Song {
     }
 }
 
-impl<'a, T: ToSongPath> ToSongPath for &'a T {
+impl<T: ToSongPath> ToSongPath for &T {
     fn to_song_path(&self) -> &str {
         (*self).to_song_path()
     }
@@ -322,7 +372,7 @@ impl<T: ToSongPath> ToArguments for T {
     }
 }
 
-impl<'a> ToArguments for &'a str {
+impl ToArguments for &str {
     fn to_arguments<F, E>(&self, f: &mut F) -> Result<(), E>
     where F: FnMut(&str) -> Result<(), E> {
         f(self)
@@ -330,13 +380,55 @@ impl<'a> ToArguments for &'a str {
 }
 
 impl FromIter for String {
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Self, Error> {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Self, Error> {
         for res in iter {
             let line = res?;
-            if line.0 == "file" {
-                return Ok(line.1);
+            if line.0.as_ref() == "file" {
+                return Ok(line.1.into());
             }
         }
         Err(Error::Proto(ProtoError::NoField("songname")))
     }
 }
+
+/// Decode `%XX` escapes, returning `None` on malformed input
+pub(crate) fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok())?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Escape characters which can't appear in URI query values as `%XX`
+pub(crate) fn percent_encode(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => result.push(byte as char),
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::ToQueueRange;
+
+    #[test]
+    fn inclusive_ranges() {
+        assert_eq!((2..=4).to_range(), "2:5");
+        assert_eq!((..=4).to_range(), ":5");
+        assert_eq!((2..=u32::MAX).to_range(), "2:");
+        assert_eq!((..=u32::MAX).to_range(), "0:");
+    }
+}
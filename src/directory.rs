@@ -1,6 +1,8 @@
 //! The module defines directory structs and methods.
 
-use crate::convert::FromIter;
+use crate::convert::{FromIter, Text};
+#[cfg(feature = "chrono")]
+use crate::convert::parse_timestamp;
 use crate::error::Error;
 
 /// MPD-recognised directory.
@@ -13,16 +15,24 @@ pub struct Directory {
     pub last_mod: Option<String>
 }
 
+#[cfg(feature = "chrono")]
+impl Directory {
+    /// Last modification time, if known and valid
+    pub fn last_modified(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.last_mod.as_deref().and_then(parse_timestamp)
+    }
+}
+
 impl FromIter for Directory {
     /// build from map
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Directory, Error> {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Directory, Error> {
         let mut result = Directory::default();
 
         for res in iter {
             let line = res?;
-            match &*line.0 {
-                "directory" => result.name = line.1.to_owned(),
-                "Last-Modified" => result.last_mod = Some(line.1.to_owned()),
+            match line.0.as_ref() {
+                "directory" => result.name = line.1.into(),
+                "Last-Modified" => result.last_mod = Some(line.1.into()),
                 _ => {}
             }
         }
@@ -122,7 +122,7 @@ impl StdError for ServerError {}
 
 impl fmt::Display for ServerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} error (`{}') at {}", self.code, self.detail, self.pos)
+        write!(f, "{} error (`{}') in `{}' at {}", self.code, self.detail, self.command, self.pos)
     }
 }
 
@@ -156,6 +156,43 @@ impl FromStr for ServerError {
 }
 // }}}
 
+// Command I/O errors {{{
+/// I/O error which happened while running a command
+///
+/// Errors of this type are wrapped into [`std::io::Error`] of the same kind, so they can be
+/// inspected with [`Error::command()`] or by downcasting the inner error.
+#[derive(Debug)]
+pub struct CommandIoError {
+    /// command name, which was running when the error happened
+    /// (`command_list_begin` or `command_list_ok_begin` for command lists)
+    pub command: String,
+    /// original I/O error
+    pub error: IoError,
+}
+
+impl CommandIoError {
+    /// Attach command name to the I/O error, unless it already has one
+    pub(crate) fn wrap(command: &str, error: IoError) -> IoError {
+        if command.is_empty() || error.get_ref().is_some_and(|e| e.is::<CommandIoError>()) {
+            return error;
+        }
+        IoError::new(error.kind(), CommandIoError { command: command.to_owned(), error })
+    }
+}
+
+impl StdError for CommandIoError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for CommandIoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in `{}'", self.error, self.command)
+    }
+}
+// }}}
+
 // Error {{{
 /// Main error type, describing all possible error classes for the crate
 #[derive(Debug)]
@@ -173,6 +210,20 @@ pub enum Error {
 /// Shortcut type for MPD results
 pub type Result<T> = result::Result<T, Error>;
 
+impl Error {
+    /// Name of the command which caused the error, if known
+    ///
+    /// It's reported by the server for [`Server`](Error::Server) errors, and recorded
+    /// by the client for [`Io`](Error::Io) errors happened while running a command.
+    pub fn command(&self) -> Option<&str> {
+        match self {
+            Error::Server(e) if !e.command.is_empty() => Some(&e.command),
+            Error::Io(e) => e.get_ref().and_then(|e| e.downcast_ref::<CommandIoError>()).map(|e| &*e.command),
+            _ => None,
+        }
+    }
+}
+
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match *self {
@@ -278,6 +329,8 @@ pub enum ParseError {
     BadState(String),
     /// unknown error code in `ACK` response
     BadErrorCode(usize),
+    /// invalid connection URL
+    BadUrl(String),
 }
 
 impl StdError for ParseError {}
@@ -306,6 +359,7 @@ impl fmt::Display for ParseError {
             E::BadChans(_) => "invalid audio format channels",
             E::BadState(_) => "invalid playing state",
             E::BadErrorCode(_) => "unknown error code",
+            E::BadUrl(_) => "invalid connection URL",
         };
 
         write!(f, "{}", desc)
@@ -335,6 +389,21 @@ impl From<StringParseError> for ParseError {
         match e {}
     }
 }
+
+/// Response line skipped by lenient client, see [`set_lenient()`](crate::Client::set_lenient)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    /// skipped line, either a field with invalid value or a line which is not a field at all
+    pub line: String,
+    /// why the line was not accepted
+    pub error: ParseError,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "skipped `{}': {}", self.line, self.error)
+    }
+}
 // }}}
 
 // Protocol errors {{{
@@ -355,6 +424,8 @@ pub enum ProtoError {
     NoField(&'static str),
     /// expected sticker value, but didn't find it
     BadSticker,
+    /// object looked up by name (like an output) is not known to the server
+    NotFound(String),
 }
 
 impl StdError for ProtoError {}
@@ -367,9 +438,33 @@ impl fmt::Display for ProtoError {
             ProtoError::BadBanner => "banner error",
             ProtoError::NoField(_) => "missing field",
             ProtoError::BadSticker => "sticker error",
+            ProtoError::NotFound(_) => "object not found",
         };
 
         write!(f, "{}", desc)
     }
 }
 // }}}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn server_error_command() {
+        let error = Error::Server("ACK [50@1] {play} song doesn't exist".parse().unwrap());
+        assert_eq!(error.command(), Some("play"));
+        assert_eq!(error.to_string(), "item not found error (`song doesn't exist') in `play' at 1");
+    }
+
+    #[test]
+    fn io_error_command() {
+        let error = CommandIoError::wrap("status", IoError::new(ErrorKind::UnexpectedEof, "connection closed by server"));
+        let error = Error::Io(CommandIoError::wrap("ping", error));
+        assert_eq!(error.command(), Some("status"));
+        assert_eq!(error.to_string(), "connection closed by server in `status'");
+        assert!(matches!(error, Error::Io(ref e) if e.kind() == ErrorKind::UnexpectedEof));
+        assert_eq!(Error::Io(IoError::new(ErrorKind::BrokenPipe, "pipe")).command(), None);
+    }
+}
@@ -26,19 +26,40 @@
 //! [`IdleGuard`] struct, which catches mutable reference
 //! to original [`Client`] struct, thus enforcing MPD contract in regards of (im)possibility
 //! to send commands while in "idle" mode.
+//!
+//! Programs with their own event loop can check if events have arrived without blocking,
+//! with [`try_get()`](IdleGuard::try_get), e.g. when the client file descriptor
+//! becomes readable.
+//!
+//! Programs which just react to events can use [`idle_loop()`](Client::idle_loop) instead,
+//! which enters "idle" mode again after each batch of events is handled:
+//!
+//! ```rust,no_run
+//! # use mpd::{Client, Subsystem};
+//! use std::ops::ControlFlow;
+//!
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! conn.idle_loop(&[Subsystem::Player], |conn, _events| {
+//!     let status = conn.status()?;
+//!     println!("{:?}", status.state);
+//!     Ok(ControlFlow::<()>::Continue(()))
+//! }).unwrap();
+//! ```
 
 use crate::client::Client;
 use crate::error::{Error, ParseError};
 use crate::proto::Proto;
 
 use std::fmt;
-use std::io::{Read, Write};
-use std::mem::forget;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::ops::ControlFlow;
 use std::str::FromStr;
+use std::time::Duration;
 
 /// Subsystems for `idle` command
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Subsystem {
     /// database: the song database has been modified after update.
     Database,
@@ -69,6 +90,8 @@ pub enum Subsystem {
     Neighbor,
     /// mount: the mount list has changed
     Mount,
+    /// subsystem unknown to this library, added in a newer MPD version
+    Other(String),
 }
 
 impl FromStr for Subsystem {
@@ -90,13 +113,13 @@ impl FromStr for Subsystem {
             "message" => Ok(Message),
             "neighbor" => Ok(Neighbor),
             "mount" => Ok(Mount),
-            _ => Err(ParseError::BadValue(s.to_owned())),
+            _ => Ok(Other(s.to_owned())),
         }
     }
 }
 
 impl Subsystem {
-    fn to_str(self) -> &'static str {
+    fn to_str(&self) -> &str {
         use self::Subsystem as S;
         match self {
             S::Database => "database",
@@ -113,6 +136,7 @@ impl Subsystem {
             S::Message => "message",
             S::Neighbor => "neighbor",
             S::Mount => "mount",
+            S::Other(ref name) => name,
         }
     }
 }
@@ -131,24 +155,102 @@ impl crate::proto::ToArguments for Subsystem {
     }
 }
 
+/// Streams which can be read without blocking indefinitely, to check for events without waiting
+pub trait Nonblocking {
+    /// Switch the stream to non-blocking mode or back
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+    /// Get read timeout, `None` means blocking forever
+    fn read_timeout(&self) -> io::Result<Option<Duration>>;
+    /// Set read timeout, `None` means blocking forever
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Nonblocking for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        TcpStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Nonblocking for std::os::unix::net::UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_nonblocking(self, nonblocking)
+    }
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        std::os::unix::net::UnixStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
 /// "Idle" mode guard enforcing MPD asynchronous events protocol
-pub struct IdleGuard<'a, S: 'a + Read + Write>(&'a mut Client<S>);
+pub struct IdleGuard<'a, S: 'a + Read + Write> {
+    client: &'a mut Client<S>,
+    done: bool,
+}
 
 impl<'a, S: 'a + Read + Write> IdleGuard<'a, S> {
     /// Get list of subsystems with new events, interrupting idle mode in process
-    pub fn get(self) -> Result<Vec<Subsystem>, Error> {
-        let result = self.0.read_list("changed").and_then(|v| v.into_iter().map(|b| b.parse().map_err(From::from)).collect());
-        forget(self);
+    ///
+    /// Returns empty list if the events were already received with [`try_get()`](IdleGuard::try_get).
+    pub fn get(mut self) -> Result<Vec<Subsystem>, Error> {
+        let result = if self.done { Ok(Vec::new()) } else { read_events(self.client) };
+        self.done = true;
         result
     }
 }
 
+impl<'a, S: 'a + Read + Write + Nonblocking> IdleGuard<'a, S> {
+    /// Get list of subsystems with new events if they have arrived, without blocking
+    ///
+    /// Returns `None` if there are no events yet. Once events are returned, idle mode
+    /// is over, and the guard can be dropped to use the client again.
+    pub fn try_get(&mut self) -> Result<Option<Vec<Subsystem>>, Error> {
+        self.get_timeout(Duration::ZERO)
+    }
+
+    /// Wait for events up to the given timeout, returning `None` if there are no events yet
+    ///
+    /// See [`try_get()`](IdleGuard::try_get) for details.
+    pub fn get_timeout(&mut self, timeout: Duration) -> Result<Option<Vec<Subsystem>>, Error> {
+        if self.done || !self.client.wait_for_data(timeout)? {
+            return Ok(None);
+        }
+        self.done = true;
+        read_events(self.client).map(Some)
+    }
+}
+
+#[cfg(unix)]
+impl<'a, S: 'a + Read + Write + std::os::unix::io::AsRawFd> std::os::unix::io::AsRawFd for IdleGuard<'a, S> {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.client.as_raw_fd()
+    }
+}
+
 impl<'a, S: 'a + Read + Write> Drop for IdleGuard<'a, S> {
     fn drop(&mut self) {
-        let _ = self.0.run_command("noidle", ()).map(|_| self.0.drain());
+        if !self.done {
+            let _ = self.client.run_command("noidle", ()).map(|_| self.client.drain());
+        }
     }
 }
 
+fn read_events<S: Read + Write>(client: &mut Client<S>) -> Result<Vec<Subsystem>, Error> {
+    client.read_list("changed").and_then(|v| v.into_iter().map(|b| b.parse().map_err(From::from)).collect())
+}
+
 /// This trait implements `idle` command of MPD protocol
 ///
 /// See module's documentation for details.
@@ -184,6 +286,36 @@ impl<S: Read + Write> Idle for Client<S> {
     type Stream = S;
     fn idle<'a>(&'a mut self, subsystems: &[Subsystem]) -> Result<IdleGuard<'a, S>, Error> {
         self.run_command("idle", subsystems)?;
-        Ok(IdleGuard(self))
+        Ok(IdleGuard { client: self, done: false })
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Wait for events from a set of subsystems over and over, passing them to the callback
+    ///
+    /// The client is out of "idle" mode while the callback runs, so it can run any commands.
+    /// The loop ends when the callback returns [`ControlFlow::Break`] with some value,
+    /// or when either the callback or waiting for events fails.
+    pub fn idle_loop<T, F>(&mut self, subsystems: &[Subsystem], mut callback: F) -> Result<T, Error>
+    where F: FnMut(&mut Client<S>, Vec<Subsystem>) -> Result<ControlFlow<T>, Error> {
+        loop {
+            let events = self.wait(subsystems)?;
+            if let ControlFlow::Break(value) = callback(self, events)? {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Subsystem;
+
+    #[test]
+    fn unknown_subsystem() {
+        assert_eq!("stored_playlist".parse::<Subsystem>().unwrap(), Subsystem::Playlist);
+        let other = "teleporter".parse::<Subsystem>().unwrap();
+        assert_eq!(other, Subsystem::Other("teleporter".into()));
+        assert_eq!(other.to_string(), "teleporter");
     }
 }
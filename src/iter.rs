@@ -0,0 +1,123 @@
+//! The module defines iterators parsing responses as they are received
+//!
+//! Methods like [`queue_iter()`](crate::Client::queue_iter) return [`StructIter`],
+//! which reads and parses the response one item at a time instead of collecting it
+//! into a `Vec`, so huge queues or search results don't have to be kept in memory,
+//! and first items can be shown before the whole response is received.
+//!
+//! ```rust,no_run
+//! # use mpd::Client;
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! for song in conn.queue_iter().unwrap().take(10) {
+//!     println!("{}", song.unwrap().file);
+//! }
+//! ```
+
+use crate::client::Client;
+use crate::convert::FromIter;
+use crate::error::{Error, ParseWarning};
+use crate::proto::{check_value, Proto};
+use crate::reply::{parse_line, split_pair, Line};
+
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+
+/// Iterator over structures in a response, parsed as they are received
+///
+/// The client can't be used until the iterator is dropped. If the iterator is dropped
+/// before reaching the end, the rest of the response is read and discarded.
+pub struct StructIter<'a, S: 'a + Read + Write, T> {
+    client: &'a mut Client<S>,
+    keys: &'static [&'static str],
+    /// lines of the structure being read
+    entry: String,
+    /// first line of the next structure, read ahead
+    pending: String,
+    done: bool,
+    _item: PhantomData<T>,
+}
+
+impl<'a, S: 'a + Read + Write, T: FromIter> StructIter<'a, S, T> {
+    /// Iterate over structures, each starting with one of `keys` fields
+    pub(crate) fn new(client: &'a mut Client<S>, keys: &'static [&'static str]) -> StructIter<'a, S, T> {
+        StructIter { client, keys, entry: String::new(), pending: String::new(), done: false, _item: PhantomData }
+    }
+
+    /// Read lines up to the start of the next structure
+    ///
+    /// Lines are kept as they are received, so pairs are parsed without copying them.
+    fn read_entry(&mut self) -> Result<(), Error> {
+        self.entry.clear();
+        self.entry.push_str(&self.pending);
+        self.pending.clear();
+        let lenient = self.client.is_lenient();
+        while !self.done {
+            let line = match self.client.read_line() {
+                Ok(line) => line,
+                Err(e) => {
+                    self.done = true;
+                    return Err(e);
+                }
+            };
+            let error = match parse_line(line) {
+                Ok(Line::Pair(key, value)) => {
+                    let checked = if lenient { check_value::<T>(key, value) } else { Ok(()) };
+                    match checked {
+                        Ok(()) => {
+                            let buf = if !self.entry.is_empty() && self.keys.contains(&key) { &mut self.pending } else { &mut self.entry };
+                            buf.push_str(line);
+                            buf.push('\n');
+                            if !self.pending.is_empty() {
+                                break;
+                            }
+                            continue;
+                        }
+                        Err(error) => error,
+                    }
+                }
+                Ok(Line::Ok) => {
+                    self.done = true;
+                    continue;
+                }
+                Ok(Line::Ack(e)) => {
+                    self.done = true;
+                    return Err(Error::Server(e));
+                }
+                Err(error) => error,
+            };
+            let warning = ParseWarning { line: line.to_owned(), error };
+            match self.client.warnings() {
+                Some(warnings) => warnings.push(warning),
+                None => {
+                    self.done = true;
+                    return Err(Error::Parse(warning.error));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: 'a + Read + Write, T: FromIter> Iterator for StructIter<'a, S, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Result<T, Error>> {
+        match self.read_entry() {
+            Ok(()) if self.entry.is_empty() => None,
+            // Lines were checked to be pairs while reading
+            Ok(()) => Some(T::from_iter(self.entry.lines().filter_map(split_pair).map(Ok))),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'a, S: 'a + Read + Write, T> Drop for StructIter<'a, S, T> {
+    fn drop(&mut self) {
+        while !self.done {
+            match self.client.read_line() {
+                Ok(line) => self.done = line == "OK" || line.starts_with("ACK "),
+                Err(_) => self.done = true,
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! The module defines keepalive wrapper for long-lived connections
+//!
+//! MPD drops connections which stay silent longer than its `connection_timeout`
+//! setting (60 seconds by default), unless they are in "idle" mode. [`Keepalive`]
+//! shares the client with a background thread, which sends `ping` whenever
+//! the client hasn't been used for the given interval.
+//!
+//! ```rust,no_run
+//! use mpd::keepalive::Keepalive;
+//! use mpd::Client;
+//! use std::time::Duration;
+//!
+//! let conn = Keepalive::new(Client::connect("127.0.0.1:6600").unwrap(), Duration::from_secs(30));
+//! // ...much later
+//! println!("Status: {:?}", conn.lock().status());
+//! ```
+
+use crate::client::Client;
+
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+struct State {
+    last_used: Instant,
+    stopped: bool,
+}
+
+struct Shared<S: Read + Write> {
+    client: Mutex<Client<S>>,
+    state: Mutex<State>,
+    wakeup: Condvar,
+}
+
+impl<S: Read + Write> Shared<S> {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn touch(&self) {
+        self.state().last_used = Instant::now();
+    }
+}
+
+/// Client wrapper pinging the server in background while the client is not used
+///
+/// The client is accessed with [`lock()`](Keepalive::lock) method. No pings are sent
+/// while the lock is held, so it's safe to enter "idle" mode with a locked client.
+pub struct Keepalive<S: Read + Write + Send + 'static> {
+    shared: Arc<Shared<S>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<S: Read + Write + Send + 'static> Keepalive<S> {
+    /// Start pinging the server when the client is not used for the given interval
+    pub fn new(client: Client<S>, interval: Duration) -> Keepalive<S> {
+        let shared = Arc::new(Shared {
+            client: Mutex::new(client),
+            state: Mutex::new(State { last_used: Instant::now(), stopped: false }),
+            wakeup: Condvar::new(),
+        });
+
+        let thread = {
+            let shared = shared.clone();
+            thread::spawn(move || keepalive(&shared, interval))
+        };
+
+        Keepalive { shared, thread: Some(thread) }
+    }
+
+    /// Get exclusive access to the client
+    pub fn lock(&self) -> ClientGuard<'_, S> {
+        let client = self.shared.client.lock().unwrap_or_else(PoisonError::into_inner);
+        ClientGuard { client, shared: &self.shared }
+    }
+
+    /// Stop pinging and release the client
+    pub fn into_client(mut self) -> Client<S> {
+        self.stop();
+        let shared = self.shared.clone();
+        drop(self);
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => shared.client.into_inner().unwrap_or_else(PoisonError::into_inner),
+            Err(_) => unreachable!("keepalive thread is stopped"),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.shared.state().stopped = true;
+        self.shared.wakeup.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<S: Read + Write + Send + 'static> Drop for Keepalive<S> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Locked client, the keepalive timer restarts when the guard is dropped
+pub struct ClientGuard<'a, S: Read + Write> {
+    client: MutexGuard<'a, Client<S>>,
+    shared: &'a Shared<S>,
+}
+
+impl<S: Read + Write> Deref for ClientGuard<'_, S> {
+    type Target = Client<S>;
+    fn deref(&self) -> &Client<S> {
+        &self.client
+    }
+}
+
+impl<S: Read + Write> DerefMut for ClientGuard<'_, S> {
+    fn deref_mut(&mut self) -> &mut Client<S> {
+        &mut self.client
+    }
+}
+
+impl<S: Read + Write> Drop for ClientGuard<'_, S> {
+    fn drop(&mut self) {
+        self.shared.touch();
+    }
+}
+
+fn keepalive<S: Read + Write>(shared: &Shared<S>, interval: Duration) {
+    let mut state = shared.state();
+    loop {
+        if state.stopped {
+            return;
+        }
+
+        let idle_for = state.last_used.elapsed();
+        if idle_for < interval {
+            state = shared.wakeup.wait_timeout(state, interval - idle_for).unwrap_or_else(PoisonError::into_inner).0;
+            continue;
+        }
+
+        // The client is in use right now, so it's not going to time out
+        drop(state);
+        if let Ok(mut client) = shared.client.try_lock() {
+            let _ = client.ping();
+        }
+        state = shared.state();
+        state.last_used = Instant::now();
+    }
+}
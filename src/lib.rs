@@ -32,11 +32,11 @@
 //! # }
 //! ```
 
-mod macros;
 mod convert;
 pub mod error;
 pub mod version;
 pub mod reply;
+pub mod codec;
 pub mod status;
 pub mod song;
 pub mod directory;
@@ -50,21 +50,54 @@ pub mod search;
 pub mod message;
 pub mod idle;
 pub mod mount;
-mod sticker;
+pub mod queue;
+pub mod watcher;
+pub mod library;
+pub mod update;
+pub mod keepalive;
+pub mod artwork;
+pub mod sticker;
+pub mod config;
+pub mod capabilities;
+pub mod command_list;
+pub mod iter;
+pub mod capture;
 
 mod proto;
+mod trace;
+#[cfg(feature = "net")]
+pub mod socket;
+#[cfg(feature = "net")]
+pub mod builder;
+#[cfg(feature = "net")]
+pub mod reconnect;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod client;
+#[cfg(feature = "async")]
+pub mod aio;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
+#[cfg(feature = "net")]
+pub use builder::ClientBuilder;
+pub use artwork::Picture;
+pub use capabilities::ServerCapabilities;
 pub use client::Client;
+pub use config::Config;
 pub use idle::{Idle, Subsystem};
 pub use message::{Channel, Message};
-pub use mount::{Mount, Neighbor};
-pub use output::Output;
-pub use list::GroupedValues;
+pub use mount::{Mount, Neighbor, Storage, StoragePlugin};
+pub use output::{Output, OutputRef};
+pub use list::{GroupNode, GroupedValues};
 pub use playlist::{Playlist, SaveMode, EditAction};
-pub use plugin::Plugin;
+pub use plugin::{Decoder, Plugin};
 pub use search::{Query, Term};
 pub use song::{Id, Song};
-pub use stats::Stats;
-pub use status::{ReplayGain, State, Status};
-pub use version::Version;
+pub use stats::{Count, Stats};
+pub use sticker::{Sticker, StickerOp};
+pub use status::{FadeSettings, ReplayGain, ReplayGainConfig, SeekMode, State, Status};
+pub use update::UpdateJob;
+pub use version::{Feature, ProtocolFeature, Version};
@@ -0,0 +1,249 @@
+//! The module defines local cache of the music database for browsing
+//!
+//! Browsing a large library tag by tag takes a lot of round trips to the server.
+//! [`LibraryCache`] fetches the whole database once with a single `listallinfo` call,
+//! groups songs into artist → album → song index as they are received, and then
+//! answers browse queries locally, until the database changes.
+//!
+//! With `serde` feature the cache can be saved and loaded in any serde format,
+//! and [`update()`](LibraryCache::update) checks if it's still in sync with the
+//! database before rebuilding it.
+//!
+//! ```rust,no_run
+//! use mpd::library::LibraryCache;
+//! use mpd::Client;
+//!
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let mut library = LibraryCache::new();
+//! library.update(&mut conn).unwrap();
+//! for artist in library.artists() {
+//!     println!("{}: {:?}", artist, library.albums(artist).collect::<Vec<_>>());
+//! }
+//! ```
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::idle::Subsystem;
+use crate::lsinfo::LsInfoEntry;
+use crate::song::Song;
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+/// Songs of a single artist, by album title
+type Albums = BTreeMap<String, Vec<Song>>;
+
+/// Local index of the music database, grouped by artist and album
+///
+/// Songs with several artists are listed under each of them. Like MPD searches,
+/// `AlbumArtist` falls back to `Artist` for songs without it. Songs without the
+/// artist tag are listed under empty artist name, and songs without the album tag
+/// are listed under empty album title.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryCache {
+    tag: String,
+    db_update: Option<Duration>,
+    artists: BTreeMap<String, Albums>,
+}
+
+impl Default for LibraryCache {
+    fn default() -> LibraryCache {
+        LibraryCache { tag: "AlbumArtist".to_owned(), db_update: None, artists: BTreeMap::new() }
+    }
+}
+
+impl LibraryCache {
+    /// Create empty cache grouping songs by album artist, the first update fetches the whole index
+    pub fn new() -> LibraryCache {
+        LibraryCache::default()
+    }
+
+    /// Group songs by another tag (e.g. `Artist` or `Composer`) instead of `AlbumArtist`
+    pub fn artist_tag(mut self, tag: &str) -> LibraryCache {
+        self.tag = tag.to_owned();
+        self.reset();
+        self
+    }
+
+    /// Database update time the cache corresponds to, if the index was fetched already
+    ///
+    /// This is the `db_update` field of [`Stats`](crate::Stats).
+    pub fn db_update(&self) -> Option<Duration> {
+        self.db_update
+    }
+
+    /// Check if the index was fetched already
+    pub fn is_valid(&self) -> bool {
+        self.db_update.is_some()
+    }
+
+    /// Drop cached index, so the next update fetches it anew
+    pub fn reset(&mut self) {
+        self.db_update = None;
+        self.artists.clear();
+    }
+
+    /// Rebuild the index if idle events include database changes, returning `true` if the cache changed
+    pub fn handle<S: Read + Write>(&mut self, client: &mut Client<S>, events: &[Subsystem]) -> Result<bool> {
+        if events.contains(&Subsystem::Database) {
+            self.reset();
+            self.update(client)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Fetch the index unless the cache is in sync with the database, returning `true` if the cache changed
+    pub fn update<S: Read + Write>(&mut self, client: &mut Client<S>) -> Result<bool> {
+        let db_update = client.stats()?.db_update;
+        if self.db_update == Some(db_update) {
+            return Ok(false);
+        }
+
+        let mut library = LibraryCache { tag: self.tag.clone(), ..LibraryCache::default() };
+        for entry in client.listallinfo_entries("")? {
+            if let LsInfoEntry::Song(song) = entry? {
+                library.insert(song);
+            }
+        }
+        self.artists = library.artists;
+        self.db_update = Some(db_update);
+        Ok(true)
+    }
+
+    fn insert(&mut self, song: Song) {
+        let mut artists: Vec<String> = Vec::new();
+        let mut tag = Some(self.tag.as_str());
+        while let Some(name) = tag {
+            for value in song.tag_values(name) {
+                if !artists.iter().any(|artist| artist == value) {
+                    artists.push(value.to_owned());
+                }
+            }
+            tag = if artists.is_empty() { fallback_tag(name) } else { None };
+        }
+        if artists.is_empty() {
+            artists.push(String::new());
+        }
+
+        let album = song.album().unwrap_or_default().to_owned();
+        for artist in artists {
+            self.artists.entry(artist).or_default().entry(album.clone()).or_default().push(song.clone());
+        }
+    }
+
+    /// Artist names, sorted
+    pub fn artists(&self) -> impl Iterator<Item = &str> {
+        self.artists.keys().map(String::as_str)
+    }
+
+    /// Album titles of the artist, sorted
+    pub fn albums<'a>(&'a self, artist: &str) -> impl Iterator<Item = &'a str> {
+        self.artists.get(artist).into_iter().flat_map(|albums| albums.keys().map(String::as_str))
+    }
+
+    /// Songs of the artist's album, in database order
+    pub fn songs(&self, artist: &str, album: &str) -> &[Song] {
+        self.artists.get(artist).and_then(|albums| albums.get(album)).map_or(&[], Vec::as_slice)
+    }
+
+    /// All songs of the artist, by album
+    pub fn artist_songs<'a>(&'a self, artist: &str) -> impl Iterator<Item = &'a Song> {
+        self.artists.get(artist).into_iter().flat_map(|albums| albums.values().flatten())
+    }
+
+    /// Find songs with any tag value containing the text, ignoring case
+    pub fn search<'a>(&'a self, text: &str) -> Vec<&'a Song> {
+        let text = text.to_lowercase();
+        self.artists
+            .values()
+            .flat_map(|albums| albums.values().flatten())
+            .filter(|song| {
+                song.file.to_lowercase().contains(&text)
+                    || song.title.iter().chain(&song.artist).chain(song.tags.iter().map(|(_, value)| value)).any(|value| value.to_lowercase().contains(&text))
+            })
+            .collect()
+    }
+
+    /// Total number of cached songs
+    pub fn len(&self) -> usize {
+        self.artists.values().flat_map(BTreeMap::values).map(Vec::len).sum()
+    }
+
+    /// Check if there are no cached songs
+    pub fn is_empty(&self) -> bool {
+        self.artists.is_empty()
+    }
+}
+
+/// Tag used by MPD when the song doesn't have the given one
+fn fallback_tag(tag: &str) -> Option<&'static str> {
+    if tag.eq_ignore_ascii_case("AlbumArtist") || tag.eq_ignore_ascii_case("ArtistSort") {
+        Some("Artist")
+    } else if tag.eq_ignore_ascii_case("AlbumArtistSort") {
+        Some("AlbumArtist")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LibraryCache;
+    use crate::song::Song;
+    use crate::testing::MockServer;
+
+    fn song(file: &str, artist: Option<&str>, album: Option<&str>) -> Song {
+        let tags = artist.map(|artist| ("AlbumArtist".into(), artist.into())).into_iter().chain(album.map(|album| ("Album".into(), album.into())));
+        Song { file: file.into(), tags: tags.collect(), ..Song::default() }
+    }
+
+    #[test]
+    fn browse() {
+        let mut library = LibraryCache::new();
+        library.insert(song("b1.flac", Some("B"), Some("Second")));
+        library.insert(song("b2.flac", Some("B"), Some("First")));
+        library.insert(song("b3.flac", Some("B"), Some("Second")));
+        library.insert(song("loose.flac", None, None));
+
+        assert_eq!(library.artists().collect::<Vec<_>>(), ["", "B"]);
+        assert_eq!(library.albums("B").collect::<Vec<_>>(), ["First", "Second"]);
+        assert_eq!(library.albums("A").count(), 0);
+        assert_eq!(library.songs("B", "Second").iter().map(|song| &*song.file).collect::<Vec<_>>(), ["b1.flac", "b3.flac"]);
+        assert_eq!(library.songs("", "").len(), 1);
+        assert_eq!(library.artist_songs("B").count(), 3);
+        assert_eq!(library.search("SECOND").len(), 2);
+        assert_eq!(library.len(), 4);
+    }
+
+    #[test]
+    fn update() {
+        let server = MockServer::new();
+        server
+            .expect("stats", "db_update: 100")
+            .expect(
+                "listallinfo \"\"",
+                "directory: a\nLast-Modified: 2024-01-01T00:00:00Z\n\
+                 file: a/1.flac\nAlbumArtist: X\nAlbumArtist: Y\nAlbum: One\n\
+                 file: a/2.flac\nArtist: Z\nAlbum: Two\n\
+                 playlist: a/list.m3u\nLast-Modified: 2024-01-01T00:00:00Z\n\
+                 file: 3.flac",
+            )
+            .expect("stats", "db_update: 100");
+        let mut conn = server.client();
+
+        let mut library = LibraryCache::new();
+        assert!(library.update(&mut conn).unwrap());
+        assert!(!library.update(&mut conn).unwrap());
+        server.verify();
+
+        assert_eq!(library.artists().collect::<Vec<_>>(), ["", "X", "Y", "Z"]);
+        assert_eq!(library.songs("X", "One").len(), 1);
+        assert_eq!(library.songs("Y", "One").len(), 1);
+        assert_eq!(library.songs("Z", "Two")[0].file, "a/2.flac");
+        assert_eq!(library.songs("", "")[0].file, "3.flac");
+        assert_eq!(library.len(), 4);
+    }
+}
@@ -1,6 +1,7 @@
 //! The module defines a tag grouped values struct for parsing grouped list calls.
 
-use crate::{error::Result, proto::Pairs};
+use crate::error::{Error, ParseError, Result};
+use crate::proto::{Pairs, ResponseLines};
 
 #[derive(Debug)]
 struct Group {
@@ -21,7 +22,7 @@ pub struct GroupedValues {
 impl<'a> GroupedValues {
     /// Parse a grouped list call response. sep MUST be lowercase.
     pub fn from_pairs_with_sep<I>(pairs: &'a mut Pairs<I>, sep: &'a str) -> Result<Self>
-    where I: Iterator<Item = std::io::Result<String>> {
+    where I: ResponseLines {
         let mut groups: Vec<(String, Vec<String>)> = Vec::new();
 
         let mut curr_group: Option<Group> = None;
@@ -29,7 +30,7 @@ impl<'a> GroupedValues {
         loop {
             match pairs.next() {
                 Some(Ok((a, b))) => {
-                    if &*a.to_lowercase() == sep {
+                    if a.eq_ignore_ascii_case(sep) {
                         // Flush current group into main struct
                         if let Some(group) = curr_group {
                             groups.push((group.key, group.contents));
@@ -53,3 +54,83 @@ impl<'a> GroupedValues {
         Ok(Self { groups })
     }
 }
+
+/// Tag value with values of nested groups, as returned by `list` with several `group` clauses
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupNode {
+    /// tag name, as reported by the server
+    pub tag: String,
+    /// tag value
+    pub value: String,
+    /// values nested under this one, empty for the innermost level
+    pub children: Vec<GroupNode>,
+}
+
+impl GroupNode {
+    /// Parse a list call response into a tree of values
+    ///
+    /// Nesting is inferred from the order in which tags appear in the response:
+    /// the first tag is the outermost group, and each new tag is nested into the previous one.
+    pub fn from_pairs<I>(pairs: &mut Pairs<I>) -> Result<Vec<GroupNode>>
+    where I: ResponseLines {
+        let mut levels: Vec<String> = Vec::new();
+        let mut roots: Vec<GroupNode> = Vec::new();
+        let mut depth = 0;
+
+        for pair in pairs {
+            let (tag, value) = pair?;
+            let level = match levels.iter().position(|l| l.eq_ignore_ascii_case(&tag)) {
+                Some(level) => level,
+                None => {
+                    levels.push(tag.to_lowercase());
+                    levels.len() - 1
+                }
+            };
+            // A value can only be nested into the last value of the level above
+            if level > depth {
+                return Err(Error::Parse(ParseError::BadValue(format!("{} `{}' has no parent {}", tag, value, levels[level - 1]))));
+            }
+
+            let mut siblings = &mut roots;
+            for _ in 0..level {
+                siblings = &mut siblings.last_mut().unwrap().children;
+            }
+            siblings.push(GroupNode { tag, value, children: Vec::new() });
+            depth = level + 1;
+        }
+
+        Ok(roots)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GroupNode;
+    use crate::error::{Error, ParseError};
+    use crate::proto::Pairs;
+
+    fn node(tag: &str, value: &str, children: Vec<GroupNode>) -> GroupNode {
+        GroupNode { tag: tag.into(), value: value.into(), children }
+    }
+
+    #[test]
+    fn nested_groups() {
+        let response = "AlbumArtist: A\nAlbum: X\nTitle: 1\nTitle: 2\nAlbum: Y\nTitle: 3\nAlbumArtist: B\nAlbum: Z\nTitle: 4\nOK\n";
+        let tree = GroupNode::from_pairs(&mut Pairs::new(response.lines())).unwrap();
+        assert_eq!(tree, vec![
+            node("AlbumArtist", "A", vec![
+                node("Album", "X", vec![node("Title", "1", vec![]), node("Title", "2", vec![])]),
+                node("Album", "Y", vec![node("Title", "3", vec![])]),
+            ]),
+            node("AlbumArtist", "B", vec![node("Album", "Z", vec![node("Title", "4", vec![])])]),
+        ]);
+    }
+
+    #[test]
+    fn bad_nesting() {
+        let response = "Album: X\nTitle: 1\nAlbum: Y\nArtist: A\nOK\n";
+        let error = GroupNode::from_pairs(&mut Pairs::new(response.lines())).unwrap_err();
+        assert!(matches!(error, Error::Parse(ParseError::BadValue(ref e)) if e == "Artist `A' has no parent title"));
+    }
+}
@@ -1,48 +1,93 @@
 //! The module defines LSInfo entry structs and methods.
 
-use crate::convert::FromIter;
+use crate::convert::{FromIter, Text};
 use crate::directory::Directory;
 use crate::error::{Error, ParseError, ProtoError};
+use crate::iter::StructIter;
+use crate::playlist::Playlist;
 use crate::song::Song;
 
+use std::iter;
+
 /// Enum over lsinfo entry types
 #[derive(Debug, Clone, PartialEq)]
 pub enum LsInfoEntry {
     /// A file that is an MPD-recognised song
     Song(Song),
     /// A directory
-    Directory(Directory)
-    // TODO: playlist
+    Directory(Directory),
+    /// A stored playlist file
+    Playlist(Playlist),
 }
 
 impl FromIter for LsInfoEntry {
     /// build song from map
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(mut iter: I) -> Result<LsInfoEntry, Error> {
-        // Peek at the first element to see if we're dealing with a directory
-        // or a song file.
-        // TODO: add playlist support
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(mut iter: I) -> Result<LsInfoEntry, Error> {
+        // Peek at the first element to see if we're dealing with a directory,
+        // a song file or a playlist.
 
         let maybe_first_elem = iter.next();
         if let Some(first_elem) = maybe_first_elem {
             if let Ok((k, v)) = first_elem {
                 // We have to set dir name or song URI by ourselves since we
                 // have already advanced the iterator past it.
-                match k.as_str() {
+                match k.as_ref() {
                     "directory" => {
                         let mut dir = Directory::from_iter(iter)?;
-                        dir.name = v;
+                        dir.name = v.into();
                         return Ok(LsInfoEntry::Directory(dir));
                     },
                     "file" => {
                         let mut song = Song::from_iter(iter)?;
-                        song.file = v;
+                        song.file = v.into();
                         return Ok(LsInfoEntry::Song(song));
                     },
+                    "playlist" => {
+                        let playlist = Playlist::from_iter(iter::once(Ok((k, v))).chain(iter))?;
+                        return Ok(LsInfoEntry::Playlist(playlist));
+                    },
                     _ => return Err(Error::Parse(ParseError::BadPair))
                 }
             }
             return Err(Error::Proto(ProtoError::NotPair));
         }
-        return Err(Error::Proto(ProtoError::NotPair));
+        Err(Error::Proto(ProtoError::NotPair))
     }
 }
+
+/// Entry of `listall` response, without any metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListAllEntry {
+    /// Song file URI
+    File(String),
+    /// Directory path
+    Directory(String),
+    /// Stored playlist path
+    Playlist(String),
+}
+
+impl ListAllEntry {
+    pub(crate) fn from_pair((key, value): (String, String)) -> Result<ListAllEntry, Error> {
+        match &*key {
+            "file" => Ok(ListAllEntry::File(value)),
+            "directory" => Ok(ListAllEntry::Directory(value)),
+            "playlist" => Ok(ListAllEntry::Playlist(value)),
+            _ => Err(Error::Parse(ParseError::BadPair)),
+        }
+    }
+
+    /// Entry path, relative to the music directory
+    pub fn path(&self) -> &str {
+        match self {
+            ListAllEntry::File(path) | ListAllEntry::Directory(path) | ListAllEntry::Playlist(path) => path,
+        }
+    }
+}
+
+/// Keys starting a new entry in `lsinfo`-like responses
+pub(crate) const ENTRY_KEYS: &[&str] = &["file", "directory", "playlist"];
+
+/// Iterator over entries of `listallinfo` response, parsed as they are received
+///
+/// See [`StructIter`] for details.
+pub type LsInfoEntries<'a, S> = StructIter<'a, S, LsInfoEntry>;
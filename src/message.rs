@@ -7,13 +7,37 @@
 //!
 //! Also client can get asynchronous notifications about new messages from subscribed
 //! channels with `idle` command, by waiting for `message` subsystem events.
+//!
+//! Subscriptions made with [`subscription()`](Client::subscription) are cancelled
+//! automatically when the returned guard is dropped:
+//!
+//! ```rust,no_run
+//! # use mpd::{Channel, Client};
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let mut sub = conn.subscription(&Channel::new("remote").unwrap()).unwrap();
+//! let messages = sub.readmessages().unwrap();
+//! // unsubscribed here
+//! ```
+//!
+//! Simple request-response exchanges can use [`wait_for_message()`](Client::wait_for_message),
+//! which subscribes to a channel and waits until some messages arrive there.
+//!
+//! With `json` feature, structured payloads can be sent as JSON with
+//! [`send_json()`](Client::send_json) and received with [`read_json()`](Client::read_json).
 
+use crate::client::Client;
 use crate::convert::FromMap;
 use crate::error::{Error, ProtoError};
+use crate::idle::{Idle, Nonblocking, Subsystem};
+#[cfg(feature = "serde")]
+use crate::error::ParseError;
 use crate::proto::ToArguments;
 
 use std::collections::BTreeMap;
 use std::fmt;
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
 
 /// Message
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -99,3 +123,108 @@ impl ToArguments for &Channel {
         f(&self.to_string())
     }
 }
+
+/// Channel subscription, which is cancelled when the guard is dropped
+///
+/// The client is available through the guard while it's alive.
+#[must_use = "the subscription is cancelled when the guard is dropped"]
+pub struct Subscription<'a, S: 'a + Read + Write> {
+    pub(crate) client: &'a mut Client<S>,
+    pub(crate) channel: Channel,
+}
+
+impl<S: Read + Write> Subscription<'_, S> {
+    /// Subscribed channel
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+}
+
+impl<S: Read + Write> Deref for Subscription<'_, S> {
+    type Target = Client<S>;
+    fn deref(&self) -> &Client<S> {
+        self.client
+    }
+}
+
+impl<S: Read + Write> DerefMut for Subscription<'_, S> {
+    fn deref_mut(&mut self) -> &mut Client<S> {
+        self.client
+    }
+}
+
+impl<S: Read + Write> Drop for Subscription<'_, S> {
+    fn drop(&mut self) {
+        let _ = self.client.unsubscribe(&self.channel);
+    }
+}
+
+impl<S: Read + Write + Nonblocking> Client<S> {
+    /// Subscribe to a channel and wait up to the given timeout until some messages arrive
+    ///
+    /// The subscription is cancelled before returning, so the channel must not be subscribed
+    /// to already. All messages read are returned, including ones from other subscribed
+    /// channels. Empty list is returned if the time is out.
+    pub fn wait_for_message(&mut self, channel: &Channel, timeout: Duration) -> crate::error::Result<Vec<Message>> {
+        let deadline = Instant::now() + timeout;
+        let mut sub = self.subscription(channel)?;
+        let mut received = Vec::new();
+        loop {
+            received.extend(sub.readmessages()?);
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() || received.iter().any(|m| m.channel == *channel) {
+                return Ok(received);
+            }
+
+            // Messages which arrive while the guard is dropped are read on the next iteration
+            let _ = sub.idle(&[Subsystem::Message])?.get_timeout(left)?;
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: Read + Write> Client<S> {
+    /// Send a value encoded as JSON to a channel
+    ///
+    /// Values which can't be encoded (like maps with non-string keys) are reported
+    /// as [`ParseError::BadValue`], and nothing is sent.
+    pub fn send_json<T: serde::Serialize>(&mut self, channel: &Channel, value: &T) -> crate::error::Result<()> {
+        let message = serde_json::to_string(value).map_err(|e| Error::Parse(ParseError::BadValue(e.to_string())))?;
+        self.sendmessage(channel, &message)
+    }
+
+    /// Read queued messages from subscribed channels, decoding them from JSON
+    ///
+    /// Each message is decoded separately, so a malformed message doesn't make others lost.
+    pub fn read_json<T: serde::de::DeserializeOwned>(&mut self) -> crate::error::Result<Vec<(Channel, crate::error::Result<T>)>> {
+        let messages = self.readmessages()?;
+        Ok(messages
+            .into_iter()
+            .map(|m| {
+                let value = serde_json::from_str(&m.message).map_err(|e| Error::Parse(ParseError::BadValue(e.to_string())));
+                (m.channel, value)
+            })
+            .collect())
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use super::Channel;
+    use crate::error::{Error, ParseError};
+    use crate::testing::MockServer;
+    use std::collections::HashMap;
+
+    #[test]
+    fn send_json_unencodable() {
+        let server = MockServer::new();
+        let mut conn = server.client();
+        let channel = Channel::new("test").unwrap();
+        let value: HashMap<(u8, u8), u8> = vec![((1, 2), 3)].into_iter().collect();
+        match conn.send_json(&channel, &value) {
+            Err(Error::Parse(ParseError::BadValue(_))) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        server.verify();
+    }
+}
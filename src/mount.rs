@@ -8,11 +8,171 @@
 //!
 //! Possible, but inactive, mounts are named "neighbors" and can be
 //! listed with [`neighbors()`](crate::Client::neighbors) method.
+//!
+//! Storage URIs can be built with [`Storage`], which knows URI schemes of
+//! storage plugins shipped with MPD, and passes mount parameters as URI query:
+//!
+//! ```rust,no_run
+//! # use mpd::{Client, Storage};
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! conn.mount("nas", Storage::smb("nas.local", "music").param("user", "guest")).unwrap();
+//! ```
 
-use crate::convert::FromMap;
-use crate::error::{Error, ProtoError};
+use crate::convert::{percent_decode, percent_encode, FromMap};
+use crate::error::{Error, ParseError, ProtoError};
 
+use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// MPD storage plugin, as identified by the storage URI scheme
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StoragePlugin {
+    /// local directory, given by its absolute path
+    Local,
+    /// NFS share (`nfs://`)
+    Nfs,
+    /// SMB/CIFS share (`smb://`)
+    Smb,
+    /// removable drive managed by UDisks (`udisks://`)
+    Udisks,
+    /// WebDAV server (`http://` or `https://`), handled by curl plugin
+    WebDav,
+    /// any other URI scheme
+    Other(String),
+}
+
+impl StoragePlugin {
+    /// Detect storage plugin by the storage URI
+    pub fn from_uri(uri: &str) -> StoragePlugin {
+        match uri.split_once("://") {
+            None => StoragePlugin::Local,
+            Some((scheme, _)) => match &*scheme.to_ascii_lowercase() {
+                "file" => StoragePlugin::Local,
+                "nfs" => StoragePlugin::Nfs,
+                "smb" => StoragePlugin::Smb,
+                "udisks" => StoragePlugin::Udisks,
+                "http" | "https" => StoragePlugin::WebDav,
+                _ => StoragePlugin::Other(scheme.to_owned()),
+            },
+        }
+    }
+}
+
+/// Storage URI with optional mount parameters
+///
+/// Parameters are sent as URI query, which is how MPD storage plugins receive
+/// options like credentials or protocol versions. Values are percent-encoded.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Storage {
+    /// storage plugin handling the URI
+    pub plugin: StoragePlugin,
+    /// storage URI without parameters
+    pub location: String,
+    /// mount parameters
+    pub params: Vec<(String, String)>,
+}
+
+impl Storage {
+    fn new(plugin: StoragePlugin, location: String) -> Storage {
+        Storage { plugin, location, params: Vec::new() }
+    }
+
+    /// Local directory at the absolute path
+    pub fn local(path: &str) -> Storage {
+        Storage::new(StoragePlugin::Local, path.to_owned())
+    }
+
+    /// NFS export on the server
+    pub fn nfs(server: &str, export: &str) -> Storage {
+        Storage::new(StoragePlugin::Nfs, format!("nfs://{}/{}", server, export.trim_start_matches('/')))
+    }
+
+    /// SMB share on the server
+    pub fn smb(server: &str, share: &str) -> Storage {
+        Storage::new(StoragePlugin::Smb, format!("smb://{}/{}", server, share.trim_start_matches('/')))
+    }
+
+    /// Removable drive with given UDisks ID (like `by-uuid-...`)
+    pub fn udisks(id: &str) -> Storage {
+        Storage::new(StoragePlugin::Udisks, format!("udisks://{}", id))
+    }
+
+    /// WebDAV server at the HTTP(S) URL
+    pub fn webdav(url: &str) -> Storage {
+        Storage::new(StoragePlugin::WebDav, url.to_owned())
+    }
+
+    /// Add mount parameter
+    pub fn param(mut self, name: &str, value: &str) -> Storage {
+        self.params.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+impl fmt::Display for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.location)?;
+        for (i, (name, value)) in self.params.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { '?' } else { '&' }, percent_encode(name), percent_encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Storage {
+    type Err = ParseError;
+    fn from_str(uri: &str) -> Result<Storage, ParseError> {
+        let (location, query) = match uri.split_once('?') {
+            // Local paths can contain question marks
+            Some((location, query)) if location.contains("://") => (location, query),
+            _ => (uri, ""),
+        };
+        let params = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (name, value) = param.split_once('=').unwrap_or((param, ""));
+                Some((percent_decode(name)?, percent_decode(value)?))
+            })
+            .collect::<Option<_>>()
+            .ok_or(ParseError::BadValue(uri.to_owned()))?;
+        Ok(Storage { plugin: StoragePlugin::from_uri(location), location: location.to_owned(), params })
+    }
+}
+
+/// Storage URI accepted by [`mount()`](crate::Client::mount), either a string or [`Storage`]
+pub trait ToStorageUri {
+    /// Storage URI as sent to the server
+    fn to_storage_uri(&self) -> Cow<'_, str>;
+}
+
+impl ToStorageUri for str {
+    fn to_storage_uri(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToStorageUri for String {
+    fn to_storage_uri(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToStorageUri for Storage {
+    fn to_storage_uri(&self) -> Cow<'_, str> {
+        Cow::Owned(self.to_string())
+    }
+}
+
+impl<T: ToStorageUri + ?Sized> ToStorageUri for &T {
+    fn to_storage_uri(&self) -> Cow<'_, str> {
+        (**self).to_storage_uri()
+    }
+}
 
 /// Mount point
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -22,13 +182,24 @@ pub struct Mount {
     pub name: String,
     /// mount storage URI
     pub storage: String,
+    /// storage plugin, detected by the storage URI
+    pub plugin: StoragePlugin,
+}
+
+impl Mount {
+    /// Parse storage URI, splitting mount parameters out
+    pub fn storage_uri(&self) -> Result<Storage, ParseError> {
+        self.storage.parse()
+    }
 }
 
 impl FromMap for Mount {
     fn from_map(map: BTreeMap<String, String>) -> Result<Mount, Error> {
+        let storage = map.get("storage").map(|s| s.to_owned()).ok_or(Error::Proto(ProtoError::NoField("storage")))?;
         Ok(Mount {
             name: map.get("mount").map(|s| s.to_owned()).ok_or(Error::Proto(ProtoError::NoField("mount")))?,
-            storage: map.get("storage").map(|s| s.to_owned()).ok_or(Error::Proto(ProtoError::NoField("storage")))?,
+            plugin: StoragePlugin::from_uri(&storage),
+            storage,
         })
     }
 }
@@ -51,3 +222,23 @@ impl FromMap for Neighbor {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Storage, StoragePlugin};
+
+    #[test]
+    fn storage_uri() {
+        let storage = Storage::smb("nas.local", "/music").param("user", "guest").param("password", "a&b c");
+        let uri = storage.to_string();
+        assert_eq!(uri, "smb://nas.local/music?user=guest&password=a%26b%20c");
+        assert_eq!(uri.parse::<Storage>().unwrap(), storage);
+
+        assert_eq!(Storage::nfs("server", "export").to_string(), "nfs://server/export");
+        assert_eq!("/home/me/music?".parse::<Storage>().unwrap(), Storage::local("/home/me/music?"));
+        assert_eq!(StoragePlugin::from_uri("https://dav.example.com/music"), StoragePlugin::WebDav);
+        assert_eq!(StoragePlugin::from_uri("udisks://by-uuid-1234"), StoragePlugin::Udisks);
+        assert_eq!(StoragePlugin::from_uri("ftp://host"), StoragePlugin::Other("ftp".into()));
+        assert!("nfs://server/export?x=%zz".parse::<Storage>().is_err());
+    }
+}
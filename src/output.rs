@@ -1,7 +1,24 @@
 //! The module describes output
+//!
+//! Output ids are assigned in the order outputs appear in `mpd.conf`, so they may change
+//! after MPD restarts. [`OutputRef`] addresses an output by name instead:
+//!
+//! ```rust,no_run
+//! # use mpd::Client;
+//! use mpd::OutputRef;
+//!
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let mut speakers = OutputRef::new("Speakers");
+//! speakers.toggle(&mut conn).unwrap();
+//! ```
 
-use crate::convert::FromIter;
-use crate::error::{Error, ProtoError};
+use crate::client::Client;
+use crate::convert::{FromIter, Text};
+use crate::error::{Error, ErrorCode, ParseError, ProtoError, Result, ServerError};
+use crate::idle::Subsystem;
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
 
 /// Sound output
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -16,13 +33,30 @@ pub struct Output {
     /// enabled state
     pub enabled: bool,
     /// Runtime-configurable, plugin-specific attributes, such as "dop" for ALSA
-    pub attributes: Vec<(String, String)>
+    pub attributes: BTreeMap<String, String>
+}
+
+impl Output {
+    /// Get raw attribute value
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|v| v.as_str())
+    }
+
+    /// Whether DSD over PCM is enabled (ALSA outputs only)
+    pub fn dop(&self) -> Option<bool> {
+        self.attribute("dop").map(|v| v == "1")
+    }
+
+    /// Audio formats allowed for the output, empty if any format is allowed (ALSA outputs only)
+    pub fn allowed_formats(&self) -> Option<Vec<&str>> {
+        self.attribute("allowed_formats").map(|v| v.split_whitespace().collect())
+    }
 }
 
 impl FromIter for Output {
     // Implement FromIter directly so that we can parse plugin-specific attributes
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Output, Error> {
-        let mut attributes = Vec::new();
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V)>>>(iter: I) -> Result<Output> {
+        let mut attributes = BTreeMap::new();
         let mut name: Option<String> = None;  // panic if unnamed
         let mut plugin: Option<String> = None;  // panic if not found
         let mut id: u32 = 0;
@@ -30,17 +64,15 @@ impl FromIter for Output {
 
         for res in iter {
             let line = res?;
-            match &*line.0 {
-                "outputid" => { id = line.1.parse::<u32>()? },
-                "outputname" => { name.replace(line.1); },
-                "plugin" => { plugin.replace(line.1); },
-                "outputenabled" => enabled = line.1 == "1",
+            match line.0.as_ref() {
+                "outputid" => { id = line.1.as_ref().parse::<u32>()? },
+                "outputname" => { name.replace(line.1.into()); },
+                "plugin" => { plugin.replace(line.1.into()); },
+                "outputenabled" => enabled = line.1.as_ref() == "1",
                 "attribute" =>  {
-                    let terms: Vec<&str> = line.1.split("=").collect();
-                    if terms.len() != 2 {
-                        return Err(Error::Proto(ProtoError::NotPair));
-                    }
-                    attributes.push((terms[0].to_owned(), terms[1].to_owned()));
+                    // Values may contain '=' too, e.g. "allowed_formats=dsd64:=dop"
+                    let (key, value) = line.1.as_ref().split_once('=').ok_or(Error::Parse(ParseError::BadPair))?;
+                    attributes.insert(key.to_owned(), value.to_owned());
                 },
                 _ => {}
             }
@@ -63,3 +95,129 @@ impl FromIter for Output {
         })
     }
 }
+
+/// Output addressed by name
+///
+/// The output id is looked up on first use and cached. The cache is dropped when
+/// an [`output`](Subsystem::Output) idle event is handled, or when the server
+/// reports that the cached id doesn't exist anymore.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputRef {
+    name: String,
+    id: Option<u32>,
+}
+
+impl OutputRef {
+    /// Refer to the output with given name
+    pub fn new(name: &str) -> OutputRef {
+        OutputRef { name: name.to_owned(), id: None }
+    }
+
+    /// Output name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Resolve output id, using cached one if known
+    ///
+    /// Fails with [`ProtoError::NotFound`] if the server has no output with this name.
+    pub fn id<S: Read + Write>(&mut self, client: &mut Client<S>) -> Result<u32> {
+        if let Some(id) = self.id {
+            return Ok(id);
+        }
+
+        let output = client
+            .outputs()?
+            .into_iter()
+            .find(|output| output.name == self.name)
+            .ok_or_else(|| Error::Proto(ProtoError::NotFound(self.name.clone())))?;
+        self.id = Some(output.id);
+        Ok(output.id)
+    }
+
+    /// Drop cached output id, so it is looked up again on next use
+    pub fn invalidate(&mut self) {
+        self.id = None;
+    }
+
+    /// Drop cached output id if idle events include output changes
+    pub fn handle(&mut self, events: &[Subsystem]) {
+        if events.contains(&Subsystem::Output) {
+            self.invalidate();
+        }
+    }
+
+    /// Enable the output
+    pub fn enable<S: Read + Write>(&mut self, client: &mut Client<S>) -> Result<()> {
+        self.run(client, |client, id| client.out_enable(id))
+    }
+
+    /// Disable the output
+    pub fn disable<S: Read + Write>(&mut self, client: &mut Client<S>) -> Result<()> {
+        self.run(client, |client, id| client.out_disable(id))
+    }
+
+    /// Toggle the output
+    pub fn toggle<S: Read + Write>(&mut self, client: &mut Client<S>) -> Result<()> {
+        self.run(client, |client, id| client.out_toggle(id))
+    }
+
+    /// Set runtime attribute of the output
+    pub fn set<S: Read + Write>(&mut self, client: &mut Client<S>, attribute: &str, value: &str) -> Result<()> {
+        self.run(client, |client, id| client.output_set(id, attribute, value))
+    }
+
+    /// Run a command with output id, looking it up again if the cached id turns out to be stale
+    fn run<S, F>(&mut self, client: &mut Client<S>, f: F) -> Result<()>
+    where
+        S: Read + Write,
+        F: Fn(&mut Client<S>, u32) -> Result<()>,
+    {
+        let cached = self.id.is_some();
+        let id = self.id(client)?;
+        match f(client, id) {
+            Err(Error::Server(ServerError { code: ErrorCode::NoExist, .. })) if cached => {
+                self.invalidate();
+                let id = self.id(client)?;
+                f(client, id)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Output, OutputRef};
+    use crate::capture::Replay;
+    use crate::client::Client;
+    use crate::convert::FromIter;
+    use crate::error::{Error, ProtoError};
+
+    fn parse(lines: &[(&str, &str)]) -> Output {
+        Output::from_iter(lines.iter().map(|&(k, v)| Ok((k.to_owned(), v.to_owned())))).unwrap()
+    }
+
+    #[test]
+    fn attributes() {
+        let output = parse(&[
+            ("outputid", "0"),
+            ("outputname", "alsa"),
+            ("plugin", "alsa"),
+            ("outputenabled", "1"),
+            ("attribute", "allowed_formats=96000:16:* dsd64:=dop"),
+            ("attribute", "dop=0"),
+        ]);
+        assert_eq!(output.dop(), Some(false));
+        assert_eq!(output.allowed_formats(), Some(vec!["96000:16:*", "dsd64:=dop"]));
+        assert_eq!(output.attribute("missing"), None);
+    }
+
+    #[test]
+    fn unknown_output() {
+        let capture: &[u8] = b"S 14\nOK MPD 0.23.5\n\nC 8\noutputs\n\nS 62\noutputid: 0\noutputname: alsa\nplugin: alsa\noutputenabled: 1\nOK\n\n";
+        let mut conn = Client::new(Replay::from_reader(capture).unwrap()).unwrap();
+        let result = OutputRef::new("Speakers").id(&mut conn);
+        assert!(matches!(result, Err(Error::Proto(ProtoError::NotFound(ref name))) if name == "Speakers"));
+    }
+}
@@ -1,11 +1,14 @@
 //! The module defines playlist data structures
 
-use crate::convert::FromMap;
+use crate::convert::{FromMap, ToQueueRange};
+#[cfg(feature = "chrono")]
+use crate::convert::parse_timestamp;
 use crate::error::{Error, ProtoError};
 use crate::proto::*;
 
 use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::ops::Range;
 
 /// Save mode when calling save().
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -53,7 +56,11 @@ pub enum EditAction<'a> {
     /// Remove the song at the given position from the playlist of the given name.
     ///
     /// Fields: playlist name, position to remove.
-    Delete(Cow<'a, str>, u32)
+    Delete(Cow<'a, str>, u32),
+    /// Remove songs in the given range of positions from the playlist (MPD 0.23.3+).
+    ///
+    /// Fields: playlist name, range of positions to remove (end excluded).
+    DeleteRange(Cow<'a, str>, Range<u32>)
 }
 
 impl<'a> ToArguments for EditAction<'a> {
@@ -71,7 +78,8 @@ impl<'a> ToArguments for EditAction<'a> {
             },
             Self::Clear(name) => name.to_arguments(f),
             Self::Move(name, old, new) => (name, *old, *new).to_arguments(f),
-            Self::Delete(name, pos) => (name, *pos).to_arguments(f)
+            Self::Delete(name, pos) => (name, *pos).to_arguments(f),
+            Self::DeleteRange(name, range) => (name, range.clone().to_range()).to_arguments(f)
         }
     }
 }
@@ -83,7 +91,7 @@ impl<'a> EditAction<'a> {
             Self::Add(_, _, _) => "playlistadd",
             Self::Clear(_) => "playlistclear",
             Self::Move(_, _, _) => "playlistmove",
-            Self::Delete(_, _) => "playlistdelete"
+            Self::Delete(_, _) | Self::DeleteRange(_, _) => "playlistdelete"
         }
     }
 }
@@ -99,6 +107,14 @@ pub struct Playlist {
     pub last_mod: String,
 }
 
+#[cfg(feature = "chrono")]
+impl Playlist {
+    /// Last modification time, if valid
+    pub fn last_modified(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        parse_timestamp(&self.last_mod)
+    }
+}
+
 impl FromMap for Playlist {
     fn from_map(map: BTreeMap<String, String>) -> Result<Playlist, Error> {
         Ok(Playlist {
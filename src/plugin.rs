@@ -1,9 +1,20 @@
 //! The module defines decoder plugin data structures
+//!
+//! Decoders list is returned by [`decoders()`](crate::Client::decoders), and can be used
+//! to check if the server is able to play some file before adding it to the queue:
+//!
+//! ```rust,no_run
+//! # use mpd::Client;
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let decoders = conn.decoders().unwrap();
+//! println!("{}", mpd::plugin::can_play(&decoders, "music/track.opus"));
+//! ```
 
-use crate::convert::FromIter;
+use crate::convert::{FromIter, Text};
 use crate::error::Error;
 
 /// Decoder plugin
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Plugin {
     /// name
@@ -14,31 +25,55 @@ pub struct Plugin {
     pub mime_types: Vec<String>,
 }
 
+/// Decoder plugin, as listed by `decoders` command
+pub type Decoder = Plugin;
+
+impl Plugin {
+    /// Check if the plugin can decode given file (by its suffix) or MIME type
+    ///
+    /// Both suffixes and MIME types are compared case-insensitively.
+    pub fn can_play(&self, path_or_mime: &str) -> bool {
+        if self.mime_types.iter().any(|mime| mime.eq_ignore_ascii_case(path_or_mime)) {
+            return true;
+        }
+        let name = path_or_mime.rsplit('/').next().unwrap_or(path_or_mime);
+        match name.rsplit_once('.') {
+            Some((_, suffix)) => self.suffixes.iter().any(|s| s.eq_ignore_ascii_case(suffix)),
+            None => false,
+        }
+    }
+}
+
+/// Check if any of the decoders can play given file (by its suffix) or MIME type
+pub fn can_play(decoders: &[Plugin], path_or_mime: &str) -> bool {
+    decoders.iter().any(|decoder| decoder.can_play(path_or_mime))
+}
+
 impl FromIter for Vec<Plugin> {
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Self, Error> {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Self, Error> {
         let mut result = Vec::new();
         let mut plugin: Option<Plugin> = None;
         for reply in iter {
             let (a, b) = reply?;
-            match &*a {
+            match a.as_ref() {
                 "plugin" => {
                     if let Some(p) = plugin {
                         result.push(p)
                     }
 
-                    plugin = Some(Plugin { name: b, suffixes: Vec::new(), mime_types: Vec::new() });
+                    plugin = Some(Plugin { name: b.into(), suffixes: Vec::new(), mime_types: Vec::new() });
                 }
                 "mime_type" => {
                     if let Some(p) = plugin.as_mut() {
-                        p.mime_types.push(b)
+                        p.mime_types.push(b.into())
                     }
                 }
                 "suffix" => {
                     if let Some(p) = plugin.as_mut() {
-                        p.suffixes.push(b)
+                        p.suffixes.push(b.into())
                     }
                 }
-                _ => unreachable!(),
+                _ => (),
             }
         }
         if let Some(p) = plugin {
@@ -47,3 +82,19 @@ impl FromIter for Vec<Plugin> {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn can_play() {
+        let pairs = [("plugin", "flac"), ("suffix", "flac"), ("mime_type", "audio/flac"), ("plugin", "mad"), ("suffix", "mp3"), ("mime_type", "audio/mpeg")];
+        let decoders = Vec::<Plugin>::from_iter(pairs.iter().map(|&(k, v)| Ok((k.to_owned(), v.to_owned())))).unwrap();
+        assert_eq!(decoders.len(), 2);
+        assert!(super::can_play(&decoders, "Music/Some.Artist/track.FLAC"));
+        assert!(super::can_play(&decoders, "audio/MPEG"));
+        assert!(!super::can_play(&decoders, "track.ogg"));
+        assert!(!super::can_play(&decoders, "flac"));
+    }
+}
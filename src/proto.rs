@@ -4,31 +4,235 @@
 use bufstream::BufStream;
 
 use crate::convert::FromIter;
-use crate::error::{Error, ParseError, ProtoError, Result};
-use crate::reply::Reply;
+use crate::error::{CommandIoError, Error, ParseError, ParseWarning, ProtoError, Result};
+use crate::reply::{parse_line, Line, Reply};
+use crate::trace::{self, CommandSpan};
 
 use std::borrow::Cow;
 use std::fmt;
-use std::io::{self, Lines, Read, Write};
+use std::io::{self, BufRead, Read, Write};
 use std::result::Result as StdResult;
 use std::str::FromStr;
-use fxhash::FxHashSet;
+use std::time::Duration;
 
-pub struct Pairs<I>(pub I);
+/// Source of response lines for [`Pairs`], each borrowed until the next one is read
+pub trait ResponseLines {
+    fn next_line(&mut self) -> Option<io::Result<&str>>;
 
-impl<I> Iterator for Pairs<I>
-where I: Iterator<Item = io::Result<String>>
-{
+    /// Warnings list, if lines which are not pairs should be skipped
+    fn warnings(&mut self) -> Option<&mut Vec<ParseWarning>> {
+        None
+    }
+}
+
+impl ResponseLines for std::str::Lines<'_> {
+    fn next_line(&mut self) -> Option<io::Result<&str>> {
+        self.next().map(Ok)
+    }
+}
+
+/// Response pairs, ending after `OK` line or the first error
+pub struct Pairs<I> {
+    lines: I,
+    done: bool,
+}
+
+impl<I> Pairs<I> {
+    pub fn new(lines: I) -> Pairs<I> {
+        Pairs { lines, done: false }
+    }
+}
+
+impl<I: ResponseLines> Iterator for Pairs<I> {
     type Item = Result<(String, String)>;
     fn next(&mut self) -> Option<Result<(String, String)>> {
-        let reply: Option<Result<Reply>> =
-            self.0.next().map(|v| v.map_err(Error::Io).and_then(|s| s.parse::<Reply>().map_err(Error::Parse)));
-        match reply {
-            Some(Ok(Reply::Pair(a, b))) => Some(Ok((a, b))),
-            None | Some(Ok(Reply::Ok)) => None,
-            Some(Ok(Reply::Ack(e))) => Some(Err(Error::Server(e))),
-            Some(Err(e)) => Some(Err(e)),
+        if self.done {
+            return None;
         }
+        loop {
+            let result = match self.lines.next_line() {
+                Some(Ok(line)) => match parse_line(line) {
+                    Ok(Line::Pair(a, b)) => return Some(Ok((a.to_owned(), b.to_owned()))),
+                    Ok(Line::Ok) => None,
+                    Ok(Line::Ack(e)) => Some(Err(Error::Server(e))),
+                    Err(error) => {
+                        let warning = ParseWarning { line: line.to_owned(), error };
+                        match self.lines.warnings() {
+                            Some(warnings) => {
+                                warnings.push(warning);
+                                continue;
+                            }
+                            None => Some(Err(Error::Parse(warning.error))),
+                        }
+                    }
+                },
+                Some(Err(e)) => Some(Err(Error::Io(e))),
+                None => Some(Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by server")))),
+            };
+            self.done = true;
+            return result;
+        }
+    }
+}
+
+/// Read a line into the buffer, without trailing newline, `None` at the end of stream
+pub fn read_line_into<'b, R: BufRead>(reader: &mut R, buf: &'b mut Vec<u8>) -> io::Result<Option<&'b str>> {
+    buf.clear();
+    if reader.read_until(b'\n', buf)? == 0 {
+        return Ok(None);
+    }
+    if buf.ends_with(b"\n") {
+        buf.pop();
+    }
+    std::str::from_utf8(buf).map(Some).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
+}
+
+/// Response lines read into the client's reusable buffer, attaching the running command name to I/O errors
+pub struct CommandLines<'a, S: Read + Write> {
+    pub socket: &'a mut BufStream<S>,
+    pub line: &'a mut Vec<u8>,
+    pub command: &'a str,
+    pub span: &'a CommandSpan,
+    pub warnings: Option<&'a mut Vec<ParseWarning>>,
+    pub done: bool,
+}
+
+impl<S: Read + Write> ResponseLines for CommandLines<'_, S> {
+    fn next_line(&mut self) -> Option<io::Result<&str>> {
+        if self.done {
+            return None;
+        }
+        match read_line_into(self.socket, self.line) {
+            Ok(Some(line)) => {
+                trace::received(self.span, line);
+                Some(Ok(line))
+            }
+            Ok(None) => {
+                self.done = true;
+                Some(Err(CommandIoError::wrap(self.command, io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by server"))))
+            }
+            Err(e) => Some(Err(CommandIoError::wrap(self.command, e))),
+        }
+    }
+
+    fn warnings(&mut self) -> Option<&mut Vec<ParseWarning>> {
+        self.warnings.as_deref_mut()
+    }
+}
+
+/// Check of a single field value, see [`check_value()`]
+type CheckValue = fn(&str, &str) -> StdResult<(), ParseError>;
+
+/// Pairs of a complete response, borrowed from the response text
+///
+/// Iteration stops at `OK` or `list_OK` line, `ACK` line or missing terminating line
+/// is yielded as an error.
+pub struct ResponsePairs<'a> {
+    lines: std::str::Lines<'a>,
+    done: bool,
+    warnings: Option<&'a mut Vec<ParseWarning>>,
+    check: Option<CheckValue>,
+}
+
+impl<'a> ResponsePairs<'a> {
+    pub fn new(response: &'a str) -> ResponsePairs<'a> {
+        ResponsePairs { lines: response.lines(), done: false, warnings: None, check: None }
+    }
+
+    /// Skip lines which are not pairs if `warnings` are given, collecting warnings about them
+    ///
+    /// When parsed into structures, pairs with values rejected by the structure are skipped too.
+    pub fn lenient(mut self, warnings: Option<&'a mut Vec<ParseWarning>>) -> ResponsePairs<'a> {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Parse pairs into a list of structures, each starting with one of `keys` fields
+    ///
+    /// Pairs before the first key field are skipped.
+    pub fn parse_structs<T: FromIter>(mut self, keys: &[&str]) -> Result<Vec<T>> {
+        self.check_values::<T>();
+        let mut result = Vec::new();
+        let mut entry: Vec<(&str, &str)> = Vec::new();
+        let mut started = false;
+        for pair in self {
+            let (key, value) = pair?;
+            if keys.iter().any(|k| key.eq_ignore_ascii_case(k)) {
+                if started {
+                    result.push(T::from_iter(entry.drain(..).map(Ok))?);
+                }
+                started = true;
+            }
+            if started {
+                entry.push((key, value));
+            }
+        }
+        if started {
+            result.push(T::from_iter(entry.into_iter().map(Ok))?);
+        }
+        Ok(result)
+    }
+
+    /// Parse pairs into a structure
+    pub fn parse_struct<T: FromIter>(mut self) -> Result<T> {
+        self.check_values::<T>();
+        T::from_iter(self)
+    }
+
+    fn check_values<T: FromIter>(&mut self) {
+        if self.warnings.is_some() {
+            self.check = Some(check_value::<T>);
+        }
+    }
+}
+
+/// Check if the structure accepts value of a single field
+///
+/// Parsers stop at the first invalid value, so a lenient client checks each pair
+/// on its own beforehand and skips rejected ones. Missing fields are reported
+/// as protocol errors, which are ignored here.
+pub fn check_value<T: FromIter>(key: &str, value: &str) -> StdResult<(), ParseError> {
+    match T::from_iter(std::iter::once(Ok((key, value)))) {
+        Err(Error::Parse(e)) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl<'a> Iterator for ResponsePairs<'a> {
+    type Item = Result<(&'a str, &'a str)>;
+    fn next(&mut self) -> Option<Result<(&'a str, &'a str)>> {
+        while !self.done {
+            let line = match self.lines.next() {
+                Some(line) => line,
+                None => {
+                    self.done = true;
+                    return Some(Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by server"))));
+                }
+            };
+            let error = match parse_line(line) {
+                Ok(Line::Pair(key, value)) => match self.check.map_or(Ok(()), |check| check(key, value)) {
+                    Ok(()) => return Some(Ok((key, value))),
+                    Err(error) => error,
+                },
+                Err(error) => error,
+                Ok(Line::Ok) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(Line::Ack(e)) => {
+                    self.done = true;
+                    return Some(Err(Error::Server(e)));
+                }
+            };
+            match self.warnings.as_deref_mut() {
+                Some(warnings) => warnings.push(ParseWarning { line: line.to_owned(), error }),
+                None => {
+                    self.done = true;
+                    return Some(Err(Error::Parse(error)));
+                }
+            }
+        }
+        None
     }
 }
 
@@ -40,9 +244,7 @@ pub struct Maps<'a, I: 'a> {
     first: bool
 }
 
-impl<'a, I> Iterator for Maps<'a, I>
-where I: Iterator<Item = io::Result<String>>
-{
+impl<'a, I: ResponseLines> Iterator for Maps<'a, I> {
     type Item = Result<Vec<(String, String)>>;
     fn next(&mut self) -> Option<Result<Vec<(String, String)>>> {
         if self.done {
@@ -58,7 +260,7 @@ where I: Iterator<Item = io::Result<String>>
         loop {
             match self.pairs.next() {
                 Some(Ok((a, b))) => {
-                    if a.to_lowercase() == self.sep {
+                    if a.eq_ignore_ascii_case(&self.sep) {
                         self.value = Some(b);
                         if self.first {
                             self.first = false;
@@ -89,16 +291,14 @@ where I: Iterator<Item = io::Result<String>>
 // Variant of Maps that can take multiple separators. Used for lsinfo.
 pub struct MultiSepMaps<'a, I: 'a> {
     pairs: &'a mut Pairs<I>,
-    seps: FxHashSet<String>,
+    seps: Vec<String>,
     last_sep: Option<String>,
     value: Option<String>,
     done: bool,
     first: bool,
 }
 
-impl<'a, I> Iterator for MultiSepMaps<'a, I>
-where I: Iterator<Item = io::Result<String>>
-{
+impl<'a, I: ResponseLines> Iterator for MultiSepMaps<'a, I> {
     type Item = Result<Vec<(String, String)>>;
     fn next(&mut self) -> Option<Result<Vec<(String, String)>>> {
         if self.done {
@@ -114,7 +314,7 @@ where I: Iterator<Item = io::Result<String>>
         loop {
             match self.pairs.next() {
                 Some(Ok((a, b))) => {
-                    if self.seps.contains(a.to_lowercase().as_str()) {
+                    if self.seps.iter().any(|sep| a.eq_ignore_ascii_case(sep)) {
                         self.value = Some(b);
                         self.last_sep = Some(a);
                         if self.first {
@@ -143,18 +343,14 @@ where I: Iterator<Item = io::Result<String>>
 }
 
 
-impl<I> Pairs<I>
-where I: Iterator<Item = io::Result<String>>
-{
+impl<I: ResponseLines> Pairs<I> {
     pub fn split<'a, 'b: 'a>(&'a mut self, f: &'b str) -> Maps<'a, I> {
         Maps { pairs: self, sep: f.to_lowercase(), value: None, done: false, first: true }
     }
 
     pub fn split_multisep<'a, 'b: 'a>(&'a mut self, f: &[&'static str]) -> MultiSepMaps<'a, I> {
-        let mut seps: FxHashSet<String> = FxHashSet::default();
-        for elem in f {
-            seps.insert(elem.to_lowercase());
-        }
+        // There are only a couple of separators, so a linear scan beats hashing here
+        let seps = f.iter().map(|elem| elem.to_lowercase()).collect();
         MultiSepMaps { pairs: self, seps, last_sep: None, value: None, done: false, first: true }
     }
 }
@@ -165,26 +361,34 @@ pub trait Proto {
     type Stream: Read + Write;
 
     fn read_bytes(&mut self, bytes: usize) -> Result<Vec<u8>>;
-    fn read_line(&mut self) -> Result<String>;
-    fn read_pairs(&mut self) -> Pairs<Lines<&mut BufStream<Self::Stream>>>;
+    /// Read a single response line, without trailing newline
+    fn read_line(&mut self) -> Result<&str>;
+    fn read_pairs(&mut self) -> Pairs<CommandLines<'_, Self::Stream>>;
+    /// Read a complete response, up to `OK`, `list_OK` or `ACK` line,
+    /// skipping malformed pairs if the client is lenient
+    fn read_response(&mut self) -> Result<ResponsePairs<'_>>;
 
     fn run_command_list<I>(&mut self, commands_args: &[(&str, I)]) -> Result<()>
     where I: ToArguments;
+    /// Same as `run_command_list`, but uses `command_list_ok_begin`, so each
+    /// command's response is terminated with `list_OK` and can be read separately.
+    fn run_command_list_ok<I>(&mut self, commands_args: &[(&str, I)]) -> Result<()>
+    where I: ToArguments;
     fn run_command<I>(&mut self, command: &str, arguments: I) -> Result<()>
     where I: ToArguments;
 
     fn read_structs<'a, T>(&'a mut self, key: &'static str) -> Result<Vec<T>>
     where T: 'a + FromIter {
-        self.read_pairs().split(key).map(|v| v.and_then(|v| FromIter::from_iter(v.into_iter().map(Ok)))).collect()
+        self.read_response()?.parse_structs(&[key])
     }
 
     fn read_multisep_structs<'a, T>(&'a mut self, keys: &[&'static str]) -> Result<Vec<T>>
     where T: 'a + FromIter {
-        self.read_pairs().split_multisep(keys).map(|v| v.and_then(|v| FromIter::from_iter(v.into_iter().map(Ok)))).collect()
+        self.read_response()?.parse_structs(keys)
     }
 
     fn read_list(&mut self, key: &str) -> Result<Vec<String>> {
-        self.read_pairs().filter(|r| r.as_ref().map(|(a, _)| &a.to_lowercase() == key).unwrap_or(true)).map(|r| r.map(|(_, b)| b)).collect()
+        self.read_pairs().filter(|r| r.as_ref().map(|(a, _)| a.eq_ignore_ascii_case(key)).unwrap_or(true)).map(|r| r.map(|(_, b)| b)).collect()
     }
 
     fn read_struct<'a, T>(&'a mut self) -> Result<T>
@@ -192,13 +396,12 @@ pub trait Proto {
         T: 'a + FromIter,
         Self::Stream: 'a,
     {
-        FromIter::from_iter(self.read_pairs())
+        self.read_response()?.parse_struct()
     }
 
     fn drain(&mut self) -> Result<()> {
         loop {
-            let reply = self.read_line()?;
-            match &*reply {
+            match self.read_line()? {
                 "OK" | "list_OK" => break,
                 _ => (),
             }
@@ -287,7 +490,17 @@ impl <'a> ToArguments for &Cow<'a, str> {
     }
 }
 
-impl <'a, T: ToArguments> ToArguments for Vec<T> {
+impl<T: ToArguments> ToArguments for Option<T> {
+    fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
+    where F: FnMut(&str) -> StdResult<(), E> {
+        match self {
+            Some(arg) => arg.to_arguments(f),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: ToArguments> ToArguments for Vec<T> {
     fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
     where F: FnMut(&str) -> StdResult<(), E> {
         for arg in self.iter() {
@@ -308,16 +521,35 @@ macro_rules! argument_for_display {
     };
 }
 
+impl ToArguments for bool {
+    fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
+    where F: FnMut(&str) -> StdResult<(), E> {
+        f(if *self { "1" } else { "0" })
+    }
+}
+
+impl ToArguments for Duration {
+    fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
+    where F: FnMut(&str) -> StdResult<(), E> {
+        f(&self.as_secs_f64().to_string())
+    }
+}
+
 argument_for_display! {i8}
 argument_for_display! {u8}
+argument_for_display! {i32}
 argument_for_display! {u32}
+argument_for_display! {u64}
 argument_for_display! {f32}
 argument_for_display! {f64}
 argument_for_display! {usize}
 argument_for_display! {crate::status::ReplayGain}
 argument_for_display! {crate::song::Id}
 argument_for_display! {crate::song::Range}
+argument_for_display! {crate::song::QueuePosition}
 argument_for_display! {crate::message::Channel}
+argument_for_display! {crate::sticker::StickerOp}
+argument_for_display! {crate::version::ProtocolFeature}
 
 macro_rules! argument_for_tuple {
     ( $($t:ident: $T: ident),+ ) => {
@@ -341,7 +573,7 @@ argument_for_tuple! {t0: T0, t1: T1, t2: T2, t3: T3}
 argument_for_tuple! {t0: T0, t1: T1, t2: T2, t3:T3, t4: T4}
 argument_for_tuple! {t0: T0, t1: T1, t2: T2, t3:T3, t4: T4, t5: T5}
 
-impl<'a, T: ToArguments> ToArguments for &'a [T] {
+impl<T: ToArguments> ToArguments for &[T] {
     fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
     where F: FnMut(&str) -> StdResult<(), E> {
         for arg in *self {
@@ -355,13 +587,109 @@ pub struct Quoted<'a, D: fmt::Display + 'a + ?Sized>(pub &'a D);
 
 impl<'a, D: fmt::Display + 'a + ?Sized> fmt::Display for Quoted<'a, D> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        let unquoted = format!("{}", self.0);
-        if unquoted.is_empty() {
-            // return Ok(());
+        formatter.write_str("\"")?;
+        fmt::Write::write_fmt(&mut Escaped(formatter), format_args!("{}", self.0))?;
+        formatter.write_str("\"")
+    }
+}
+
+/// Writer escaping backslashes and double quotes on the fly
+struct Escaped<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl fmt::Write for Escaped<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut rest = s;
+        while let Some(i) = rest.find(['\\', '"']) {
+            self.0.write_str(&rest[..i])?;
+            self.0.write_str("\\")?;
+            self.0.write_str(&rest[i..i + 1])?;
+            rest = &rest[i + 1..];
         }
-        let quoted = unquoted.replace('\\', r"\\").replace('"', r#"\""#);
-        formatter.write_fmt(format_args!("\"{}\"", &quoted))
+        self.0.write_str(rest)
     }
 }
 
 // }}}
+
+#[cfg(test)]
+mod test {
+    use super::{Proto, Quoted, ResponsePairs};
+    use crate::capture::Replay;
+    use crate::client::Client;
+    use crate::error::ParseWarning;
+    use crate::song::Song;
+    use crate::status::{State, Status};
+
+    #[test]
+    fn truncated_pairs() {
+        let capture: &[u8] = b"S 14\nOK MPD 0.23.5\n\nC 7\nstatus\n\nS 10\nvolume: 5\n\n";
+        let mut conn = Client::new(Replay::from_reader(capture).unwrap()).unwrap();
+        conn.run_command("status", ()).unwrap();
+        let mut pairs = conn.read_pairs();
+        assert_eq!(pairs.next().unwrap().unwrap(), ("volume".to_owned(), "5".to_owned()));
+        assert!(pairs.next().unwrap().is_err());
+        assert!(pairs.next().is_none());
+        assert!(pairs.next().is_none());
+    }
+
+    #[test]
+    fn quoted() {
+        assert_eq!(Quoted(r#"a "b" \c"#).to_string(), r#""a \"b\" \\c""#);
+        assert_eq!(Quoted(&42).to_string(), r#""42""#);
+        assert_eq!(Quoted("").to_string(), r#""""#);
+    }
+
+    #[test]
+    fn arguments() {
+        use crate::codec::encode_command;
+        use std::time::Duration;
+
+        assert_eq!(encode_command("random", true), "random \"1\"\n");
+        assert_eq!(encode_command("seekcur", Duration::from_millis(1500)), "seekcur \"1.5\"\n");
+        assert_eq!(encode_command("test", (-1i32, u64::MAX, None::<u8>)), "test \"-1\" \"18446744073709551615\"\n");
+    }
+
+    #[test]
+    fn lenient() {
+        let response = "volume: loud\nstate: play\nOK\n";
+        assert!(ResponsePairs::new(response).parse_struct::<Status>().is_err());
+
+        let mut warnings: Vec<ParseWarning> = Vec::new();
+        let status: Status = ResponsePairs::new(response).lenient(Some(&mut warnings)).parse_struct().unwrap();
+        assert_eq!(status.state, State::Play);
+        assert_eq!(warnings.iter().map(|w| &*w.line).collect::<Vec<_>>(), vec!["volume: loud"]);
+
+        warnings.clear();
+        let response = "file: a.flac\nTime: long\nno separator\nfile: b.flac\nTime: 10\nOK\n";
+        assert!(ResponsePairs::new(response).parse_structs::<Song>(&["file"]).is_err());
+        let songs: Vec<Song> = ResponsePairs::new(response).lenient(Some(&mut warnings)).parse_structs(&["file"]).unwrap();
+        assert_eq!(songs.len(), 2);
+        assert_eq!(songs[0].duration, None);
+        assert_eq!(warnings.iter().map(|w| &*w.line).collect::<Vec<_>>(), vec!["Time: long", "no separator"]);
+    }
+
+    #[test]
+    fn lenient_client() {
+        let capture: Vec<u8> = [
+            ('S', "OK MPD 0.23.5\n"),
+            ('C', "playlistinfo\n"),
+            ('S', "file: a.flac\nTime: long\nfile: b.flac\nno separator\nOK\n"),
+            ('C', "status\n"),
+            ('S', "volume: 5\nno separator\nOK\n"),
+        ]
+        .iter()
+        .flat_map(|(dir, data)| format!("{} {}\n{}\n", dir, data.len(), data).into_bytes())
+        .collect();
+        let mut conn = Client::new(Replay::from_reader(&capture[..]).unwrap()).unwrap();
+        conn.set_lenient(true);
+
+        let songs = conn.queue_iter().unwrap().collect::<Result<Vec<Song>, _>>().unwrap();
+        assert_eq!(songs.iter().map(|song| &*song.file).collect::<Vec<_>>(), vec!["a.flac", "b.flac"]);
+        conn.run_command("status", ()).unwrap();
+        let pairs = conn.read_pairs().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(pairs, vec![("volume".to_owned(), "5".to_owned())]);
+
+        let warnings = conn.take_warnings();
+        assert_eq!(warnings.iter().map(|w| &*w.line).collect::<Vec<_>>(), vec!["Time: long", "no separator", "no separator"]);
+    }
+}
@@ -0,0 +1,526 @@
+//! The module defines helpers operating on the play queue as a whole
+//!
+//! MPD keeps only song URIs in the queue, so after a library reorganization
+//! queued entries may point to files which do not exist anymore. The
+//! [`reconcile_queue()`](Client::reconcile_queue) method is meant to be called
+//! when a [`database`](crate::Subsystem::Database) idle event arrives, and finds
+//! such entries, optionally repairing them.
+//!
+//! [`QueueEntry`] describes a song to add with its position, range, priority and
+//! tags, which are all applied by [`enqueue()`](Client::enqueue):
+//!
+//! ```rust,no_run
+//! # use mpd::Client;
+//! use mpd::queue::QueueEntry;
+//! use mpd::song::QueuePosition;
+//! use std::time::Duration;
+//!
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let entry = QueueEntry::new("album/intro.flac")
+//!     .position(QueuePosition::AfterCurrent(0))
+//!     .range(Duration::from_secs(5)..)
+//!     .priority(10);
+//! let id = conn.enqueue(entry).unwrap();
+//! ```
+//!
+//! [`QueueSync`] keeps a local copy of the queue up to date, fetching only
+//! the entries changed since the last seen queue version:
+//!
+//! ```rust,no_run
+//! # use mpd::{Client, Idle, Subsystem};
+//! use mpd::queue::QueueSync;
+//!
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let mut idle = Client::connect("127.0.0.1:6600").unwrap();
+//! let mut queue = QueueSync::new();
+//! queue.update(&mut conn).unwrap();
+//! loop {
+//!     let events = idle.wait(&[Subsystem::Queue]).unwrap();
+//!     if queue.handle(&mut conn, &events).unwrap() {
+//!         println!("{} songs in queue", queue.songs().len());
+//!     }
+//! }
+//! ```
+
+use crate::client::Client;
+use crate::convert::ToSongRange;
+use crate::error::Result;
+use crate::idle::Subsystem;
+use crate::proto::Proto;
+use crate::search::{Query, Term};
+use crate::song::{Id, PosIdChange, QueuePosition, Range, Song};
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::mem;
+use std::time::Duration;
+
+/// Max number of commands sent in one command list while checking the queue
+const BATCH_SIZE: usize = 256;
+
+/// Max difference in song durations to consider two songs the same
+const DURATION_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// A queue entry which doesn't match the database anymore
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueIssue {
+    /// The file was not found, but a matching song exists under another URI
+    Moved {
+        /// queue ID of the entry
+        id: Id,
+        /// queue position of the entry
+        pos: u32,
+        /// the URI which doesn't exist anymore
+        file: String,
+        /// the song found in the database instead
+        song: Box<Song>,
+    },
+    /// The file was not found, and nothing similar could be found in the database
+    Missing {
+        /// queue ID of the entry
+        id: Id,
+        /// queue position of the entry
+        pos: u32,
+        /// the URI which doesn't exist anymore
+        file: String,
+    },
+}
+
+impl QueueIssue {
+    /// Queue ID of the affected entry
+    pub fn id(&self) -> Id {
+        match *self {
+            QueueIssue::Moved { id, .. } | QueueIssue::Missing { id, .. } => id,
+        }
+    }
+}
+
+/// A song to add into the queue, along with its queue-only settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueEntry {
+    uri: String,
+    position: Option<QueuePosition>,
+    range: Option<Range>,
+    priority: Option<u8>,
+    tags: Vec<(String, String)>,
+}
+
+impl QueueEntry {
+    /// Describe a song (or a stream) with given URI, appended to the end of the queue by default
+    pub fn new<U: Into<String>>(uri: U) -> QueueEntry {
+        QueueEntry { uri: uri.into(), position: None, range: None, priority: None, tags: Vec::new() }
+    }
+
+    /// Insert the song at given position instead of appending it
+    pub fn position<Q: Into<QueuePosition>>(mut self, position: Q) -> QueueEntry {
+        self.position = Some(position.into());
+        self
+    }
+
+    /// Play only given range (in seconds) of the song
+    pub fn range<R: ToSongRange>(mut self, range: R) -> QueueEntry {
+        self.range = Some(range.to_range());
+        self
+    }
+
+    /// Set song priority, songs with higher priority are played first in random mode
+    pub fn priority(mut self, priority: u8) -> QueueEntry {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Add a tag to the queued song, MPD allows this for remote songs (streams) only
+    pub fn tag<T: Into<String>, V: Into<String>>(mut self, tag: T, value: V) -> QueueEntry {
+        self.tags.push((tag.into(), value.into()));
+        self
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Add a song into the queue and apply all the settings of the entry
+    ///
+    /// The song is added first, and then other settings are applied in a single command list.
+    /// If any of them fails, the song is removed from the queue again and the error is returned.
+    pub fn enqueue(&mut self, entry: QueueEntry) -> Result<Id> {
+        let id = match entry.position {
+            Some(position) => self.run_command("addid", (&entry.uri, position)),
+            None => self.run_command("addid", &entry.uri),
+        }
+        .and_then(|_| self.read_field("Id"))
+        .map(Id)?;
+
+        let mut commands: Vec<(&str, Vec<String>)> = Vec::new();
+        if let Some(range) = entry.range {
+            commands.push(("rangeid", vec![id.to_string(), range.to_string()]));
+        }
+        if let Some(priority) = entry.priority {
+            commands.push(("prioid", vec![priority.to_string(), id.to_string()]));
+        }
+        for (tag, value) in entry.tags {
+            commands.push(("addtagid", vec![id.to_string(), tag, value]));
+        }
+
+        if !commands.is_empty() {
+            if let Err(e) = self.run_command_list(&commands).and_then(|_| self.expect_ok()) {
+                let _ = self.delete(id);
+                return Err(e);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Check all queued files against the database and report entries whose files disappeared
+    ///
+    /// Missing files are looked up by their `MUSICBRAINZ_TRACKID` tag if present, otherwise
+    /// by title (and artist, if known). Found songs must also have the same duration, album,
+    /// track and disc, and if several songs match, only the one with the same file name is
+    /// taken. Streams (URIs with a scheme) are never reported.
+    ///
+    /// If `repair` is set, moved entries are replaced by the songs found in place, and entries
+    /// which couldn't be found at all are deleted from the queue.
+    pub fn reconcile_queue(&mut self, repair: bool) -> Result<Vec<QueueIssue>> {
+        let queue: Vec<Song> = self.queue(None)?.into_iter().filter(|song| song.place.is_some() && !song.file.contains("://")).collect();
+
+        let mut missing = Vec::new();
+        for chunk in queue.chunks(BATCH_SIZE) {
+            let queries: Vec<Query> = chunk.iter().map(|song| exact(Term::File, &song.file)).collect();
+            for (song, found) in chunk.iter().zip(self.find_batch(&queries)?) {
+                if found.is_empty() {
+                    missing.push(song);
+                }
+            }
+        }
+
+        // The same file may be queued several times, look it up only once
+        let mut located: HashMap<&str, Option<Song>> = HashMap::new();
+        let mut issues = Vec::with_capacity(missing.len());
+        for song in missing {
+            let place = song.place.unwrap_or_default();
+            let found = match located.get(&*song.file) {
+                Some(found) => found.clone(),
+                None => {
+                    let found = self.locate(song)?;
+                    located.insert(&song.file, found.clone());
+                    found
+                }
+            };
+            let issue = match found {
+                Some(found) => QueueIssue::Moved { id: place.id, pos: place.pos, file: song.file.clone(), song: Box::new(found) },
+                None => QueueIssue::Missing { id: place.id, pos: place.pos, file: song.file.clone() },
+            };
+            issues.push(issue);
+        }
+
+        if repair && !issues.is_empty() {
+            self.repair_queue(&issues)?;
+        }
+
+        Ok(issues)
+    }
+
+    /// Find a database song which is most likely the same as the given queued one
+    fn locate(&mut self, song: &Song) -> Result<Option<Song>> {
+        let query = match (song.musicbrainz_trackid(), &song.title) {
+            (Some(mbid), _) => exact(Term::Tag("MUSICBRAINZ_TRACKID".into()), mbid),
+            (None, Some(title)) => {
+                let mut query = exact(Term::Tag("title".into()), title);
+                if let Some(ref artist) = song.artist {
+                    query.and(Term::Tag("artist".into()), artist.as_str());
+                }
+                query
+            }
+            (None, None) => return Ok(None),
+        };
+        Ok(best_match(song, self.find(&query, None)?))
+    }
+
+    /// Run a number of `find` commands in a single command list, returning results for each one
+    fn find_batch(&mut self, queries: &[Query]) -> Result<Vec<Vec<Song>>> {
+        let commands: Vec<(&str, &Query)> = queries.iter().map(|query| ("find", query)).collect();
+        self.run_command_list_ok(&commands)?;
+        let results = queries.iter().map(|_| self.read_structs("file")).collect::<Result<Vec<_>>>()?;
+        self.expect_ok()?;
+        Ok(results)
+    }
+
+    fn repair_queue(&mut self, issues: &[QueueIssue]) -> Result<()> {
+        let mut commands: Vec<(&str, Vec<String>)> = Vec::new();
+
+        // Adding a replacement right before the old entry and deleting the latter
+        // keeps positions of all the other entries intact.
+        for issue in issues {
+            if let QueueIssue::Moved { id, pos, ref song, .. } = *issue {
+                commands.push(("addid", vec![song.file.clone(), pos.to_string()]));
+                commands.push(("deleteid", vec![id.to_string()]));
+            }
+        }
+        for issue in issues {
+            if let QueueIssue::Missing { id, .. } = *issue {
+                commands.push(("deleteid", vec![id.to_string()]));
+            }
+        }
+
+        self.run_command_list(&commands).and_then(|_| self.read_fields::<u32>("Id")).map(|_| ())
+    }
+}
+
+/// Local copy of the play queue, updated incrementally
+///
+/// Songs which only moved within the queue are reused from the cache, so metadata
+/// is only fetched for new entries and entries changed in place.
+#[derive(Debug, Clone, Default)]
+pub struct QueueSync {
+    version: Option<u32>,
+    songs: Vec<Song>,
+}
+
+impl QueueSync {
+    /// Create empty cache, the first update fetches the whole queue
+    pub fn new() -> QueueSync {
+        QueueSync::default()
+    }
+
+    /// Cached queue songs, in queue order
+    pub fn songs(&self) -> &[Song] {
+        &self.songs
+    }
+
+    /// Queue version the cache corresponds to, if the queue was fetched already
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    /// Drop cached songs, so the next update fetches the whole queue
+    pub fn reset(&mut self) {
+        self.version = None;
+        self.songs.clear();
+    }
+
+    /// Update the cache if idle events include queue changes, returning `true` if the cache changed
+    pub fn handle<S: Read + Write>(&mut self, client: &mut Client<S>, events: &[Subsystem]) -> Result<bool> {
+        if events.contains(&Subsystem::Queue) { self.update(client) } else { Ok(false) }
+    }
+
+    /// Bring the cache up to date with the server, returning `true` if the cache changed
+    pub fn update<S: Read + Write>(&mut self, client: &mut Client<S>) -> Result<bool> {
+        self.update_changes(client).map(|changes| changes.is_some())
+    }
+
+    /// Bring the cache up to date, returning positions changed since the previous version
+    ///
+    /// When the whole queue has to be fetched, every position is reported as changed.
+    pub(crate) fn update_changes<S: Read + Write>(&mut self, client: &mut Client<S>) -> Result<Option<Vec<PosIdChange>>> {
+        let status = client.status()?;
+        let version = match self.version {
+            Some(version) if version == status.queue_version => return Ok(None),
+            Some(version) => version,
+            None => return self.fetch(client, status.queue_version).map(Some),
+        };
+
+        let changes = client.changesposid(version, None)?;
+        let mut songs = match rearrange(mem::take(&mut self.songs), &changes, status.queue_len as usize) {
+            Some(songs) => songs,
+            None => return self.fetch(client, status.queue_version).map(Some),
+        };
+
+        let missing: Vec<u32> = (0..songs.len() as u32).filter(|&pos| songs[pos as usize].is_none()).collect();
+        if let (Some(&first), Some(&last)) = (missing.first(), missing.last()) {
+            for song in client.changes(version, (first, last + 1))? {
+                if let Some(slot) = song.place.and_then(|place| songs.get_mut(place.pos as usize)) {
+                    if slot.is_none() {
+                        *slot = Some(song);
+                    }
+                }
+            }
+        }
+
+        // The queue changed once again while we were fetching changes
+        match songs.into_iter().collect() {
+            Some(songs) => self.songs = songs,
+            None => return self.fetch(client, status.queue_version).map(Some),
+        }
+        self.version = Some(status.queue_version);
+        Ok(Some(changes))
+    }
+
+    fn fetch<S: Read + Write>(&mut self, client: &mut Client<S>, version: u32) -> Result<Vec<PosIdChange>> {
+        self.songs = client.queue(None)?;
+        self.version = Some(version);
+        Ok(self.songs.iter().filter_map(|song| song.place).map(|place| PosIdChange { pos: place.pos, id: place.id }).collect())
+    }
+}
+
+/// Apply queue changes to cached songs, leaving `None` where song metadata has to be fetched
+///
+/// Returns `None` if changes don't match the cache.
+fn rearrange(old: Vec<Song>, changes: &[PosIdChange], len: usize) -> Option<Vec<Option<Song>>> {
+    let mut old: Vec<Option<Song>> = old.into_iter().map(Some).collect();
+    let changed: BTreeMap<u32, Id> = changes.iter().map(|change| (change.pos, change.id)).collect();
+
+    // Songs at changed positions may have moved somewhere else
+    let mut moved = BTreeMap::new();
+    for &pos in changed.keys() {
+        if let Some(song) = old.get_mut(pos as usize).and_then(Option::take) {
+            if let Some(place) = song.place {
+                moved.insert(place.id, song);
+            }
+        }
+    }
+
+    let mut songs = Vec::with_capacity(len);
+    for pos in 0..len as u32 {
+        let song = match changed.get(&pos) {
+            None => Some(old.get_mut(pos as usize).and_then(Option::take)?),
+            // A song changed in place needs fresh metadata
+            Some(id) => moved.remove(id).filter(|song| song.place.is_some_and(|place| place.pos != pos)).map(|mut song| {
+                if let Some(ref mut place) = song.place {
+                    place.pos = pos;
+                }
+                song
+            }),
+        };
+        songs.push(song);
+    }
+    Some(songs)
+}
+
+fn exact<'a>(term: Term<'a>, value: &'a str) -> Query<'a> {
+    let mut query = Query::new();
+    query.and(term, value);
+    query
+}
+
+/// Pick the candidate which is the same song as the queued one
+///
+/// Candidates must agree with the song on album, track and disc tags the song has,
+/// and have the same duration. If several candidates remain, only the one with the same
+/// file name is taken, as there's no telling which one is right otherwise.
+fn best_match(song: &Song, candidates: Vec<Song>) -> Option<Song> {
+    let agrees = |found: &Song| {
+        let same_duration = match (song.duration, found.duration) {
+            (Some(a), Some(b)) => a.abs_diff(b) <= DURATION_TOLERANCE,
+            (a, b) => a == b,
+        };
+        same_duration && ["Album", "Track", "Disc"].iter().all(|&tag| song.tag(tag).is_none() || found.tag(tag) == song.tag(tag))
+    };
+    let file_name = |file: &str| file.rsplit('/').next().unwrap_or_default().to_owned();
+
+    let mut candidates: Vec<Song> = candidates.into_iter().filter(agrees).collect();
+    if candidates.len() > 1 {
+        candidates.retain(|found| file_name(&found.file) == file_name(&song.file));
+    }
+    match candidates.len() {
+        1 => candidates.pop(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{best_match, rearrange, QueueIssue};
+    use crate::song::{Id, PosIdChange, QueuePlace, Song};
+    use crate::testing::MockServer;
+    use std::time::Duration;
+
+    fn song(file: &str, id: u32, pos: u32) -> Song {
+        Song { file: file.into(), place: Some(QueuePlace { id: Id(id), pos, prio: 0 }), ..Song::default() }
+    }
+
+    fn change(pos: u32, id: u32) -> PosIdChange {
+        PosIdChange { pos, id: Id(id) }
+    }
+
+    #[test]
+    fn rearrange_move() {
+        let old = vec![song("a", 1, 0), song("b", 2, 1), song("c", 3, 2)];
+        let songs = rearrange(old, &[change(0, 2), change(1, 1)], 3).unwrap();
+        assert_eq!(songs, vec![Some(song("b", 2, 0)), Some(song("a", 1, 1)), Some(song("c", 3, 2))]);
+    }
+
+    #[test]
+    fn rearrange_add_delete() {
+        let old = vec![song("a", 1, 0), song("b", 2, 1), song("c", 3, 2)];
+        let songs = rearrange(old, &[change(1, 3), change(2, 4), change(3, 5)], 4).unwrap();
+        assert_eq!(songs, vec![Some(song("a", 1, 0)), Some(song("c", 3, 1)), None, None]);
+    }
+
+    #[test]
+    fn rearrange_changed_in_place() {
+        let old = vec![song("a", 1, 0), song("b", 2, 1)];
+        let songs = rearrange(old, &[change(1, 2)], 2).unwrap();
+        assert_eq!(songs, vec![Some(song("a", 1, 0)), None]);
+    }
+
+    #[test]
+    fn rearrange_mismatch() {
+        let old = vec![song("a", 1, 0)];
+        assert_eq!(rearrange(old, &[], 2), None);
+    }
+
+    fn tagged(file: &str, album: &str, secs: u64) -> Song {
+        Song {
+            file: file.into(),
+            title: Some("A".into()),
+            duration: Some(Duration::from_secs(secs)),
+            tags: vec![("Album".into(), album.into())],
+            ..Song::default()
+        }
+    }
+
+    #[test]
+    fn best_match_tags() {
+        let queued = tagged("old/a.flac", "X", 100);
+        // Other album or duration is another song, even with the same title
+        let found = best_match(&queued, vec![tagged("new/a.flac", "Y", 100), tagged("live/a.flac", "X", 300), tagged("new/b.flac", "X", 100)]);
+        assert_eq!(found.map(|song| song.file), Some("new/b.flac".to_owned()));
+        // Unknown duration doesn't match known one
+        let unknown = Song { duration: None, ..tagged("new/a.flac", "X", 0) };
+        assert_eq!(best_match(&queued, vec![unknown]), None);
+    }
+
+    #[test]
+    fn best_match_duplicates() {
+        let queued = tagged("old/a.flac", "X", 100);
+        let found = best_match(&queued, vec![tagged("new/b.flac", "X", 100), tagged("new/a.flac", "X", 100)]);
+        assert_eq!(found.map(|song| song.file), Some("new/a.flac".to_owned()));
+        // Several candidates and none of them has the same file name
+        assert_eq!(best_match(&queued, vec![tagged("new/b.flac", "X", 100), tagged("new/c.flac", "X", 100)]), None);
+    }
+
+    #[test]
+    fn reconcile_repair() {
+        let server = MockServer::new();
+        server
+            .expect(
+                "playlistinfo",
+                "file: old/a.flac\nTitle: A\nArtist: X\nduration: 100.000\nPos: 0\nId: 1\n\
+                 file: ok.flac\nPos: 1\nId: 2\n\
+                 file: old/a.flac\nTitle: A\nArtist: X\nduration: 100.000\nPos: 2\nId: 3\n\
+                 file: gone.flac\nTitle: G\nduration: 50.000\nPos: 3\nId: 4",
+            )
+            .expect(r#"find "(file == \"old/a.flac\")""#, "")
+            .expect(r#"find "(file == \"ok.flac\")""#, "file: ok.flac")
+            .expect(r#"find "(file == \"old/a.flac\")""#, "")
+            .expect(r#"find "(file == \"gone.flac\")""#, "")
+            // The duplicate entry is looked up only once
+            .expect(r#"find "((title == \"A\") AND (artist == \"X\"))""#, "file: new/a.flac\nTitle: A\nArtist: X\nduration: 100.500")
+            .expect(r#"find "(title == \"G\")""#, "file: x/g1.flac\nTitle: G\nduration: 50.000\nfile: y/g2.flac\nTitle: G\nduration: 50.000")
+            .expect("addid new/a.flac 0", "Id: 5")
+            .expect("deleteid 1", "")
+            .expect("addid new/a.flac 2", "Id: 6")
+            .expect("deleteid 3", "")
+            .expect("deleteid 4", "");
+
+        let mut conn = server.client();
+        let issues = conn.reconcile_queue(true).unwrap();
+        let summary: Vec<_> = issues
+            .iter()
+            .map(|issue| match issue {
+                QueueIssue::Moved { id, song, .. } => (id.0, Some(&*song.file)),
+                QueueIssue::Missing { id, .. } => (id.0, None),
+            })
+            .collect();
+        assert_eq!(summary, vec![(1, Some("new/a.flac")), (3, Some("new/a.flac")), (4, None)]);
+        server.verify();
+    }
+}
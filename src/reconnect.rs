@@ -0,0 +1,224 @@
+//! The module defines client wrapper which survives server restarts
+//!
+//! [`ReconnectingClient`] runs commands on a regular [`Client`], and if the
+//! connection turns out to be lost (broken pipe, reset or closed by the server),
+//! it connects again, restores connection state (password, partition, binary
+//! limit and tag types, as configured with [`ClientBuilder`]) and retries.
+//!
+//! ```rust,no_run
+//! use mpd::reconnect::ReconnectingClient;
+//! use mpd::ClientBuilder;
+//!
+//! let mut conn = ReconnectingClient::new(ClientBuilder::from_env().unwrap()).unwrap();
+//! let status = conn.run(|mpd| mpd.status()).unwrap();
+//! println!("Status: {:?}", status);
+//! ```
+//!
+//! [`IdleWatcher`] does the same for a connection waiting for idle events,
+//! and reports [`IdleEvent::Reconnected`] after the connection is restored, as
+//! any events could have been missed while it was lost:
+//!
+//! ```rust,no_run
+//! use mpd::reconnect::{IdleEvent, IdleWatcher};
+//! use mpd::ClientBuilder;
+//!
+//! let watcher = IdleWatcher::new(ClientBuilder::from_env().unwrap(), &[]).unwrap();
+//! for event in watcher {
+//!     match event.unwrap() {
+//!         IdleEvent::Changed(subsystems) => println!("Changed: {:?}", subsystems),
+//!         IdleEvent::Reconnected => println!("Reconnected, refreshing everything"),
+//!     }
+//! }
+//! ```
+
+use crate::builder::ClientBuilder;
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::idle::{Idle, Subsystem};
+use crate::socket::Socket;
+
+use std::io::ErrorKind;
+use std::thread;
+use std::time::Duration;
+
+/// Client connection which reconnects automatically when the connection is lost
+#[derive(Debug)]
+pub struct ReconnectingClient {
+    builder: ClientBuilder,
+    client: Option<Client<Socket>>,
+    retries: usize,
+}
+
+impl ReconnectingClient {
+    /// Connect using given builder, which is also used to set up new connections
+    pub fn new(builder: ClientBuilder) -> Result<ReconnectingClient> {
+        let client = builder.connect()?;
+        Ok(ReconnectingClient { builder, client: Some(client), retries: 1 })
+    }
+
+    /// Set number of reconnection attempts for a single command, 1 by default
+    pub fn retries(mut self, retries: usize) -> ReconnectingClient {
+        self.retries = retries;
+        self
+    }
+
+    /// Run an operation on the client, reconnecting and retrying it if the connection is lost
+    ///
+    /// The operation may be run more than once, so it should be safe to repeat:
+    /// e.g. if a command list is interrupted, part of it may have been executed already.
+    pub fn run<T, F>(&mut self, mut f: F) -> Result<T>
+    where F: FnMut(&mut Client<Socket>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            let result = match self.client {
+                Some(ref mut client) => f(client),
+                None => self.reconnect().and_then(|_| f(self.client.as_mut().unwrap())),
+            };
+            match result {
+                Err(ref e) if is_disconnect(e) && attempt < self.retries => {
+                    attempt += 1;
+                    self.client = None;
+                }
+                Err(e) => {
+                    if is_disconnect(&e) {
+                        self.client = None;
+                    }
+                    return Err(e);
+                }
+                Ok(value) => return Ok(value),
+            }
+        }
+    }
+
+    /// Drop current connection and connect again
+    pub fn reconnect(&mut self) -> Result<()> {
+        self.client = None;
+        self.client = Some(self.builder.connect()?);
+        Ok(())
+    }
+
+    /// Check if the client is connected at the moment
+    ///
+    /// The connection is only known to be lost after some command fails.
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// Login with the password, also using it for future connections
+    pub fn login<P: AsRef<str>>(&mut self, password: P) -> Result<()> {
+        let password = password.as_ref();
+        self.builder = self.builder.clone().password(password);
+        self.run(|client| client.login(password))
+    }
+
+    /// Switch to the partition, also using it for future connections
+    pub fn switch_partition<N: AsRef<str>>(&mut self, name: N) -> Result<()> {
+        self.builder = self.builder.clone().partition(name.as_ref());
+        let builder = self.builder.clone();
+        self.run(|client| builder.setup_partition(client))
+    }
+
+    /// Set binary chunk size limit, also using it for future connections
+    pub fn binarylimit(&mut self, limit: usize) -> Result<()> {
+        self.builder = self.builder.clone().binary_limit(limit);
+        self.run(|client| client.binarylimit(limit))
+    }
+
+    /// Receive only given tag types, also for future connections
+    pub fn tagtypes(&mut self, tagtypes: &[&str]) -> Result<()> {
+        self.builder = self.builder.clone().tagtypes(tagtypes);
+        let builder = self.builder.clone();
+        self.run(|client| builder.setup_tagtypes(client))
+    }
+
+    /// Release current connection, if any
+    pub fn into_client(self) -> Option<Client<Socket>> {
+        self.client
+    }
+}
+
+/// Event reported by [`IdleWatcher`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdleEvent {
+    /// some subsystems have changed
+    Changed(Vec<Subsystem>),
+    /// the connection was lost and restored, so the state should be fetched anew
+    Reconnected,
+}
+
+/// Connection waiting for idle events, which reconnects automatically when the connection is lost
+///
+/// While the server is not available, connection attempts are repeated with the retry interval.
+/// The iterator never ends by itself, but it yields errors other than connection failures.
+#[derive(Debug)]
+pub struct IdleWatcher {
+    builder: ClientBuilder,
+    client: Option<Client<Socket>>,
+    subsystems: Vec<Subsystem>,
+    retry_interval: Duration,
+}
+
+impl IdleWatcher {
+    /// Connect using given builder and watch for events from a set of subsystems
+    ///
+    /// If empty subsystems slice is given, watch for events from any subsystem.
+    pub fn new(builder: ClientBuilder, subsystems: &[Subsystem]) -> Result<IdleWatcher> {
+        let client = builder.connect()?;
+        Ok(IdleWatcher { builder, client: Some(client), subsystems: subsystems.to_vec(), retry_interval: Duration::from_secs(1) })
+    }
+
+    /// Set interval between connection attempts, 1 second by default
+    pub fn retry_interval(mut self, interval: Duration) -> IdleWatcher {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// Wait for the next event, reconnecting if needed
+    pub fn next_event(&mut self) -> Result<IdleEvent> {
+        loop {
+            let client = match self.client {
+                Some(ref mut client) => client,
+                None => match self.builder.connect() {
+                    Ok(client) => {
+                        self.client = Some(client);
+                        return Ok(IdleEvent::Reconnected);
+                    }
+                    Err(Error::Io(_)) => {
+                        thread::sleep(self.retry_interval);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                },
+            };
+            match client.wait(&self.subsystems) {
+                Ok(subsystems) => return Ok(IdleEvent::Changed(subsystems)),
+                Err(ref e) if is_disconnect(e) => self.client = None,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Release current connection, if any
+    pub fn into_client(self) -> Option<Client<Socket>> {
+        self.client
+    }
+}
+
+impl Iterator for IdleWatcher {
+    type Item = Result<IdleEvent>;
+
+    fn next(&mut self) -> Option<Result<IdleEvent>> {
+        Some(self.next_event())
+    }
+}
+
+/// Check if the error means the connection is lost
+fn is_disconnect(e: &Error) -> bool {
+    match e {
+        Error::Io(e) => matches!(
+            e.kind(),
+            ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::NotConnected | ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
@@ -21,16 +21,53 @@ pub enum Reply {
 impl FromStr for Reply {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Reply, ParseError> {
-        if s == "OK" || s == "list_OK" {
-            Ok(Reply::Ok)
-        } else if let Ok(ack) = s.parse::<ServerError>() {
-            Ok(Reply::Ack(ack))
-        } else {
-            let mut splits = s.splitn(2, ':');
-            match (splits.next(), splits.next()) {
-                (Some(a), Some(b)) => Ok(Reply::Pair(a.to_owned(), b.trim().to_owned())),
-                _ => Err(ParseError::BadPair),
-            }
+        parse_line(s).map(|line| match line {
+            Line::Ok => Reply::Ok,
+            Line::Ack(ack) => Reply::Ack(ack),
+            Line::Pair(key, value) => Reply::Pair(key.to_owned(), value.to_owned()),
+        })
+    }
+}
+
+/// Borrowed counterpart of [`Reply`]
+pub(crate) enum Line<'a> {
+    Ok,
+    Ack(ServerError),
+    Pair(&'a str, &'a str),
+}
+
+pub(crate) fn parse_line(s: &str) -> Result<Line<'_>, ParseError> {
+    if s == "OK" || s == "list_OK" {
+        return Ok(Line::Ok);
+    }
+    if s.starts_with("ACK ") {
+        if let Ok(ack) = s.parse::<ServerError>() {
+            return Ok(Line::Ack(ack));
         }
     }
+    split_pair(s).map(|(key, value)| Line::Pair(key, value)).ok_or(ParseError::BadPair)
+}
+
+/// Split `field: value` line into field and value
+pub(crate) fn split_pair(s: &str) -> Option<(&str, &str)> {
+    // The colon is ASCII, so the split can't fall into the middle of a UTF-8 sequence
+    memchr::memchr(b':', s.as_bytes()).map(|i| (&s[..i], s[i + 1..].trim()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_pair() {
+        assert_eq!("file: http://example.com:8000/stream".parse(), Ok(Reply::Pair("file".into(), "http://example.com:8000/stream".into())));
+        assert_eq!("Title: ".parse(), Ok(Reply::Pair("Title".into(), "".into())));
+        assert_eq!("no separator".parse::<Reply>(), Err(ParseError::BadPair));
+    }
+
+    #[test]
+    fn parse_ok_and_ack() {
+        assert_eq!("list_OK".parse(), Ok(Reply::Ok));
+        assert!(matches!("ACK [50@0] {play} song doesn't exist".parse(), Ok(Reply::Ack(_))));
+    }
 }
@@ -3,6 +3,7 @@
 // TODO: unfinished functionality
 
 use crate::proto::{Quoted, ToArguments};
+use crate::song::QueuePosition;
 use std::{
     borrow::Cow
 };
@@ -26,11 +27,19 @@ pub enum Term<'a> {
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(untagged, rename_all = "lowercase"))]
 pub enum Operation {
+    /// value equals to the given string
     Equals,
+    /// value doesn't equal to the given string
     NotEquals,
+    /// value contains the given string
     Contains,
+    /// value starts with the given string (MPD 0.24+)
     #[cfg_attr(feature = "serde", serde(rename = "starts_with"))]
-    StartsWith
+    StartsWith,
+    /// value matches the given Perl-compatible regular expression
+    Regex,
+    /// value doesn't match the given Perl-compatible regular expression
+    NotRegex,
 }
 
 #[derive(Debug, Clone)]
@@ -41,6 +50,91 @@ pub struct Filter<'a> {
     how: Operation
 }
 
+/// Filter expression combining several conditions, in MPD 0.21+ filter syntax
+///
+/// ```
+/// use mpd::search::{Expression, Operation, Query};
+/// use mpd::Term;
+///
+/// // Songs by either artist, which are not live recordings
+/// let expr = Expression::new(Term::Tag("artist".into()), "Mac DeMarco")
+///     .or(Expression::new(Term::Tag("artist".into()), "Homeshake"))
+///     .and(!Expression::with_op(Term::Tag("album".into()), Operation::Contains, "Live"));
+/// let query = Query::from(expr);
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expression<'a> {
+    /// single condition
+    Filter(Filter<'a>),
+    /// all conditions must match
+    And(Vec<Expression<'a>>),
+    /// any condition must match (MPD 0.24+)
+    Or(Vec<Expression<'a>>),
+    /// condition must not match
+    Not(Box<Expression<'a>>),
+    /// pre-built filter expression in MPD syntax, like `(artist == 'Foo')`
+    ///
+    /// The expression is sent as is (only quoted as a whole), so values inside
+    /// must be escaped by the caller.
+    Raw(Cow<'a, str>),
+}
+
+impl<'a> Expression<'a> {
+    /// Condition matching exact value
+    pub fn new<W>(typ: Term<'a>, what: W) -> Expression<'a>
+    where W: 'a + Into<Cow<'a, str>> {
+        Expression::Filter(Filter::new(typ, what))
+    }
+
+    /// Condition matching value with given operation
+    pub fn with_op<W>(typ: Term<'a>, how: Operation, what: W) -> Expression<'a>
+    where W: 'a + Into<Cow<'a, str>> {
+        Expression::Filter(Filter::new_with_op(typ, what, how))
+    }
+
+    /// Pre-built filter expression in MPD syntax, see [`Expression::Raw`]
+    pub fn raw<W>(expr: W) -> Expression<'a>
+    where W: 'a + Into<Cow<'a, str>> {
+        Expression::Raw(expr.into())
+    }
+
+    /// Combine with another expression, both must match
+    pub fn and(self, other: Expression<'a>) -> Expression<'a> {
+        match self {
+            Expression::And(mut exprs) => {
+                exprs.push(other);
+                Expression::And(exprs)
+            }
+            expr => Expression::And(vec![expr, other]),
+        }
+    }
+
+    /// Combine with another expression, any of them must match (MPD 0.24+)
+    pub fn or(self, other: Expression<'a>) -> Expression<'a> {
+        match self {
+            Expression::Or(mut exprs) => {
+                exprs.push(other);
+                Expression::Or(exprs)
+            }
+            expr => Expression::Or(vec![expr, other]),
+        }
+    }
+}
+
+impl<'a> std::ops::Not for Expression<'a> {
+    type Output = Expression<'a>;
+    fn not(self) -> Expression<'a> {
+        Expression::Not(Box::new(self))
+    }
+}
+
+impl<'a> From<Filter<'a>> for Expression<'a> {
+    fn from(filter: Filter<'a>) -> Expression<'a> {
+        Expression::Filter(filter)
+    }
+}
+
 impl<'a> Filter<'a> {
     pub fn new<W>(typ: Term<'a>, what: W) -> Filter<'a>
     where W: 'a + Into<Cow<'a, str>> {
@@ -86,10 +180,88 @@ impl From<Option<(u32, u32)>> for Window {
     }
 }
 
+/// Key to sort search results by
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortKey<'a> {
+    /// tag value
+    Tag(Cow<'a, str>),
+    /// file modification time
+    LastModified,
+    /// time the file was added to the database (MPD 0.24+)
+    Added,
+}
+
+/// Sort direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// Sorting of search results
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sort<'a> {
+    pub key: SortKey<'a>,
+    pub order: SortOrder,
+}
+
+impl<'a> Sort<'a> {
+    /// Sort by given key in ascending order
+    pub fn asc(key: SortKey<'a>) -> Sort<'a> {
+        Sort { key, order: SortOrder::Ascending }
+    }
+
+    /// Sort by given key in descending order
+    pub fn desc(key: SortKey<'a>) -> Sort<'a> {
+        Sort { key, order: SortOrder::Descending }
+    }
+}
+
+/// Options of [`findadd_with()`](crate::Client::findadd_with) and [`searchadd_with()`](crate::Client::searchadd_with)
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions<'a> {
+    sort: Option<Sort<'a>>,
+    window: Option<Window>,
+    position: Option<QueuePosition>,
+}
+
+impl<'a> AddOptions<'a> {
+    /// Add all matching songs to the end of the queue
+    pub fn new() -> AddOptions<'a> {
+        AddOptions::default()
+    }
+
+    /// Sort the results before adding them
+    ///
+    /// Combined with a window, this adds e.g. only the most recently added songs.
+    pub fn sort(mut self, sort: Sort<'a>) -> AddOptions<'a> {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Add only given window of the results
+    pub fn window<W: Into<Window>>(mut self, window: W) -> AddOptions<'a> {
+        self.window = Some(window.into());
+        self
+    }
+
+    /// Insert the results at given position instead of appending them (MPD 0.23+)
+    ///
+    /// The position may be relative to the current song, e.g. to add results right after it.
+    pub fn position<Q: Into<QueuePosition>>(mut self, position: Q) -> AddOptions<'a> {
+        self.position = Some(position.into());
+        self
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Query<'a> {
-    filters: Vec<Filter<'a>>,
+    filters: Vec<Expression<'a>>,
 }
 
 impl<'a> Query<'a> {
@@ -98,14 +270,26 @@ impl<'a> Query<'a> {
     }
 
     pub fn and<'b: 'a, V: 'b + Into<Cow<'b, str>>>(&mut self, term: Term<'b>, value: V) -> &mut Query<'a> {
-        self.filters.push(Filter::new(term, value));
+        self.filters.push(Expression::new(term, value));
         self
     }
 
     pub fn and_with_op<'b: 'a, V: 'b + Into<Cow<'b, str>>>(&mut self, term: Term<'b>, op: Operation, value: V) -> &mut Query<'a> {
-        self.filters.push(Filter::new_with_op(term, value, op));
+        self.filters.push(Expression::with_op(term, op, value));
         self
     }
+
+    /// Add arbitrary filter expression, which must match too
+    pub fn and_expr(&mut self, expr: Expression<'a>) -> &mut Query<'a> {
+        self.filters.push(expr);
+        self
+    }
+}
+
+impl<'a> From<Expression<'a>> for Query<'a> {
+    fn from(expr: Expression<'a>) -> Query<'a> {
+        Query { filters: vec![expr] }
+    }
 }
 
 impl<'a> fmt::Display for Term<'a> {
@@ -134,7 +318,9 @@ impl fmt::Display for Operation {
             Operation::Equals => "==",
             Operation::NotEquals => "!=",
             Operation::Contains => "contains",
-            Operation::StartsWith => "starts_with"
+            Operation::StartsWith => "starts_with",
+            Operation::Regex => "=~",
+            Operation::NotRegex => "!~",
         })
     }
 }
@@ -146,27 +332,44 @@ impl ToArguments for Operation {
     }
 }
 
+impl<'a> fmt::Display for Filter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.typ {
+            // For some terms, the filter clause cannot have an operation
+            Term::Base | Term::LastMod | Term::AddedSince => write!(f, "({} {})", self.typ, Quoted(&self.what)),
+            _ => write!(f, "({} {} {})", self.typ, self.how, Quoted(&self.what)),
+        }
+    }
+}
+
 impl<'a> ToArguments for &'a Filter<'a> {
     fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
     where F: FnMut(&str) -> StdResult<(), E> {
-        match self.typ {
-            // For some terms, the filter clause cannot have an operation
-            Term::Base | Term::LastMod | Term::AddedSince => {
-                f(&format!(
-                    "({} {})",
-                    &self.typ,
-                    &Quoted(&self.what).to_string()
-                ))
-            }
-            _ => {
-                f(&format!(
-                    "({} {} {})",
-                    &self.typ,
-                    &self.how,
-                    &Quoted(&self.what).to_string())
-                )
+        f(&self.to_string())
+    }
+}
+
+impl<'a> fmt::Display for Expression<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (exprs, op) = match *self {
+            Expression::Filter(ref filter) => return filter.fmt(f),
+            Expression::Not(ref expr) => return write!(f, "(!{})", expr),
+            Expression::Raw(ref expr) => return f.write_str(expr),
+            Expression::And(ref exprs) => (exprs, " AND "),
+            Expression::Or(ref exprs) => (exprs, " OR "),
+        };
+        // A single expression doesn't need extra parentheses
+        if let [expr] = &exprs[..] {
+            return expr.fmt(f);
+        }
+        f.write_str("(")?;
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                f.write_str(op)?;
             }
+            expr.fmt(f)?;
         }
+        f.write_str(")")
     }
 }
 
@@ -174,28 +377,32 @@ impl<'a> ToArguments for &'a Query<'a> {
     // Use MPD 0.21+ filter syntax
     fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
     where F: FnMut(&str) -> StdResult<(), E> {
-        // Construct the query string in its entirety first before escaping
-        if !self.filters.is_empty() {
-            let mut qs = String::new();
-            if self.filters.len() > 1 {
-                qs.push_str("(");
-            }
-            for (i, filter) in self.filters.iter().enumerate() {
-                if i > 0 {
-                    qs.push_str(" AND ");
-                }
-                // Leave escaping to the filter since terms should not be escaped or quoted
-                filter.to_arguments(&mut |arg| {
-                    qs.push_str(arg);
-                    Ok(())
-                })?;
-            }
-            if self.filters.len() > 1 {
-                qs.push_str(")");
-            }
-            f(&qs)
-        } else {
-            Ok(())
+        match &self.filters[..] {
+            [] => Ok(()),
+            [expr] => f(&expr.to_string()),
+            // Construct the query string in its entirety first before escaping
+            exprs => f(&Expression::And(exprs.to_vec()).to_string()),
+        }
+    }
+}
+
+impl<'a> fmt::Display for SortKey<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            SortKey::Tag(ref tag) => tag,
+            SortKey::LastModified => "Last-Modified",
+            SortKey::Added => "Added",
+        })
+    }
+}
+
+impl<'a> ToArguments for &'a Sort<'a> {
+    fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
+    where F: FnMut(&str) -> StdResult<(), E> {
+        f("sort")?;
+        match self.order {
+            SortOrder::Ascending => f(&self.key.to_string()),
+            SortOrder::Descending => f(&format!("-{}", self.key)),
         }
     }
 }
@@ -213,6 +420,15 @@ impl ToArguments for Window {
     }
 }
 
+impl<'a> ToArguments for &AddOptions<'a> {
+    fn to_arguments<F, E>(&self, f: &mut F) -> StdResult<(), E>
+    where F: FnMut(&str) -> StdResult<(), E> {
+        self.sort.as_ref().to_arguments(f)?;
+        self.window.to_arguments(f)?;
+        self.position.map(|pos| ("position", pos)).to_arguments(f)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -236,12 +452,54 @@ mod test {
         assert_eq!(output, vec!["window", "0:2"]);
     }
 
+    #[test]
+    fn add_options_format() {
+        assert!(collect(&AddOptions::new()).is_empty());
+        assert_eq!(collect(&AddOptions::new().window((1, 3))), vec!["window", "1:3"]);
+        let options = AddOptions::new().position(QueuePosition::AfterCurrent(0)).window((0, 1)).sort(Sort::desc(SortKey::Added));
+        assert_eq!(collect(&options), vec!["sort", "-Added", "window", "0:1", "position", "+0"]);
+    }
+
     #[test]
     fn find_query_format() {
         let mut query = Query::new();
         let finished = query.and(Term::Tag("albumartist".into()), "Mac DeMarco").and(Term::Tag("album".into()), "Salad Days");
         let output = collect(&*finished);
-        assert_eq!(output, vec!["albumartist", "Mac DeMarco", "album", "Salad Days"]);
+        assert_eq!(output, vec![r#"((albumartist == "Mac DeMarco") AND (album == "Salad Days"))"#]);
+    }
+
+    #[test]
+    fn sort_format() {
+        let sort = Sort::desc(SortKey::Tag("Date".into()));
+        assert_eq!(collect(&sort), vec!["sort", "-Date"]);
+        let sort = Sort::asc(SortKey::LastModified);
+        assert_eq!(collect(&sort), vec!["sort", "Last-Modified"]);
+    }
+
+    #[test]
+    fn expression_format() {
+        let expr = Expression::new(Term::Tag("artist".into()), "A")
+            .or(Expression::new(Term::Tag("artist".into()), "B \"C\""))
+            .and(!Expression::with_op(Term::Tag("album".into()), Operation::Contains, "Live"));
+        let mut query = Query::from(expr);
+        query.and(Term::Base, "music");
+        assert_eq!(collect(&query), vec![
+            r#"((((artist == "A") OR (artist == "B \"C\"")) AND (!(album contains "Live"))) AND (base "music"))"#
+        ]);
+    }
+
+    #[test]
+    fn raw_expression_format() {
+        let query = Query::from(Expression::raw("(artist =~ '^The ')"));
+        assert_eq!(collect(&query), vec!["(artist =~ '^The ')"]);
+    }
+
+    #[test]
+    fn regex_format() {
+        let mut query = Query::new();
+        query.and_with_op(Term::Tag("title".into()), Operation::Regex, r"^\d+ \w");
+        query.and_with_op(Term::Tag("artist".into()), Operation::NotRegex, "x");
+        assert_eq!(collect(&query), vec![r#"((title =~ "^\\d+ \\w") AND (artist !~ "x"))"#]);
     }
 
     #[test]
@@ -1,14 +1,18 @@
 //! The module defines song structs and methods.
 
-use crate::convert::FromIter;
+use crate::convert::{FromIter, Text};
+#[cfg(feature = "chrono")]
+use crate::convert::parse_timestamp;
 use crate::error::{Error, ParseError};
+use crate::status::AudioFormat;
 
+use std::borrow::Cow;
 use std::fmt;
 use std::str::FromStr;
 use std::time::Duration;
 
 /// Song ID
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Default)]
 pub struct Id(pub u32);
 
 impl fmt::Display for Id {
@@ -45,6 +49,48 @@ pub struct QueuePlace {
     pub prio: u8,
 }
 
+/// Position to insert songs at in the queue
+///
+/// Relative positions require MPD 0.23+ and fail if no song is playing.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QueuePosition {
+    /// absolute zero-based position
+    Absolute(u32),
+    /// offset after the current song, `0` means right after it
+    AfterCurrent(u32),
+    /// offset before the current song, `0` means right before it
+    BeforeCurrent(u32),
+}
+
+impl QueuePosition {
+    /// Position to insert the next song at, so that songs keep their order
+    pub(crate) fn next(self) -> QueuePosition {
+        match self {
+            QueuePosition::Absolute(pos) => QueuePosition::Absolute(pos + 1),
+            QueuePosition::AfterCurrent(offset) => QueuePosition::AfterCurrent(offset + 1),
+            // The current song moves forward as songs are inserted before it
+            QueuePosition::BeforeCurrent(offset) => QueuePosition::BeforeCurrent(offset),
+        }
+    }
+}
+
+impl fmt::Display for QueuePosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QueuePosition::Absolute(pos) => write!(f, "{}", pos),
+            QueuePosition::AfterCurrent(offset) => write!(f, "+{}", offset),
+            QueuePosition::BeforeCurrent(offset) => write!(f, "-{}", offset),
+        }
+    }
+}
+
+impl From<u32> for QueuePosition {
+    fn from(pos: u32) -> QueuePosition {
+        QueuePosition::Absolute(pos)
+    }
+}
+
 /// Song range
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -58,30 +104,46 @@ impl Default for Range {
 
 impl fmt::Display for Range {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.as_secs().fmt(f)?;
+        fmt_secs(self.0, f)?;
         f.write_str(":")?;
         if let Some(v) = self.1 {
-            v.as_secs().fmt(f)?;
+            fmt_secs(v, f)?;
         }
         Ok(())
     }
 }
 
+/// Write duration in seconds, with millisecond precision if needed
+fn fmt_secs(d: Duration, f: &mut fmt::Formatter) -> fmt::Result {
+    if d.subsec_nanos() == 0 {
+        write!(f, "{}", d.as_secs())
+    } else {
+        write!(f, "{:.3}", d.as_secs_f64())
+    }
+}
+
 impl FromStr for Range {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Range, ParseError> {
-        let mut splits = s.split('-').flat_map(|v| v.parse().into_iter());
-        match (splits.next(), splits.next()) {
-            (Some(s), Some(e)) => Ok(Range(Duration::from_secs(s), Some(Duration::from_secs(e)))),
-            (None, Some(e)) => Ok(Range(Duration::from_secs(0), Some(Duration::from_secs(e)))),
-            (Some(s), None) => Ok(Range(Duration::from_secs(s), None)),
-            (None, None) => Ok(Range(Duration::from_secs(0), None)),
-        }
+        let mut splits = s.split('-').map(parse_secs);
+        let start = splits.next().transpose()?.flatten().unwrap_or_default();
+        let end = splits.next().transpose()?.flatten();
+        Ok(Range(start, end))
+    }
+}
+
+/// Parse seconds with optional fractional part, empty string means no value
+fn parse_secs(s: &str) -> Result<Option<Duration>, ParseError> {
+    match s {
+        "" => Ok(None),
+        s => Ok(Some(Duration::try_from_secs_f64(s.parse()?)?)),
     }
 }
 
 /// Song data
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+///
+/// With `serde` feature the song is serialized completely, so it can be stored and loaded back without loss.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Song {
     /// filename
@@ -90,51 +152,201 @@ pub struct Song {
     pub name: Option<String>,
     /// title
     pub title: Option<String>,
-    /// last modification time
+    /// last modification time, as ISO 8601 UTC string
+    ///
+    /// Such strings sort in chronological order, see also `last_modified()` with `chrono` feature.
     pub last_mod: Option<String>,
+    /// time the song was added to the database (MPD 0.24+), in the same format as `last_mod`
+    pub added: Option<String>,
     /// artist
     pub artist: Option<String>,
-    /// duration (in seconds resolution)
+    /// duration (in milliseconds resolution, or in seconds for old servers)
     pub duration: Option<Duration>,
     /// place in the queue (if queued for playback)
     pub place: Option<QueuePlace>,
     /// range to play (if queued for playback and range was set)
     pub range: Option<Range>,
-    /// arbitrary tags, like album, artist etc
-    pub tags: Vec<(String, String)>,
+    /// audio format of the file, if known
+    pub format: Option<AudioFormat>,
+    /// arbitrary tags, like album, genre etc, in the order they were received
+    ///
+    /// Tags may be repeated, e.g. when a song has several genres: all the values are
+    /// kept here, except the first artist, title and name, which have their own fields.
+    /// Use [`tag_values()`](Song::tag_values) to get all values of a tag.
+    ///
+    /// Names of tags known to MPD are shared static strings, see [`intern_tag()`].
+    /// Note that tag names used to be `String`s: use `name.as_ref()` to compare them,
+    /// or `name.into_owned()` to get a `String`.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_tags"))]
+    pub tags: Vec<(Cow<'static, str>, String)>,
+}
+
+/// Deserialize tags, sharing names of known tags just like parsed songs do
+#[cfg(feature = "serde")]
+fn deserialize_tags<'de, D>(deserializer: D) -> Result<Vec<(Cow<'static, str>, String)>, D::Error>
+where D: serde::Deserializer<'de> {
+    let tags: Vec<(String, String)> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(tags
+        .into_iter()
+        .map(|(key, value)| match intern_tag(&key) {
+            Some(key) => (Cow::Borrowed(key), value),
+            None => (Cow::Owned(key), value),
+        })
+        .collect())
+}
+
+impl Song {
+    /// Get all values of a tag, in the order they were received
+    ///
+    /// Tag name is case-insensitive, values of `Artist`, `Title` and `Name` include
+    /// corresponding fields.
+    pub fn tag_values<'a: 'n, 'n>(&'a self, name: &'n str) -> impl Iterator<Item = &'a str> + 'n {
+        let field = if name.eq_ignore_ascii_case("Artist") {
+            self.artist.as_deref()
+        } else if name.eq_ignore_ascii_case("Title") {
+            self.title.as_deref()
+        } else if name.eq_ignore_ascii_case("Name") {
+            self.name.as_deref()
+        } else {
+            None
+        };
+        let tags = self.tags.iter().filter(move |(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str());
+        field.into_iter().chain(tags)
+    }
+
+    /// Get the first value of a tag, if present
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tag_values(name).next()
+    }
+
+    /// Album name
+    pub fn album(&self) -> Option<&str> {
+        self.tag("Album")
+    }
+
+    /// Album artist
+    pub fn album_artist(&self) -> Option<&str> {
+        self.tag("AlbumArtist")
+    }
+
+    /// Track number, `3/12`-like values are also accepted
+    pub fn track(&self) -> Option<u32> {
+        self.tag("Track").and_then(parse_number)
+    }
+
+    /// Disc number, `1/2`-like values are also accepted
+    pub fn disc(&self) -> Option<u32> {
+        self.tag("Disc").and_then(parse_number)
+    }
+
+    /// First genre, see [`tag_values()`](Song::tag_values) to get all of them
+    pub fn genre(&self) -> Option<&str> {
+        self.tag("Genre")
+    }
+
+    /// Release date, as stored in the file (usually `YYYY` or `YYYY-MM-DD`)
+    pub fn date(&self) -> Option<&str> {
+        self.tag("Date")
+    }
+
+    /// MusicBrainz recording ID
+    pub fn musicbrainz_trackid(&self) -> Option<&str> {
+        self.mbid("MUSICBRAINZ_TRACKID")
+    }
+
+    /// MusicBrainz artist ID (of the first artist)
+    pub fn musicbrainz_artistid(&self) -> Option<&str> {
+        self.mbid("MUSICBRAINZ_ARTISTID")
+    }
+
+    /// MusicBrainz release ID
+    pub fn musicbrainz_albumid(&self) -> Option<&str> {
+        self.mbid("MUSICBRAINZ_ALBUMID")
+    }
+
+    /// MusicBrainz track ID
+    pub fn musicbrainz_releasetrackid(&self) -> Option<&str> {
+        self.mbid("MUSICBRAINZ_RELEASETRACKID")
+    }
+
+    /// MusicBrainz work ID
+    pub fn musicbrainz_workid(&self) -> Option<&str> {
+        self.mbid("MUSICBRAINZ_WORKID")
+    }
+
+    /// Get MusicBrainz ID tag, ignoring values which don't look like UUIDs
+    fn mbid(&self, name: &str) -> Option<&str> {
+        self.tag(name).filter(|id| is_uuid(id))
+    }
+}
+
+/// Check if the string is a UUID in its canonical hyphenated form
+fn is_uuid(s: &str) -> bool {
+    s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Parse number from a tag value like `3` or `3/12`
+fn parse_number(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+#[cfg(feature = "chrono")]
+impl Song {
+    /// Last modification time, if known and valid
+    pub fn last_modified(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.last_mod.as_deref().and_then(parse_timestamp)
+    }
+
+    /// Time the song was added to the database, if known and valid
+    pub fn added_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.added.as_deref().and_then(parse_timestamp)
+    }
 }
 
 impl FromIter for Song {
     /// build song from map
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Song, Error> {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Song, Error> {
         let mut result = Song::default();
 
         for res in iter {
             let line = res?;
-            match &*line.0 {
-                "file" => result.file = line.1.to_owned(),
-                "Title" => result.title = Some(line.1.to_owned()),
-                "Last-Modified" => result.last_mod = Some(line.1.to_owned()),
-                "Artist" => result.artist = Some(line.1.to_owned()),
-                "Name" => result.name = Some(line.1.to_owned()),
-                // Deprecated in MPD.
+            match line.0.as_ref() {
+                "file" => result.file = line.1.into(),
+                // Repeated values of these tags are kept with the other tags
+                "Title" if result.title.is_none() => result.title = Some(line.1.into()),
+                "Artist" if result.artist.is_none() => result.artist = Some(line.1.into()),
+                "Name" if result.name.is_none() => result.name = Some(line.1.into()),
+                "Last-Modified" => result.last_mod = Some(line.1.into()),
+                "Added" => result.added = Some(line.1.into()),
+                // Deprecated in MPD, only used if precise duration is not reported
+                "Time" if result.duration.is_none() => result.duration = Some(Duration::from_secs(line.1.as_ref().parse()?)),
                 "Time" => (),
-                "duration" => result.duration = Some(Duration::try_from_secs_f64(line.1.parse()?)?),
-                "Range" => result.range = Some(line.1.parse()?),
+                "duration" => result.duration = Some(Duration::try_from_secs_f64(line.1.as_ref().parse()?)?),
+                "Range" => result.range = Some(line.1.as_ref().parse()?),
+                // Not essential, so a format MPD reports in some unexpected way doesn't fail the whole listing
+                "Format" => result.format = line.1.as_ref().parse().ok(),
                 "Id" => match result.place {
-                    None => result.place = Some(QueuePlace { id: Id(line.1.parse()?), pos: 0, prio: 0 }),
-                    Some(ref mut place) => place.id = Id(line.1.parse()?),
+                    None => result.place = Some(QueuePlace { id: Id(line.1.as_ref().parse()?), pos: 0, prio: 0 }),
+                    Some(ref mut place) => place.id = Id(line.1.as_ref().parse()?),
                 },
                 "Pos" => match result.place {
-                    None => result.place = Some(QueuePlace { pos: line.1.parse()?, id: Id(0), prio: 0 }),
-                    Some(ref mut place) => place.pos = line.1.parse()?,
+                    None => result.place = Some(QueuePlace { pos: line.1.as_ref().parse()?, id: Id(0), prio: 0 }),
+                    Some(ref mut place) => place.pos = line.1.as_ref().parse()?,
                 },
                 "Prio" => match result.place {
-                    None => result.place = Some(QueuePlace { prio: line.1.parse()?, id: Id(0), pos: 0 }),
-                    Some(ref mut place) => place.prio = line.1.parse()?,
+                    None => result.place = Some(QueuePlace { prio: line.1.as_ref().parse()?, id: Id(0), pos: 0 }),
+                    Some(ref mut place) => place.prio = line.1.as_ref().parse()?,
                 },
                 _ => {
-                    result.tags.push((line.0, line.1));
+                    let key = match intern_tag(line.0.as_ref()) {
+                        Some(key) => Cow::Borrowed(key),
+                        None => Cow::Owned(line.0.into()),
+                    };
+                    result.tags.push((key, line.1.into()));
                 }
             }
         }
@@ -143,6 +355,47 @@ impl FromIter for Song {
     }
 }
 
+/// Get static copy of a tag name known to MPD, so parsed songs don't allocate it again and again
+pub fn intern_tag(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "Artist" => "Artist",
+        "ArtistSort" => "ArtistSort",
+        "Album" => "Album",
+        "AlbumSort" => "AlbumSort",
+        "AlbumArtist" => "AlbumArtist",
+        "AlbumArtistSort" => "AlbumArtistSort",
+        "Title" => "Title",
+        "TitleSort" => "TitleSort",
+        "Track" => "Track",
+        "Name" => "Name",
+        "Genre" => "Genre",
+        "Mood" => "Mood",
+        "Date" => "Date",
+        "OriginalDate" => "OriginalDate",
+        "Composer" => "Composer",
+        "ComposerSort" => "ComposerSort",
+        "Performer" => "Performer",
+        "Conductor" => "Conductor",
+        "Work" => "Work",
+        "Ensemble" => "Ensemble",
+        "Movement" => "Movement",
+        "MovementNumber" => "MovementNumber",
+        "ShowMovement" => "ShowMovement",
+        "Location" => "Location",
+        "Grouping" => "Grouping",
+        "Comment" => "Comment",
+        "Disc" => "Disc",
+        "Label" => "Label",
+        "MUSICBRAINZ_ARTISTID" => "MUSICBRAINZ_ARTISTID",
+        "MUSICBRAINZ_ALBUMID" => "MUSICBRAINZ_ALBUMID",
+        "MUSICBRAINZ_ALBUMARTISTID" => "MUSICBRAINZ_ALBUMARTISTID",
+        "MUSICBRAINZ_TRACKID" => "MUSICBRAINZ_TRACKID",
+        "MUSICBRAINZ_RELEASETRACKID" => "MUSICBRAINZ_RELEASETRACKID",
+        "MUSICBRAINZ_WORKID" => "MUSICBRAINZ_WORKID",
+        "MUSICBRAINZ_RELEASEGROUPID" => "MUSICBRAINZ_RELEASEGROUPID",
+        _ => return None,
+    })
+}
 
 /// A pair of position and ID as returned by the `plchangesposid` command.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -156,14 +409,14 @@ pub struct PosIdChange {
 
 impl FromIter for PosIdChange {
     /// build PosIdChange from map
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<PosIdChange, Error> {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<PosIdChange, Error> {
         let mut result = PosIdChange::default();
 
         for res in iter {
             let line = res?;
-            match &*line.0 {
-                "cpos" => result.pos = line.1.parse::<u32>()?,
-                "Id" => result.id = Id(line.1.parse::<u32>()?),
+            match line.0.as_ref() {
+                "cpos" => result.pos = line.1.as_ref().parse::<u32>()?,
+                "Id" => result.id = Id(line.1.as_ref().parse::<u32>()?),
                 _ => {}
             }
         }
@@ -171,3 +424,148 @@ impl FromIter for PosIdChange {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{intern_tag, AudioFormat, QueuePosition, Range, Song};
+    use crate::convert::FromIter;
+    use std::borrow::Cow;
+    use std::time::Duration;
+
+    #[test]
+    fn range_parse() {
+        assert_eq!("1.500-3.000".parse::<Range>().unwrap(), Range(Duration::from_millis(1500), Some(Duration::from_secs(3))));
+        assert_eq!("10-".parse::<Range>().unwrap(), Range(Duration::from_secs(10), None));
+        assert_eq!("-2".parse::<Range>().unwrap(), Range(Duration::ZERO, Some(Duration::from_secs(2))));
+        assert!("a-b".parse::<Range>().is_err());
+    }
+
+    #[test]
+    fn range_format() {
+        assert_eq!(Range(Duration::from_millis(1500), Some(Duration::from_secs(3))).to_string(), "1.500:3");
+        assert_eq!(Range::default().to_string(), "0:");
+    }
+
+    #[test]
+    fn queue_position_format() {
+        assert_eq!(QueuePosition::Absolute(3).to_string(), "3");
+        assert_eq!(QueuePosition::AfterCurrent(0).to_string(), "+0");
+        assert_eq!(QueuePosition::BeforeCurrent(2).to_string(), "-2");
+    }
+
+    #[test]
+    fn interned_tags() {
+        let pairs = vec![("file", "a.flac"), ("Album", "A"), ("X-Custom", "B")].into_iter().map(Ok);
+        let song = Song::from_iter(pairs).unwrap();
+        assert!(matches!(song.tags[0].0, Cow::Borrowed("Album")));
+        assert!(matches!(song.tags[1].0, Cow::Owned(ref k) if k == "X-Custom"));
+        assert_eq!(intern_tag("MUSICBRAINZ_TRACKID"), Some("MUSICBRAINZ_TRACKID"));
+        assert_eq!(intern_tag("album"), None);
+    }
+
+    #[test]
+    fn multiple_values() {
+        let pairs = vec![("file", "a.flac"), ("Artist", "A"), ("Genre", "Rock"), ("Artist", "B"), ("Genre", "Jazz")];
+        let song = Song::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(song.artist.as_deref(), Some("A"));
+        assert_eq!(song.tag_values("artist").collect::<Vec<_>>(), ["A", "B"]);
+        assert_eq!(song.tag_values("Genre").collect::<Vec<_>>(), ["Rock", "Jazz"]);
+        assert_eq!(song.tag("Genre"), Some("Rock"));
+        assert_eq!(song.tag("Album"), None);
+    }
+
+    #[test]
+    fn accessors() {
+        let pairs = vec![("file", "a.flac"), ("Album", "X"), ("AlbumArtist", "Y"), ("Track", "3/12"), ("Disc", "1"), ("Date", "2001")];
+        let song = Song::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(song.album(), Some("X"));
+        assert_eq!(song.album_artist(), Some("Y"));
+        assert_eq!(song.track(), Some(3));
+        assert_eq!(song.disc(), Some(1));
+        assert_eq!(song.date(), Some("2001"));
+        assert_eq!(song.genre(), None);
+    }
+
+    #[test]
+    fn format() {
+        let pairs = vec![("file", "a.flac"), ("Format", "44100:24:2")];
+        let song = Song::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(song.format, Some(AudioFormat { rate: 44100, bits: 24, chans: 2 }));
+        assert!(song.tags.is_empty());
+    }
+
+    #[test]
+    fn musicbrainz_ids() {
+        let pairs = vec![
+            ("file", "a.flac"),
+            ("MUSICBRAINZ_TRACKID", "c7e4b3e0-4b9c-4a9e-8d0f-0b6e5c1a2d3f"),
+            ("MUSICBRAINZ_ALBUMID", "not-an-id"),
+        ];
+        let song = Song::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(song.musicbrainz_trackid(), Some("c7e4b3e0-4b9c-4a9e-8d0f-0b6e5c1a2d3f"));
+        assert_eq!(song.musicbrainz_albumid(), None);
+        assert_eq!(song.musicbrainz_workid(), None);
+    }
+
+    #[test]
+    fn duration() {
+        let pairs = vec![("file", "a.flac"), ("Time", "3"), ("duration", "2.500")];
+        assert_eq!(Song::from_iter(pairs.into_iter().map(Ok)).unwrap().duration, Some(Duration::from_millis(2500)));
+        let pairs = vec![("file", "a.flac"), ("Time", "3")];
+        assert_eq!(Song::from_iter(pairs.into_iter().map(Ok)).unwrap().duration, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn queue_entry() {
+        let pairs = vec![("file", "a.flac"), ("Range", "1.500-"), ("Pos", "2"), ("Id", "7"), ("Prio", "10")];
+        let song = Song::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(song.place, Some(super::QueuePlace { id: super::Id(7), pos: 2, prio: 10 }));
+        assert_eq!(song.range, Some(Range(Duration::from_millis(1500), None)));
+    }
+
+    #[test]
+    fn timestamp_fields() {
+        let pairs = vec![("file", "a.flac"), ("Last-Modified", "2024-01-31T12:30:00Z"), ("Added", "2024-02-01T08:00:00Z")];
+        let song = Song::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(song.last_mod.as_deref(), Some("2024-01-31T12:30:00Z"));
+        assert_eq!(song.added.as_deref(), Some("2024-02-01T08:00:00Z"));
+        assert!(song.tags.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let pairs = vec![
+            ("file", "a.flac"),
+            ("Last-Modified", "2024-01-31T12:30:00Z"),
+            ("Artist", "A"),
+            ("Artist", "B"),
+            ("Genre", "Rock"),
+            ("X-Custom", "C"),
+            ("Format", "44100:16:2"),
+            ("Range", "1.500-"),
+            ("duration", "2.500"),
+            ("Pos", "2"),
+            ("Id", "7"),
+            ("Prio", "10"),
+        ];
+        let song = Song::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        let loaded: Song = serde_json::from_str(&serde_json::to_string(&song).unwrap()).unwrap();
+        assert_eq!(loaded, song);
+        assert!(matches!(loaded.tags[1].0, Cow::Borrowed("Genre")));
+
+        let partial: Song = serde_json::from_str(r#"{"file": "b.flac"}"#).unwrap();
+        assert_eq!(partial.file, "b.flac");
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn timestamps() {
+        use chrono::{TimeZone, Utc};
+
+        let song = super::Song { last_mod: Some("2024-01-31T12:30:00Z".into()), added: Some("2024-02-01T08:00:00Z".into()), ..Default::default() };
+        assert_eq!(song.last_modified(), Some(Utc.with_ymd_and_hms(2024, 1, 31, 12, 30, 0).unwrap()));
+        assert_eq!(song.added_at(), Some(Utc.with_ymd_and_hms(2024, 2, 1, 8, 0, 0).unwrap()));
+        assert_eq!(super::Song { last_mod: Some("bogus".into()), ..Default::default() }.last_modified(), None);
+    }
+}
@@ -1,6 +1,6 @@
 //! The module describes DB and playback statistics
 
-use crate::convert::FromIter;
+use crate::convert::{FromIter, Text};
 use crate::error::Error;
 
 use std::time::Duration;
@@ -39,21 +39,56 @@ impl Default for Stats {
     }
 }
 
+#[cfg(feature = "chrono")]
+impl Stats {
+    /// Last DB update time
+    pub fn db_updated(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.db_update.as_secs() as i64, 0)
+    }
+}
+
 impl FromIter for Stats {
     /// build stats from iterator
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Stats, Error> {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Stats, Error> {
         let mut result = Stats::default();
 
         for res in iter {
             let line = res?;
-            match &*line.0 {
-                "artists" => result.artists = line.1.parse()?,
-                "albums" => result.albums = line.1.parse()?,
-                "songs" => result.songs = line.1.parse()?,
-                "uptime" => result.uptime = Duration::from_secs(line.1.parse()?),
-                "playtime" => result.playtime = Duration::from_secs(line.1.parse()?),
-                "db_playtime" => result.db_playtime = Duration::from_secs(line.1.parse()?),
-                "db_update" => result.db_update = Duration::from_secs(line.1.parse()?),
+            match line.0.as_ref() {
+                "artists" => result.artists = line.1.as_ref().parse()?,
+                "albums" => result.albums = line.1.as_ref().parse()?,
+                "songs" => result.songs = line.1.as_ref().parse()?,
+                "uptime" => result.uptime = Duration::from_secs(line.1.as_ref().parse()?),
+                "playtime" => result.playtime = Duration::from_secs(line.1.as_ref().parse()?),
+                "db_playtime" => result.db_playtime = Duration::from_secs(line.1.as_ref().parse()?),
+                "db_update" => result.db_update = Duration::from_secs(line.1.as_ref().parse()?),
+                _ => (),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Number and total duration of songs matching a query, as returned by `count`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Count {
+    /// number of songs
+    pub songs: u32,
+    /// total duration of songs, seconds resolution
+    pub playtime: Duration,
+}
+
+impl FromIter for Count {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Count, Error> {
+        let mut result = Count::default();
+
+        for res in iter {
+            let line = res?;
+            match line.0.as_ref() {
+                "songs" => result.songs = line.1.as_ref().parse()?,
+                "playtime" => result.playtime = Duration::from_secs(line.1.as_ref().parse()?),
                 _ => (),
             }
         }
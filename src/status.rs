@@ -1,10 +1,13 @@
 //! The module defines MPD status data structures
 
-use crate::convert::FromIter;
-use crate::error::{Error, ParseError};
+use crate::client::Client;
+use crate::convert::{FromIter, Text};
+use crate::error::{Error, ParseError, ProtoError};
+use crate::proto::Proto;
 use crate::song::{Id, QueuePlace};
 
 use std::fmt;
+use std::io::{Read, Write};
 use std::str::FromStr;
 use std::time::Duration;
 
@@ -33,10 +36,13 @@ pub struct Status {
     /// next song to play place in the queue
     pub nextsong: Option<QueuePlace>,
     /// time current song played, and total song duration (in seconds resolution)
+    ///
+    /// Legacy field, use `elapsed` and `duration` instead: they are filled from this one
+    /// if the server is too old to report them.
     pub time: Option<(Duration, Duration)>,
     /// elapsed play time current song played (in milliseconds resolution)
     pub elapsed: Option<Duration>,
-    /// current song duration
+    /// current song duration (in milliseconds resolution)
     pub duration: Option<Duration>,
     /// current song bitrate, kbps
     pub bitrate: Option<u32>,
@@ -55,63 +61,76 @@ pub struct Status {
     pub error: Option<String>,
     /// replay gain mode
     pub replaygain: Option<ReplayGain>,
+    /// name of the partition the connection is attached to (MPD 0.22+)
+    pub partition: Option<String>,
+    /// name of the stored playlist last loaded into the queue (MPD 0.24+)
+    pub last_loaded_playlist: Option<String>,
 }
 
 impl FromIter for Status {
-    fn from_iter<I: Iterator<Item = Result<(String, String), Error>>>(iter: I) -> Result<Status, Error> {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<Status, Error> {
         let mut result = Status::default();
 
         for res in iter {
             let line = res?;
-            match &*line.0 {
-                "volume" => result.volume = line.1.parse()?,
+            match line.0.as_ref() {
+                "volume" => result.volume = line.1.as_ref().parse()?,
 
-                "repeat" => result.repeat = &*line.1 == "1",
-                "random" => result.random = &*line.1 == "1",
-                "single" => result.single = &*line.1 == "1",
-                "consume" => result.consume = &*line.1 == "1",
+                "repeat" => result.repeat = line.1.as_ref() == "1",
+                "random" => result.random = line.1.as_ref() == "1",
+                "single" => result.single = line.1.as_ref() == "1",
+                "consume" => result.consume = line.1.as_ref() == "1",
 
-                "playlist" => result.queue_version = line.1.parse()?,
-                "playlistlength" => result.queue_len = line.1.parse()?,
-                "state" => result.state = line.1.parse()?,
+                "playlist" => result.queue_version = line.1.as_ref().parse()?,
+                "playlistlength" => result.queue_len = line.1.as_ref().parse()?,
+                "state" => result.state = line.1.as_ref().parse()?,
                 "songid" => match result.song {
-                    None => result.song = Some(QueuePlace { id: Id(line.1.parse()?), pos: 0, prio: 0 }),
-                    Some(ref mut place) => place.id = Id(line.1.parse()?),
+                    None => result.song = Some(QueuePlace { id: Id(line.1.as_ref().parse()?), pos: 0, prio: 0 }),
+                    Some(ref mut place) => place.id = Id(line.1.as_ref().parse()?),
                 },
                 "song" => match result.song {
-                    None => result.song = Some(QueuePlace { pos: line.1.parse()?, id: Id(0), prio: 0 }),
-                    Some(ref mut place) => place.pos = line.1.parse()?,
+                    None => result.song = Some(QueuePlace { pos: line.1.as_ref().parse()?, id: Id(0), prio: 0 }),
+                    Some(ref mut place) => place.pos = line.1.as_ref().parse()?,
                 },
                 "nextsongid" => match result.nextsong {
-                    None => result.nextsong = Some(QueuePlace { id: Id(line.1.parse()?), pos: 0, prio: 0 }),
-                    Some(ref mut place) => place.id = Id(line.1.parse()?),
+                    None => result.nextsong = Some(QueuePlace { id: Id(line.1.as_ref().parse()?), pos: 0, prio: 0 }),
+                    Some(ref mut place) => place.id = Id(line.1.as_ref().parse()?),
                 },
                 "nextsong" => match result.nextsong {
-                    None => result.nextsong = Some(QueuePlace { pos: line.1.parse()?, id: Id(0), prio: 0 }),
-                    Some(ref mut place) => place.pos = line.1.parse()?,
+                    None => result.nextsong = Some(QueuePlace { pos: line.1.as_ref().parse()?, id: Id(0), prio: 0 }),
+                    Some(ref mut place) => place.pos = line.1.as_ref().parse()?,
                 },
                 "time" => {
-                    let mut splits = line.1.splitn(2, ':').map(|v| v.parse().map_err(ParseError::BadInteger).map(Duration::from_secs));
+                    let mut splits = line.1.as_ref().splitn(2, ':').map(|v| v.parse().map_err(ParseError::BadInteger).map(Duration::from_secs));
                     result.time = match (splits.next(), splits.next()) {
                         (Some(Ok(a)), Some(Ok(b))) => Ok(Some((a, b))),
                         (Some(Err(e)), _) | (_, Some(Err(e))) => Err(e),
                         _ => Ok(None),
                     }?;
                 }
-                "elapsed" => result.elapsed = Some(Duration::try_from_secs_f64(line.1.parse()?)?),
-                "duration" => result.duration = Some(Duration::try_from_secs_f64(line.1.parse()?)?),
-                "bitrate" => result.bitrate = Some(line.1.parse()?),
-                "xfade" => result.crossfade = Some(Duration::from_secs(line.1.parse()?)),
-                "mixrampdb" => result.mixrampdb = line.1.parse::<f32>()?,
-                "mixrampdelay" => result.mixrampdelay = Some(Duration::from_secs_f64(line.1.parse()?)),
-                "audio" => result.audio = Some(line.1.parse()?),
-                "updating_db" => result.updating_db = Some(line.1.parse()?),
-                "error" => result.error = Some(line.1.to_owned()),
-                "replay_gain_mode" => result.replaygain = Some(line.1.parse()?),
+                "elapsed" => result.elapsed = Some(Duration::try_from_secs_f64(line.1.as_ref().parse()?)?),
+                "duration" => result.duration = Some(Duration::try_from_secs_f64(line.1.as_ref().parse()?)?),
+                "bitrate" => result.bitrate = Some(line.1.as_ref().parse()?),
+                "xfade" => result.crossfade = Some(Duration::from_secs(line.1.as_ref().parse()?)),
+                "mixrampdb" => result.mixrampdb = line.1.as_ref().parse::<f32>()?,
+                // MPD reports `nan` when mixramp is disabled
+                "mixrampdelay" => result.mixrampdelay = Duration::try_from_secs_f64(line.1.as_ref().parse()?).ok(),
+                "audio" => result.audio = Some(line.1.as_ref().parse()?),
+                "updating_db" => result.updating_db = Some(line.1.as_ref().parse()?),
+                "error" => result.error = Some(line.1.into()),
+                "replay_gain_mode" => result.replaygain = Some(line.1.as_ref().parse()?),
+                "partition" => result.partition = Some(line.1.into()),
+                "lastloadedplaylist" => result.last_loaded_playlist = Some(line.1.into()),
                 _ => (),
             }
         }
 
+        // Servers older than 0.20 only report time in seconds
+        if let Some((elapsed, duration)) = result.time {
+            result.elapsed.get_or_insert(elapsed);
+            result.duration.get_or_insert(duration);
+        }
+
         Ok(result)
     }
 }
@@ -189,6 +208,30 @@ impl FromStr for State {
     }
 }
 
+/// The way seek position is interpreted
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum SeekMode {
+    /// seek to the given position
+    #[default]
+    Absolute,
+    /// seek forward from the current position
+    Forward,
+    /// seek backward from the current position
+    Backward,
+}
+
+impl SeekMode {
+    /// Format seek position (in seconds) as `seekcur` argument
+    pub(crate) fn position(self, pos: f64) -> String {
+        match self {
+            SeekMode::Absolute => pos.to_string(),
+            SeekMode::Forward => format!("+{}", pos),
+            SeekMode::Backward => format!("-{}", pos),
+        }
+    }
+}
+
 /// Replay gain mode
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -228,3 +271,114 @@ impl fmt::Display for ReplayGain {
         })
     }
 }
+
+/// Replay gain settings which can be changed at runtime
+///
+/// Preamp and limit settings are only configured in `mpd.conf`, the protocol
+/// doesn't expose them, so this only holds the replay gain mode for now.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGainConfig {
+    /// replay gain mode
+    pub mode: ReplayGain,
+}
+
+impl ReplayGainConfig {
+    /// Read current replay gain settings
+    pub fn get<S: Read + Write>(client: &mut Client<S>) -> Result<ReplayGainConfig, Error> {
+        client.run_command("replay_gain_status", ()).and_then(|_| client.read_struct())
+    }
+
+    /// Apply all the settings in a single command list
+    pub fn apply<S: Read + Write>(&self, client: &mut Client<S>) -> Result<(), Error> {
+        client.run_command_list(&[("replay_gain_mode", self.mode)]).and_then(|_| client.expect_ok())
+    }
+}
+
+/// Crossfade and MixRamp settings
+///
+/// MixRamp overlaps songs based on their volume profile and takes precedence over crossfade,
+/// unless the songs lack MixRamp tags or the delay is not set.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FadeSettings {
+    /// crossfade duration, whole seconds, zero disables crossfade
+    pub crossfade: Duration,
+    /// mixramp threshold, dB
+    pub mixrampdb: f32,
+    /// mixramp delay, `None` disables mixramp
+    pub mixrampdelay: Option<Duration>,
+}
+
+impl FadeSettings {
+    /// Read current fade settings
+    pub fn get<S: Read + Write>(client: &mut Client<S>) -> Result<FadeSettings, Error> {
+        client.status().map(|status| FadeSettings {
+            crossfade: status.crossfade.unwrap_or_default(),
+            mixrampdb: status.mixrampdb,
+            mixrampdelay: status.mixrampdelay,
+        })
+    }
+
+    /// Apply all the settings in a single command list
+    pub fn apply<S: Read + Write>(&self, client: &mut Client<S>) -> Result<(), Error> {
+        let commands = self.commands();
+        client.run_command_list(&commands).and_then(|_| client.expect_ok())
+    }
+
+    fn commands(&self) -> [(&'static str, String); 3] {
+        [
+            ("crossfade", self.crossfade.as_secs().to_string()),
+            ("mixrampdb", self.mixrampdb.to_string()),
+            ("mixrampdelay", self.mixrampdelay.map_or_else(|| "nan".to_owned(), |delay| delay.as_secs_f64().to_string())),
+        ]
+    }
+}
+
+impl FromIter for ReplayGainConfig {
+    fn from_iter<K: Text, V: Text, I: Iterator<Item = Result<(K, V), Error>>>(iter: I) -> Result<ReplayGainConfig, Error> {
+        let mut mode = None;
+        for res in iter {
+            let (key, value) = res?;
+            if key.as_ref() == "replay_gain_mode" {
+                mode = Some(value.as_ref().parse()?);
+            }
+        }
+        Ok(ReplayGainConfig { mode: mode.ok_or(ProtoError::NoField("replay_gain_mode"))? })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FadeSettings, Status};
+    use crate::convert::FromIter;
+    use std::time::Duration;
+
+    #[test]
+    fn legacy_time() {
+        let pairs = vec![("time", "3:10"), ("elapsed", "3.250")];
+        let status = Status::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(status.elapsed, Some(Duration::from_millis(3250)));
+        assert_eq!(status.duration, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn last_loaded_playlist() {
+        let pairs = vec![("state", "stop"), ("lastloadedplaylist", "Favorites")];
+        let status = Status::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(status.last_loaded_playlist.as_deref(), Some("Favorites"));
+    }
+
+    #[test]
+    fn mixramp_disabled() {
+        let pairs = vec![("mixrampdb", "-17"), ("mixrampdelay", "nan")];
+        let status = Status::from_iter(pairs.into_iter().map(Ok)).unwrap();
+        assert_eq!(status.mixrampdelay, None);
+
+        let fade = FadeSettings { crossfade: Duration::from_secs(2), mixrampdb: -17.0, mixrampdelay: None };
+        let commands = fade.commands();
+        assert_eq!(commands[0].1, "2");
+        assert_eq!(commands[1].1, "-17");
+        assert_eq!(commands[2].1, "nan");
+    }
+}
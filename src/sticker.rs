@@ -1,12 +1,29 @@
+//! The module defines sticker data structures
+//!
+//! Stickers are arbitrary name-value pairs attached to songs (or other objects, like
+//! playlists) and stored in the MPD sticker database. They are commonly used for ratings
+//! and play counts.
+
 use crate::error::ParseError;
+use std::fmt;
 use std::str::FromStr;
 
+/// Sticker attached to some object
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Sticker {
+    /// sticker name
     pub name: String,
+    /// sticker value
     pub value: String,
 }
 
+impl fmt::Display for Sticker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
 impl FromStr for Sticker {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Sticker, ParseError> {
@@ -17,3 +34,41 @@ impl FromStr for Sticker {
         }
     }
 }
+
+/// Comparison operator for sticker search
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StickerOp {
+    /// value equals to the given string
+    Eq,
+    /// value is less than the given string (compared as strings)
+    Lt,
+    /// value is greater than the given string (compared as strings)
+    Gt,
+    /// value equals to the given integer (MPD 0.24+)
+    IntEq,
+    /// value is less than the given integer (MPD 0.24+)
+    IntLt,
+    /// value is greater than the given integer (MPD 0.24+)
+    IntGt,
+    /// value contains the given string (MPD 0.24+)
+    Contains,
+    /// value starts with the given string (MPD 0.24+)
+    StartsWith,
+}
+
+impl fmt::Display for StickerOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::StickerOp as O;
+        f.write_str(match *self {
+            O::Eq => "=",
+            O::Lt => "<",
+            O::Gt => ">",
+            O::IntEq => "eq",
+            O::IntLt => "lt",
+            O::IntGt => "gt",
+            O::Contains => "contains",
+            O::StartsWith => "starts_with",
+        })
+    }
+}
@@ -0,0 +1,269 @@
+//! The module defines scripted MPD server for unit tests
+//!
+//! [`MockServer`] is set up with expected requests and canned replies, and
+//! gives out [`Client`]s connected to it through an in-memory stream, so code
+//! using the client can be tested without running MPD.
+//!
+//! ```rust
+//! use mpd::testing::MockServer;
+//! use mpd::State;
+//!
+//! let server = MockServer::new();
+//! server
+//!     .expect("status", "volume: 50\nstate: play")
+//!     .expect("replay_gain_status", "replay_gain_mode: off")
+//!     .expect("setvol 60", "");
+//!
+//! let mut conn = server.client();
+//! assert_eq!(conn.status().unwrap().state, State::Play);
+//! conn.volume(60).unwrap();
+//! server.verify();
+//! ```
+//!
+//! Requests are compared by their words, so quoting doesn't matter: both `setvol 60`
+//! and `setvol "60"` match `setvol "60"` request sent by the client. Commands of
+//! a command list are matched one by one, and replies are joined into a single response.
+//! Note that some client methods send several commands, e.g. [`status()`](Client::status)
+//! also asks for replay gain status.
+//!
+//! If the client sends an unexpected request, the server replies with an error
+//! and remembers the request, so [`verify()`](MockServer::verify) fails later.
+
+use crate::client::Client;
+use crate::error::ErrorCode;
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+struct Expectation {
+    request: Vec<String>,
+    reply: Result<String, (ErrorCode, String)>,
+}
+
+#[derive(Default)]
+struct Script {
+    expected: VecDeque<Expectation>,
+    unexpected: Vec<String>,
+}
+
+/// Scripted MPD server
+///
+/// The server is cheap to clone, and all the clones share the same script.
+#[derive(Clone)]
+pub struct MockServer {
+    script: Arc<Mutex<Script>>,
+    version: String,
+}
+
+impl Default for MockServer {
+    fn default() -> MockServer {
+        MockServer { script: Arc::default(), version: "0.24.0".to_owned() }
+    }
+}
+
+impl MockServer {
+    /// Create server without any expected requests, reporting protocol version 0.24.0
+    pub fn new() -> MockServer {
+        MockServer::default()
+    }
+
+    /// Report another protocol version to new clients
+    pub fn version(mut self, version: &str) -> MockServer {
+        self.version = version.to_owned();
+        self
+    }
+
+    /// Expect the request and respond with the reply, given as response pairs without final `OK`
+    pub fn expect(&self, request: &str, reply: &str) -> &MockServer {
+        self.push(request, Ok(reply.to_owned()))
+    }
+
+    /// Expect the request and respond with the error
+    pub fn expect_error(&self, request: &str, code: ErrorCode, message: &str) -> &MockServer {
+        self.push(request, Err((code, message.to_owned())))
+    }
+
+    fn push(&self, request: &str, reply: Result<String, (ErrorCode, String)>) -> &MockServer {
+        self.script().expected.push_back(Expectation { request: words(request), reply });
+        self
+    }
+
+    /// Connect a new client to the server
+    pub fn client(&self) -> Client<MockStream> {
+        let output = format!("OK MPD {}\n", self.version).into_bytes().into();
+        let stream = MockStream { script: self.script.clone(), input: Vec::new(), list: None, output };
+        Client::new(stream).expect("mock server greeting")
+    }
+
+    /// Requests which were not expected, in the order they were received
+    pub fn unexpected(&self) -> Vec<String> {
+        self.script().unexpected.clone()
+    }
+
+    /// Check that all expected requests and nothing else was received
+    ///
+    /// # Panics
+    ///
+    /// Panics if some requests were unexpected, or some expected requests were not received.
+    pub fn verify(&self) {
+        let script = self.script();
+        assert!(script.unexpected.is_empty(), "unexpected requests: {:?}", script.unexpected);
+        let missing: Vec<String> = script.expected.iter().map(|expected| expected.request.join(" ")).collect();
+        assert!(missing.is_empty(), "expected requests were not received: {:?}", missing);
+    }
+
+    fn script(&self) -> MutexGuard<'_, Script> {
+        self.script.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Client side of in-memory connection to [`MockServer`]
+///
+/// Reading when the server has nothing to send reports end of stream, so
+/// the client fails instead of waiting forever.
+pub struct MockStream {
+    script: Arc<Mutex<Script>>,
+    /// bytes sent by the client, until the next newline
+    input: Vec<u8>,
+    /// commands of the command list being received
+    list: Option<(bool, Vec<String>)>,
+    /// bytes to be read by the client
+    output: VecDeque<u8>,
+}
+
+impl MockStream {
+    fn handle_line(&mut self, line: &str) {
+        match (line, &mut self.list) {
+            ("command_list_begin", None) => self.list = Some((false, Vec::new())),
+            ("command_list_ok_begin", None) => self.list = Some((true, Vec::new())),
+            ("command_list_end", Some(_)) => {
+                let (list_ok, commands) = self.list.take().unwrap_or_default();
+                self.handle_list(list_ok, commands);
+            }
+            (_, Some((_, commands))) => commands.push(line.to_owned()),
+            (_, None) => self.handle_list(false, vec![line.to_owned()]),
+        }
+    }
+
+    fn handle_list(&mut self, list_ok: bool, commands: Vec<String>) {
+        let mut script = self.script.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut response = String::new();
+        for (pos, command) in commands.iter().enumerate() {
+            let request = words(command);
+            let name = request.first().cloned().unwrap_or_default();
+            let reply = match script.expected.front() {
+                Some(expected) if expected.request == request => script.expected.pop_front().map(|expected| expected.reply),
+                _ => None,
+            };
+            match reply {
+                Some(Ok(reply)) => {
+                    for line in reply.lines() {
+                        response.push_str(line);
+                        response.push('\n');
+                    }
+                    if list_ok {
+                        response.push_str("list_OK\n");
+                    }
+                }
+                Some(Err((code, message))) => {
+                    response.push_str(&format!("ACK [{}@{}] {{{}}} {}\n", code as u8, pos, name, message));
+                    self.output.extend(response.bytes());
+                    return;
+                }
+                None => {
+                    script.unexpected.push(command.clone());
+                    response.push_str(&format!("ACK [{}@{}] {{{}}} unexpected request\n", ErrorCode::UnknownCmd as u8, pos, name));
+                    self.output.extend(response.bytes());
+                    return;
+                }
+            }
+        }
+        response.push_str("OK\n");
+        self.output.extend(response.bytes());
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.output.len());
+        for (byte, out) in self.output.drain(..len).zip(buf.iter_mut()) {
+            *out = byte;
+        }
+        Ok(len)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            if byte == b'\n' {
+                let line = String::from_utf8_lossy(&self.input).into_owned();
+                self.input.clear();
+                self.handle_line(&line);
+            } else {
+                self.input.push(byte);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Split request into words, unquoting quoted arguments
+fn words(request: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = request.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == ' ' {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => word.extend(chars.next()),
+                    '"' => break,
+                    _ => word.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ' ' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+        }
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::{words, MockServer};
+    use crate::error::{Error, ErrorCode};
+
+    #[test]
+    fn request_words() {
+        assert_eq!(words(r#"add "My \"Song\".flac""#), ["add", r#"My "Song".flac"#]);
+        assert_eq!(words("setvol  60"), ["setvol", "60"]);
+    }
+
+    #[test]
+    fn errors() {
+        let server = MockServer::new();
+        server.expect_error("play", ErrorCode::NoExist, "no such song");
+        let mut conn = server.client();
+        assert!(matches!(conn.play(), Err(Error::Server(ref e)) if e.code == ErrorCode::NoExist && e.command == "play"));
+        assert!(conn.ping().is_err());
+        assert_eq!(server.unexpected(), ["ping"]);
+    }
+}
@@ -0,0 +1,48 @@
+//! The module defines TLS connection support
+//!
+//! MPD doesn't speak TLS itself, but it can be exposed through TLS terminating
+//! proxies like stunnel or haproxy. [`Client::connect_tls()`] wraps TCP stream
+//! with rustls, verifying the server certificate against Mozilla root
+//! certificates, and [`Client::connect_tls_with_config()`] allows to provide
+//! custom rustls configuration (e.g. with self-signed certificates trusted).
+
+use crate::client::Client;
+use crate::error::Result;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::io;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+/// TLS stream over TCP connection
+pub type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// Build default rustls client configuration, trusting Mozilla root certificates
+pub fn default_config() -> Result<Arc<ClientConfig>> {
+    let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+impl Client<TlsStream> {
+    /// Connect client to a TLS endpoint, verifying its certificate against given server name
+    pub fn connect_tls(host: &str, port: u16, server_name: ServerName<'static>) -> Result<Client<TlsStream>> {
+        Client::connect_tls_with_config(host, port, server_name, default_config()?)
+    }
+
+    /// Connect client to a TLS endpoint using custom rustls configuration
+    pub fn connect_tls_with_config(host: &str, port: u16, server_name: ServerName<'static>, config: Arc<ClientConfig>) -> Result<Client<TlsStream>> {
+        let connection = ClientConnection::new(config, server_name).map_err(tls_error)?;
+        let socket = TcpStream::connect((host, port))?;
+        Client::new(StreamOwned::new(connection, socket))
+    }
+}
+
+fn tls_error(e: rustls::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
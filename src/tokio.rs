@@ -0,0 +1,49 @@
+//! The module defines asynchronous client running on top of Tokio runtime
+//!
+//! [`Client`] is the runtime-agnostic [`aio::Client`] wrapping Tokio streams,
+//! with a few constructors for Tokio sockets.
+//!
+//! ```rust,no_run
+//! # async fn run() -> mpd::error::Result<()> {
+//! let mut conn = mpd::tokio::Client::connect("127.0.0.1:6600").await?;
+//! conn.play().await?;
+//! println!("Status: {:?}", conn.status().await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::aio;
+use crate::error::Result;
+
+use ::tokio::io::{AsyncRead, AsyncWrite};
+use ::tokio::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use ::tokio::net::UnixStream;
+use ::tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+#[cfg(unix)]
+use std::path::Path;
+
+/// Asynchronous client connection over a Tokio stream
+pub type Client<S = TcpStream> = aio::Client<Compat<S>>;
+
+impl Client<TcpStream> {
+    /// Connect client to some IP address
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Client<TcpStream>> {
+        Client::from_tokio(TcpStream::connect(addr).await?).await
+    }
+}
+
+#[cfg(unix)]
+impl Client<UnixStream> {
+    /// Connect client to a Unix domain socket
+    pub async fn connect_unix<P: AsRef<Path>>(path: P) -> Result<Client<UnixStream>> {
+        Client::from_tokio(UnixStream::connect(path).await?).await
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin> Client<S> {
+    /// Create client from some arbitrary pre-connected Tokio socket
+    pub async fn from_tokio(socket: S) -> Result<Client<S>> {
+        aio::Client::new(socket.compat()).await
+    }
+}
@@ -0,0 +1,165 @@
+// Protocol traffic tracing, compiled to no-ops without `tracing` feature
+//
+// Each command gets its own `mpd_command` span, and every line sent or received
+// is reported as a debug event within the span. Passwords and binary data are
+// never logged.
+
+use crate::proto::ToArguments;
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::Span as CommandSpan;
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub(crate) struct CommandSpan;
+
+/// Span used before any command is sent
+#[cfg(feature = "tracing")]
+pub(crate) fn no_span() -> CommandSpan {
+    CommandSpan::none()
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn no_span() -> CommandSpan {
+    CommandSpan
+}
+
+/// Start a span for a new command
+#[cfg(feature = "tracing")]
+pub(crate) fn command_span(command: &str) -> CommandSpan {
+    tracing::debug_span!("mpd_command", command)
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn command_span(_command: &str) -> CommandSpan {
+    CommandSpan
+}
+
+/// Report a command line sent to the server
+#[cfg(feature = "tracing")]
+pub(crate) fn sent<A: ToArguments>(span: &CommandSpan, command: &str, arguments: &A) {
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
+    }
+    // Command lists are reported line by line, with arguments already included
+    if command.split(' ').next() == Some("password") {
+        tracing::debug!(parent: span, line = "password <elided>", "sent");
+        return;
+    }
+    let mut line = String::new();
+    crate::codec::push_command(&mut line, command, arguments);
+    tracing::debug!(parent: span, line = line.trim_end_matches('\n'), "sent");
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn sent<A: ToArguments>(_span: &CommandSpan, _command: &str, _arguments: &A) {}
+
+/// Report a response line received from the server
+#[cfg(feature = "tracing")]
+pub(crate) fn received(span: &CommandSpan, line: &str) {
+    tracing::debug!(parent: span, line = line.trim_end_matches('\n'), "received");
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn received(_span: &CommandSpan, _line: &str) {}
+
+/// Report binary data received from the server, without the data itself
+#[cfg(feature = "tracing")]
+pub(crate) fn received_binary(span: &CommandSpan, length: usize) {
+    tracing::debug!(parent: span, length, "received binary data");
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn received_binary(_span: &CommandSpan, _length: usize) {}
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use crate::testing::MockServer;
+    use std::fmt::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Parent span and fields of an event
+    type RecordedEvent = (Option<u64>, String);
+
+    /// Subscriber remembering command of each span and fields of each event
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<String>>>,
+        events: Arc<Mutex<Vec<RecordedEvent>>>,
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields(String::new());
+            span.record(&mut fields);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(format!("{}{}", span.metadata().name(), fields.0));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.events.lock().unwrap().push((event.parent().map(Id::into_u64), fields.0));
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn command_spans() {
+        let server = MockServer::new();
+        server
+            .expect("password hunter2", "")
+            .expect("ping", "")
+            .expect("albumart cover.jpg 0", "size: 7\nbinary: 7\npicture");
+        let mut conn = server.client();
+
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            conn.login("hunter2").unwrap();
+            conn.ping().unwrap();
+            assert_eq!(conn.albumart(&"cover.jpg".to_owned()).unwrap(), b"picture");
+        });
+        server.verify();
+
+        let spans = recorder.spans.lock().unwrap();
+        assert_eq!(*spans, vec![
+            "mpd_command command=\"password\"",
+            "mpd_command command=\"ping\"",
+            "mpd_command command=\"albumart\"",
+        ]);
+
+        let events = recorder.events.lock().unwrap();
+        assert!(events.iter().all(|(parent, _)| parent.is_some()), "events outside of command span: {:?}", events);
+        assert!(events.iter().all(|(_, fields)| !fields.contains("hunter2") && !fields.contains("picture")), "secrets logged: {:?}", events);
+        assert!(events.contains(&(Some(1), " message=sent line=\"password <elided>\"".to_owned())), "{:?}", events);
+        assert!(events.contains(&(Some(3), " message=received binary data length=7".to_owned())), "{:?}", events);
+    }
+}
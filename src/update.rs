@@ -0,0 +1,109 @@
+//! The module defines database update job handle
+//!
+//! [`update()`](crate::Client::update) and [`rescan()`](crate::Client::rescan)
+//! only queue the job, and the database is updated in background. The returned
+//! [`UpdateJob`] can be used to wait until the job is finished:
+//!
+//! ```rust,no_run
+//! # use mpd::Client;
+//! let mut conn = Client::connect("127.0.0.1:6600").unwrap();
+//! let job = conn.rescan().unwrap();
+//! job.wait(&mut conn).unwrap();
+//! println!("Songs in database: {}", conn.stats().unwrap().songs);
+//! ```
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::idle::{Idle, Subsystem};
+
+use std::fmt;
+use std::io::{Read, Write};
+
+/// MPD wraps job ids around to 1 after this one
+const MAX_JOB_ID: u32 = 1 << 15;
+
+/// Database update job queued on the server
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UpdateJob {
+    /// job id, as reported in [`Status::updating_db`](crate::Status::updating_db)
+    pub id: u32,
+}
+
+impl UpdateJob {
+    /// Check if the job is finished
+    ///
+    /// Jobs are run one by one in the order of their ids, so the job is finished
+    /// when no update is running, or a later job is running. Ids wrap around
+    /// after 2<sup>15</sup>, which is taken into account.
+    pub fn is_done<S: Read + Write>(&self, client: &mut Client<S>) -> Result<bool> {
+        Ok(client.status()?.updating_db.is_none_or(|current| self.is_before(current)))
+    }
+
+    /// Check if the job was queued before the other one
+    ///
+    /// Only a few jobs can be queued at once, so a job with a much smaller id
+    /// was queued after the id wrapped around.
+    fn is_before(&self, other: u32) -> bool {
+        let distance = other.wrapping_sub(self.id) % MAX_JOB_ID;
+        distance != 0 && distance < MAX_JOB_ID / 2
+    }
+
+    /// Block until the job is finished, waiting for `update` and `database` idle events
+    pub fn wait<S: Read + Write>(&self, client: &mut Client<S>) -> Result<()> {
+        // Events emitted between the status check and entering idle mode are
+        // reported as soon as the idle command is sent, so nothing is missed
+        while !self.is_done(client)? {
+            client.wait(&[Subsystem::Update, Subsystem::Database])?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for UpdateJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.id.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UpdateJob;
+    use crate::testing::MockServer;
+
+    #[test]
+    fn order() {
+        let job = UpdateJob { id: 5 };
+        assert!(!job.is_before(4));
+        assert!(!job.is_before(5));
+        assert!(job.is_before(6));
+
+        let job = UpdateJob { id: 32767 };
+        assert!(job.is_before(32768));
+        assert!(job.is_before(1));
+        assert!(!job.is_before(32766));
+
+        let job = UpdateJob { id: 1 };
+        assert!(!job.is_before(32768));
+        assert!(job.is_before(2));
+    }
+
+    #[test]
+    fn is_done_wrapped() {
+        let server = MockServer::new();
+        server
+            .expect("status", "updating_db: 32768")
+            .expect("replay_gain_status", "replay_gain_mode: off")
+            .expect("status", "updating_db: 2")
+            .expect("replay_gain_status", "replay_gain_mode: off")
+            .expect("status", "")
+            .expect("replay_gain_status", "replay_gain_mode: off");
+        let mut conn = server.client();
+
+        let job = UpdateJob { id: 32768 };
+        assert!(!job.is_done(&mut conn).unwrap());
+        assert!(job.is_done(&mut conn).unwrap());
+        assert!(job.is_done(&mut conn).unwrap());
+        server.verify();
+    }
+}
@@ -1,6 +1,7 @@
-//! This module defines MPD version type and parsing code
+//! This module defines MPD version type, protocol features and parsing code
 
 use crate::error::ParseError;
+use std::fmt;
 use std::str::FromStr;
 
 // Version {{{
@@ -37,3 +38,100 @@ impl serde::Serialize for Version {
     }
 }
 // }}}
+
+// Feature {{{
+/// Version-dependent server behavior, checked with [`Client::supports()`](crate::Client::supports)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// filter expressions in `find`, `search` etc, and `albumart` command (MPD 0.21+)
+    FilterExpressions,
+    /// `readpicture` command (MPD 0.22+)
+    ReadPicture,
+    /// partition commands and `partition` status field (MPD 0.22+)
+    Partitions,
+    /// `binarylimit` command (MPD 0.22.4+)
+    BinaryLimit,
+    /// position argument for `add`, `findadd`, `searchadd`, including relative positions (MPD 0.23+)
+    PositionOnAdd,
+    /// deleting position ranges from stored playlists (MPD 0.23.3+)
+    PlaylistDeleteRange,
+    /// position argument for `searchaddpl` (MPD 0.23.4+)
+    PlaylistAddPosition,
+    /// save modes for `save` command (MPD 0.24+)
+    SaveMode,
+    /// range argument for `listplaylistinfo` (MPD 0.24+)
+    PlaylistRange,
+    /// oneshot consume mode (MPD 0.24+)
+    OneshotConsume,
+    /// `protocol` command family (MPD 0.24+)
+    ProtocolFeatures,
+}
+
+impl Feature {
+    /// Minimal protocol version supporting the feature
+    pub fn min_version(self) -> Version {
+        match self {
+            Feature::FilterExpressions => Version(0, 21, 0),
+            Feature::ReadPicture | Feature::Partitions => Version(0, 22, 0),
+            Feature::BinaryLimit => Version(0, 22, 4),
+            Feature::PositionOnAdd => Version(0, 23, 0),
+            Feature::PlaylistDeleteRange => Version(0, 23, 3),
+            Feature::PlaylistAddPosition => Version(0, 23, 4),
+            Feature::SaveMode | Feature::PlaylistRange | Feature::OneshotConsume | Feature::ProtocolFeatures => Version(0, 24, 0),
+        }
+    }
+}
+
+impl Version {
+    /// Check if the protocol version supports given feature
+    pub fn supports(&self, feature: Feature) -> bool {
+        *self >= feature.min_version()
+    }
+}
+// }}}
+
+// ProtocolFeature {{{
+/// Optional protocol feature, which can be enabled or disabled per connection (MPD 0.24+)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProtocolFeature {
+    /// hide_playlists_in_root: don't list stored playlists in `lsinfo` of the root directory
+    HidePlaylistsInRoot,
+    /// feature unknown to this library
+    Other(String),
+}
+
+impl FromStr for ProtocolFeature {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<ProtocolFeature, ParseError> {
+        Ok(match s {
+            "hide_playlists_in_root" => ProtocolFeature::HidePlaylistsInRoot,
+            _ => ProtocolFeature::Other(s.to_owned()),
+        })
+    }
+}
+
+impl fmt::Display for ProtocolFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolFeature::HidePlaylistsInRoot => f.write_str("hide_playlists_in_root"),
+            ProtocolFeature::Other(name) => f.write_str(name),
+        }
+    }
+}
+// }}}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn supports() {
+        let version: Version = "0.23.3".parse().unwrap();
+        assert!(version.supports(Feature::BinaryLimit));
+        assert!(version.supports(Feature::PlaylistDeleteRange));
+        assert!(!version.supports(Feature::PlaylistAddPosition));
+        assert!(!version.supports(Feature::ProtocolFeatures));
+        assert!(Version(1, 0, 0).supports(Feature::SaveMode));
+    }
+}
@@ -0,0 +1,547 @@
+//! The module defines helpers which keep track of MPD state changes
+//!
+//! [`StatusStream`] yields a fresh [`Status`] each time the player state changes.
+//! It relies on `idle` notifications, but some proxies and MPD-compatible servers
+//! don't implement "idle" mode properly, so it can also poll `status` command
+//! with a fixed interval, either on demand, or as an automatic fallback when
+//! `idle` command is rejected by the server.
+//!
+//! [`StatusWatcher`] is built on top of the stream. It keeps both the status and
+//! the current song cached, and reports what exactly has changed after each update:
+//!
+//! ```rust,no_run
+//! use mpd::watcher::{StatusChange, StatusStream, StatusWatcher};
+//! use mpd::Client;
+//! use std::time::Duration;
+//!
+//! let client = Client::connect("127.0.0.1:6600").unwrap();
+//! let mut watcher = StatusWatcher::new(StatusStream::new(client, Duration::from_secs(1))).unwrap();
+//! watcher.on_change(|change| {
+//!     if let StatusChange::Song(Some(song)) = change {
+//!         println!("Now playing: {:?}", song.title);
+//!     }
+//! });
+//! loop {
+//!     watcher.wait().unwrap();
+//! }
+//! ```
+//!
+//! [`QueueWatcher`] does the same for the queue, reporting changes as a sequence
+//! of insertions, removals and moves, which can be applied to a list model one by one.
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::idle::{Idle, Subsystem};
+use crate::queue::QueueSync;
+use crate::song::{Id, PosIdChange, Song};
+use crate::status::{State, Status};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Subsystems which can affect [`Status`] fields
+const STATUS_SUBSYSTEMS: &[Subsystem] = &[Subsystem::Player, Subsystem::Mixer, Subsystem::Options, Subsystem::Queue, Subsystem::Update];
+
+/// The way [`StatusStream`] learns about changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpdateMode {
+    /// wait for `idle` events
+    Idle,
+    /// poll `status` command with the given interval
+    Poll(Duration),
+}
+
+/// Iterator over player status changes
+///
+/// The first item is always the current status, and every next one is
+/// produced when the status changes. The iterator never ends by itself,
+/// but it yields an error if the connection fails.
+///
+/// When polling, changes to the playing progress (elapsed time and bitrate)
+/// are not reported, as they change all the time while playing.
+#[derive(Debug)]
+pub struct StatusStream<S: Read + Write> {
+    client: Client<S>,
+    mode: UpdateMode,
+    interval: Duration,
+    last: Option<Status>,
+    events: Vec<Subsystem>,
+}
+
+impl<S: Read + Write> StatusStream<S> {
+    /// Create a stream using "idle" mode, which falls back to polling with given interval
+    /// if the server doesn't support it
+    pub fn new(client: Client<S>, interval: Duration) -> StatusStream<S> {
+        StatusStream { client, mode: UpdateMode::Idle, interval, last: None, events: Vec::new() }
+    }
+
+    /// Create a stream which always polls status with given interval
+    pub fn polling(client: Client<S>, interval: Duration) -> StatusStream<S> {
+        StatusStream { client, mode: UpdateMode::Poll(interval), interval, last: None, events: Vec::new() }
+    }
+
+    /// Current update mode, changes to [`UpdateMode::Poll`] if "idle" mode is not available
+    pub fn mode(&self) -> UpdateMode {
+        self.mode
+    }
+
+    /// Last reported status
+    pub fn last(&self) -> Option<&Status> {
+        self.last.as_ref()
+    }
+
+    /// Subsystems which have changed before the last reported status, always empty when polling
+    pub fn events(&self) -> &[Subsystem] {
+        &self.events
+    }
+
+    /// Release underlying client
+    pub fn into_client(self) -> Client<S> {
+        self.client
+    }
+
+    fn next_status(&mut self) -> Result<Status> {
+        self.events.clear();
+        let last = match self.last {
+            Some(ref last) => last,
+            None => return self.client.status(),
+        };
+
+        loop {
+            match self.mode {
+                UpdateMode::Idle => match self.client.wait(STATUS_SUBSYSTEMS) {
+                    Ok(events) => {
+                        self.events = events;
+                        return self.client.status();
+                    }
+                    // The server refused to go idle, so poll instead
+                    Err(Error::Server(_)) => self.mode = UpdateMode::Poll(self.interval),
+                    Err(e) => return Err(e),
+                },
+                UpdateMode::Poll(interval) => {
+                    thread::sleep(interval);
+                    let status = self.client.status()?;
+                    if !same_state(last, &status) {
+                        return Ok(status);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S: Read + Write> Iterator for StatusStream<S> {
+    type Item = Result<Status>;
+
+    fn next(&mut self) -> Option<Result<Status>> {
+        let status = self.next_status();
+        if let Ok(ref status) = status {
+            self.last = Some(status.clone());
+        }
+        Some(status)
+    }
+}
+
+/// Playing position may drift from the wall clock this much without being reported as seeking
+const SEEK_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// Compare statuses ignoring playing progress
+fn same_state(a: &Status, b: &Status) -> bool {
+    let strip = |status: &Status| Status { elapsed: None, time: None, bitrate: None, ..status.clone() };
+    strip(a) == strip(b)
+}
+
+/// Player state change reported by [`StatusWatcher`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusChange {
+    /// playback was started, paused or stopped
+    State(State),
+    /// volume was changed
+    Volume(i8),
+    /// other song is playing now, or the current song metadata has changed
+    Song(Option<Box<Song>>),
+    /// playback position was changed by seeking
+    Seek,
+    /// playback options (like repeat, random or crossfade) were changed
+    Options,
+    /// the queue was modified, new queue version is reported
+    Queue(u32),
+    /// database update was started or finished
+    Updating(Option<u32>),
+    /// player error was set or cleared
+    Error(Option<String>),
+}
+
+type Listener = Box<dyn FnMut(&StatusChange)>;
+
+/// Cached player status and current song, kept up to date by a [`StatusStream`]
+///
+/// The watcher uses its own connection, which is waiting for events most of the time.
+pub struct StatusWatcher<S: Read + Write> {
+    stream: StatusStream<S>,
+    status: Status,
+    /// when the status was fetched, to tell seeking from playing on
+    fetched: Instant,
+    song: Option<Song>,
+    listeners: Vec<Listener>,
+}
+
+impl<S: Read + Write> StatusWatcher<S> {
+    /// Fetch current status and song using the stream's connection
+    ///
+    /// Current song metadata changes (like stream titles) are only noticed in "idle" mode.
+    pub fn new(mut stream: StatusStream<S>) -> Result<StatusWatcher<S>> {
+        let status = match stream.next() {
+            Some(status) => status?,
+            None => unreachable!("status stream never ends"),
+        };
+        let song = stream.client.currentsong()?;
+        Ok(StatusWatcher { stream, status, fetched: Instant::now(), song, listeners: Vec::new() })
+    }
+
+    /// Last known status
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Last known current song
+    pub fn song(&self) -> Option<&Song> {
+        self.song.as_ref()
+    }
+
+    /// Call the listener for each change, in the order they are reported
+    pub fn on_change<F: FnMut(&StatusChange) + 'static>(&mut self, listener: F) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Wait for the next status, update cached state and notify listeners about changes
+    ///
+    /// Returns the list of changes, which may be empty if events didn't affect cached state.
+    pub fn wait(&mut self) -> Result<Vec<StatusChange>> {
+        let status = match self.stream.next() {
+            Some(status) => status?,
+            None => unreachable!("status stream never ends"),
+        };
+        // Stream titles change without switching songs, MPD reports it as a player event
+        let player_changed = self.stream.events().contains(&Subsystem::Player);
+        self.update(status, player_changed)
+    }
+
+    /// Fetch status and song anew, notifying listeners about changes
+    pub fn refresh(&mut self) -> Result<Vec<StatusChange>> {
+        let status = self.stream.client.status()?;
+        self.stream.last = Some(status.clone());
+        self.update(status, true)
+    }
+
+    fn update(&mut self, status: Status, player_changed: bool) -> Result<Vec<StatusChange>> {
+        let song_id = |status: &Status| status.song.map(|place| place.id);
+        let song = if player_changed || song_id(&status) != song_id(&self.status) {
+            self.stream.client.currentsong()?
+        } else {
+            self.song.clone()
+        };
+
+        let now = Instant::now();
+        let changes = diff(&self.status, &status, &self.song, &song, now - self.fetched);
+        self.status = status;
+        self.fetched = now;
+        self.song = song;
+        for change in &changes {
+            for listener in &mut self.listeners {
+                listener(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Release underlying client
+    pub fn into_client(self) -> Client<S> {
+        self.stream.into_client()
+    }
+}
+
+impl<S: Read + Write> Iterator for StatusWatcher<S> {
+    type Item = Result<Vec<StatusChange>>;
+
+    fn next(&mut self) -> Option<Result<Vec<StatusChange>>> {
+        Some(self.wait())
+    }
+}
+
+/// List changes between two states, fetched `passed` time apart
+fn diff(old: &Status, new: &Status, old_song: &Option<Song>, new_song: &Option<Song>, passed: Duration) -> Vec<StatusChange> {
+    let mut changes = Vec::new();
+    if old.state != new.state {
+        changes.push(StatusChange::State(new.state));
+    }
+    if old.volume != new.volume {
+        changes.push(StatusChange::Volume(new.volume));
+    }
+    if old_song != new_song {
+        changes.push(StatusChange::Song(new_song.clone().map(Box::new)));
+    } else if new.state != State::Stop && seeked(old, new, passed) {
+        changes.push(StatusChange::Seek);
+    }
+    let options = |s: &Status| (s.repeat, s.random, s.single, s.consume, s.crossfade, s.mixrampdb, s.mixrampdelay, s.replaygain);
+    if options(old) != options(new) {
+        changes.push(StatusChange::Options);
+    }
+    if old.queue_version != new.queue_version {
+        changes.push(StatusChange::Queue(new.queue_version));
+    }
+    if old.updating_db != new.updating_db {
+        changes.push(StatusChange::Updating(new.updating_db));
+    }
+    if old.error != new.error {
+        changes.push(StatusChange::Error(new.error.clone()));
+    }
+    changes
+}
+
+/// Check if playing position has jumped, instead of moving on with the wall clock
+fn seeked(old: &Status, new: &Status, passed: Duration) -> bool {
+    let (old_elapsed, new_elapsed) = match (old.elapsed, new.elapsed) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return false,
+    };
+    let expected = if old.state == State::Play { old_elapsed + passed } else { old_elapsed };
+    new_elapsed.abs_diff(expected) > SEEK_TOLERANCE
+}
+
+/// Queue change reported by [`QueueWatcher`]
+///
+/// Positions refer to the queue with all preceding changes already applied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueChange {
+    /// a song was inserted at the position
+    Inserted(u32, Box<Song>),
+    /// a song was removed from the position
+    Removed(u32, Id),
+    /// a song was moved from the first position to the second one
+    Moved(u32, u32),
+    /// song metadata (like priority or range) was changed at the position
+    Updated(u32, Box<Song>),
+}
+
+type QueueListener = Box<dyn FnMut(&QueueChange)>;
+
+/// Cached queue, kept up to date with idle events
+///
+/// Only changed songs are fetched from the server (see [`QueueSync`]).
+/// The watcher uses its own connection, which is waiting for events most of the time.
+pub struct QueueWatcher<S: Read + Write> {
+    client: Client<S>,
+    sync: QueueSync,
+    listeners: Vec<QueueListener>,
+}
+
+impl<S: Read + Write> QueueWatcher<S> {
+    /// Fetch the whole queue using dedicated connection
+    pub fn new(mut client: Client<S>) -> Result<QueueWatcher<S>> {
+        let mut sync = QueueSync::new();
+        sync.update(&mut client)?;
+        Ok(QueueWatcher { client, sync, listeners: Vec::new() })
+    }
+
+    /// Cached queue songs, in queue order
+    pub fn songs(&self) -> &[Song] {
+        self.sync.songs()
+    }
+
+    /// Queue version the cache corresponds to
+    pub fn version(&self) -> Option<u32> {
+        self.sync.version()
+    }
+
+    /// Call the listener for each change, in the order they are reported
+    pub fn on_change<F: FnMut(&QueueChange) + 'static>(&mut self, listener: F) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Wait for queue events, update the cache and notify listeners about changes
+    pub fn wait(&mut self) -> Result<Vec<QueueChange>> {
+        self.client.wait(&[Subsystem::Queue])?;
+        self.refresh()
+    }
+
+    /// Bring the cache up to date, notifying listeners about changes
+    pub fn refresh(&mut self) -> Result<Vec<QueueChange>> {
+        let old: Vec<Id> = self.sync.songs().iter().map(|song| song.place.map(|place| place.id).unwrap_or_default()).collect();
+        let delta = match self.sync.update_changes(&mut self.client)? {
+            Some(delta) => delta,
+            None => return Ok(Vec::new()),
+        };
+
+        let changes = queue_changes(&old, &delta, self.sync.songs());
+        for change in &changes {
+            for listener in &mut self.listeners {
+                listener(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Release underlying client
+    pub fn into_client(self) -> Client<S> {
+        self.client
+    }
+}
+
+impl<S: Read + Write> Iterator for QueueWatcher<S> {
+    type Item = Result<Vec<QueueChange>>;
+
+    fn next(&mut self) -> Option<Result<Vec<QueueChange>>> {
+        Some(self.wait())
+    }
+}
+
+/// Turn `plchangesposid` delta into a sequence of single song operations
+///
+/// Songs which are gone are removed first, from the end of the queue, so their
+/// positions stay valid. Then the new queue is built from the start: each position
+/// is either already in place, or gets a song moved from further on, or a new one.
+fn queue_changes(old: &[Id], delta: &[PosIdChange], new: &[Song]) -> Vec<QueueChange> {
+    let changed: BTreeMap<u32, Id> = delta.iter().map(|change| (change.pos, change.id)).collect();
+    let changed_ids: HashSet<Id> = changed.values().copied().collect();
+    let len = new.len() as u32;
+    let mut changes = Vec::new();
+
+    // Only songs at changed positions and beyond the new end may be gone
+    let mut pending = PendingSongs::new(old.len());
+    let vacated = (len..old.len() as u32).rev().chain(changed.keys().rev().copied().filter(|&pos| pos < len.min(old.len() as u32)));
+    for pos in vacated {
+        let id = old[pos as usize];
+        if !changed_ids.contains(&id) {
+            changes.push(QueueChange::Removed(pos, id));
+            pending.take(pos);
+        }
+    }
+
+    let old_pos: HashMap<Id, u32> = old.iter().enumerate().map(|(pos, &id)| (id, pos as u32)).collect();
+    for (pos, song) in (0..len).zip(new) {
+        let id = changed.get(&pos).copied().unwrap_or_else(|| old[pos as usize]);
+        match old_pos.get(&id) {
+            Some(&from) => {
+                // Songs placed so far come first, then the pending ones in their old order
+                let current = pos + pending.before(from);
+                if current != pos {
+                    changes.push(QueueChange::Moved(current, pos));
+                }
+                pending.take(from);
+                if from == pos && changed.contains_key(&pos) {
+                    changes.push(QueueChange::Updated(pos, Box::new(song.clone())));
+                }
+            }
+            None => changes.push(QueueChange::Inserted(pos, Box::new(song.clone()))),
+        }
+    }
+    changes
+}
+
+/// Old queue positions of songs which are not placed into the new queue yet
+///
+/// This is a Fenwick tree, so both operations take logarithmic time.
+struct PendingSongs(Vec<u32>);
+
+impl PendingSongs {
+    fn new(len: usize) -> PendingSongs {
+        let mut tree = vec![1; len + 1];
+        tree[0] = 0;
+        for i in 1..=len {
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= len {
+                tree[parent] += tree[i];
+            }
+        }
+        PendingSongs(tree)
+    }
+
+    /// Number of pending songs before the position
+    fn before(&self, pos: u32) -> u32 {
+        let mut i = pos as usize;
+        let mut count = 0;
+        while i > 0 {
+            count += self.0[i];
+            i &= i - 1;
+        }
+        count
+    }
+
+    fn take(&mut self, pos: u32) {
+        let mut i = pos as usize + 1;
+        while i < self.0.len() {
+            self.0[i] -= 1;
+            i += i & i.wrapping_neg();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, queue_changes, QueueChange, StatusChange};
+    use crate::song::{Id, PosIdChange, QueuePlace, Song};
+    use crate::status::{State, Status};
+    use std::time::Duration;
+
+    #[test]
+    fn changes() {
+        let old = Status { state: State::Play, volume: 50, ..Status::default() };
+        let song = Song { file: "a.flac".into(), place: Some(QueuePlace { id: Id(1), pos: 0, prio: 0 }), ..Song::default() };
+
+        let new = Status { state: State::Pause, volume: 60, random: true, ..old.clone() };
+        assert_eq!(
+            diff(&old, &new, &None, &Some(song.clone()), Duration::ZERO),
+            [StatusChange::State(State::Pause), StatusChange::Volume(60), StatusChange::Song(Some(Box::new(song.clone()))), StatusChange::Options]
+        );
+        assert!(diff(&old, &new, &None, &None, Duration::ZERO).contains(&StatusChange::State(State::Pause)));
+
+        // Playing on is not seeking, jumping either way is
+        let old = Status { elapsed: Some(Duration::from_secs(10)), ..old };
+        let played = Status { elapsed: Some(Duration::from_secs(70)), ..old.clone() };
+        assert!(diff(&old, &played, &Some(song.clone()), &Some(song.clone()), Duration::from_secs(60)).is_empty());
+        assert_eq!(diff(&old, &played, &Some(song.clone()), &Some(song.clone()), Duration::from_secs(1)), [StatusChange::Seek]);
+        let rewound = Status { elapsed: Some(Duration::from_secs(2)), ..old.clone() };
+        assert_eq!(diff(&old, &rewound, &Some(song.clone()), &Some(song.clone()), Duration::from_secs(1)), [StatusChange::Seek]);
+
+        // Paused position doesn't move on
+        let paused = Status { state: State::Pause, ..old.clone() };
+        assert!(diff(&paused, &paused, &Some(song.clone()), &Some(song.clone()), Duration::from_secs(60)).is_empty());
+        let stopped = Status { state: State::Stop, elapsed: None, ..old.clone() };
+        assert_eq!(diff(&old, &stopped, &Some(song.clone()), &Some(song), Duration::from_secs(60)), [StatusChange::State(State::Stop)]);
+    }
+
+    fn queued(file: &str, id: u32, pos: u32) -> Song {
+        Song { file: file.into(), place: Some(QueuePlace { id: Id(id), pos, prio: 0 }), ..Song::default() }
+    }
+
+    fn delta(changes: &[(u32, u32)]) -> Vec<PosIdChange> {
+        changes.iter().map(|&(pos, id)| PosIdChange { pos, id: Id(id) }).collect()
+    }
+
+    #[test]
+    fn queue_delta() {
+        let old = [Id(1), Id(2), Id(3)];
+        let new = [queued("c", 3, 0), queued("d", 4, 1), queued("a", 1, 2)];
+        assert_eq!(
+            queue_changes(&old, &delta(&[(0, 3), (1, 4), (2, 1)]), &new),
+            [QueueChange::Removed(1, Id(2)), QueueChange::Moved(1, 0), QueueChange::Inserted(1, Box::new(new[1].clone()))]
+        );
+
+        // Unchanged position may still need a move while earlier songs are placed
+        let new = [queued("x", 3, 0), queued("c", 2, 1), queued("a", 1, 2)];
+        assert_eq!(
+            queue_changes(&old[..2], &delta(&[(0, 3), (2, 1)]), &new),
+            [QueueChange::Inserted(0, Box::new(new[0].clone())), QueueChange::Moved(2, 1)]
+        );
+
+        let new = [queued("a", 1, 0)];
+        assert_eq!(queue_changes(&old, &[], &new), [QueueChange::Removed(2, Id(3)), QueueChange::Removed(1, Id(2))]);
+
+        let mut prioritized = queued("a", 1, 0);
+        prioritized.place.as_mut().unwrap().prio = 10;
+        assert_eq!(queue_changes(&old[..1], &delta(&[(0, 1)]), &[prioritized.clone()]), [QueueChange::Updated(0, Box::new(prioritized))]);
+    }
+}
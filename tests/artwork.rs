@@ -0,0 +1,57 @@
+extern crate mpd;
+
+mod helpers;
+use helpers::Daemon;
+
+use mpd::error::{Error, ErrorCode};
+use mpd::Song;
+use std::fs;
+
+fn silence() -> Song {
+    Song { file: "silence.flac".to_owned(), ..Song::default() }
+}
+
+#[test]
+fn albumart() {
+    let daemon = Daemon::start();
+    // Bigger than default binary limit, so it has to be fetched in several chunks
+    let cover: Vec<u8> = (0..20000u32).map(|i| i as u8).collect();
+    fs::write(daemon.music_directory().join("cover.jpg"), &cover).unwrap();
+
+    let mut mpd = daemon.connect();
+    assert_eq!(mpd.albumart(&silence()).unwrap(), cover);
+}
+
+#[test]
+fn albumart_missing() {
+    let daemon = Daemon::start();
+    let mut mpd = daemon.connect();
+    match mpd.albumart(&silence()) {
+        Err(Error::Server(e)) => assert_eq!(e.code, ErrorCode::NoExist),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn readpicture_missing() {
+    let mut mpd = helpers::connect();
+    assert_eq!(mpd.readpicture(&silence()).unwrap(), None);
+}
+
+#[test]
+fn albumart_chunks() {
+    let daemon = Daemon::start();
+    let cover: Vec<u8> = (0..20000u32).map(|i| i as u8).collect();
+    fs::write(daemon.music_directory().join("cover.jpg"), &cover).unwrap();
+
+    let mut mpd = daemon.connect();
+    let song = silence();
+    let mut chunks = mpd.albumart_chunks(&song);
+    let first = chunks.next().unwrap().unwrap();
+    assert!(first.len() < cover.len());
+    assert_eq!(chunks.size(), Some(cover.len()));
+
+    let mut rest = Vec::new();
+    assert_eq!(chunks.write_to(&mut rest).unwrap(), cover.len() - first.len());
+    assert_eq!([first, rest].concat(), cover);
+}
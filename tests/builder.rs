@@ -0,0 +1,31 @@
+#![cfg(feature = "net")]
+
+extern crate mpd;
+
+mod helpers;
+use helpers::Daemon;
+
+use mpd::socket::{Address, Endpoint};
+use mpd::ClientBuilder;
+use std::time::Duration;
+
+#[test]
+fn setup() {
+    let daemon = Daemon::start();
+    let endpoint = Endpoint { address: Address::Unix(daemon.sock_path().to_owned()), password: None };
+    let mut mpd = ClientBuilder::new(endpoint)
+        .connect_timeout(Duration::from_secs(1))
+        .read_timeout(Duration::from_secs(5))
+        .binary_limit(8192)
+        .tagtypes(&["artist", "title"])
+        .connect()
+        .unwrap();
+    assert_eq!(mpd.tagtypes().unwrap(), vec!["Artist", "Title"]);
+}
+
+#[test]
+fn bad_partition() {
+    let daemon = Daemon::start();
+    let endpoint = Endpoint { address: Address::Unix(daemon.sock_path().to_owned()), password: None };
+    assert!(ClientBuilder::new(endpoint).partition("nonexistent").connect().is_err());
+}
@@ -0,0 +1,38 @@
+extern crate mpd;
+
+mod helpers;
+use helpers::connect;
+use mpd::command_list::CommandList;
+use mpd::error::{Error, ErrorCode};
+use mpd::{Song, Status};
+
+#[test]
+fn command_list() {
+    let mut mpd = connect();
+    let mut list = CommandList::new();
+    list.push("clear", ()).push("addid", "silence.flac").push("status", ()).push("playlistinfo", ());
+
+    let replies = mpd.command_list(&list).unwrap();
+    assert_eq!(replies.len(), 4);
+    let id: u32 = replies[1].field("Id").unwrap();
+    let status: Status = replies[2].clone().parse().unwrap();
+    assert_eq!(status.queue_len, 1);
+    let songs: Vec<Song> = replies[3].clone().parse_structs("file").unwrap();
+    assert_eq!(songs[0].place.unwrap().id.0, id);
+}
+
+#[test]
+fn command_list_error() {
+    let mut mpd = connect();
+    let mut list = CommandList::new();
+    list.push("clear", ()).push("playid", 100500u32).push("status", ());
+
+    match mpd.command_list(&list) {
+        Err(Error::Server(e)) => {
+            assert_eq!(e.code, ErrorCode::NoExist);
+            assert_eq!(e.pos, 1);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    mpd.ping().unwrap();
+}
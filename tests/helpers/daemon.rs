@@ -131,6 +131,16 @@ impl Daemon {
         mpd::Client::new(stream)
     }
 
+    #[allow(dead_code)]
+    pub fn music_directory(&self) -> &Path {
+        &self.config.music_directory
+    }
+
+    #[allow(dead_code)]
+    pub fn sock_path(&self) -> &Path {
+        &self.config.sock_path
+    }
+
     pub fn connect(&self) -> mpd::Client<UnixStream> {
         self.maybe_connect().expect("Could not connect to daemon.")
     }
@@ -4,6 +4,7 @@ mod helpers;
 use helpers::Daemon;
 
 use mpd::Idle;
+use std::ops::ControlFlow;
 
 #[test]
 fn idle() {
@@ -18,3 +19,76 @@ fn idle() {
     let sys = idle.get().unwrap();
     assert_eq!(&*sys, &[mpd::Subsystem::Options]);
 }
+
+#[test]
+fn try_get() {
+    let daemon = Daemon::start();
+    let mut mpd = daemon.connect();
+    let mut idle = mpd.idle(&[mpd::Subsystem::Options]).unwrap();
+    assert_eq!(idle.try_get().unwrap(), None);
+
+    let mut mpd1 = daemon.connect();
+    mpd1.random(true).unwrap();
+
+    let events = loop {
+        if let Some(events) = idle.try_get().unwrap() {
+            break events;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert_eq!(events, [mpd::Subsystem::Options]);
+    drop(idle);
+    assert!(mpd.status().unwrap().random);
+}
+
+#[test]
+fn idle_loop() {
+    let daemon = Daemon::start();
+    let mut mpd = daemon.connect();
+    mpd.ping().unwrap();
+
+    // Events which happen between idles are reported by the next one
+    let mut mpd1 = daemon.connect();
+    mpd1.random(true).unwrap();
+
+    let random = mpd
+        .idle_loop(&[mpd::Subsystem::Options], |mpd, events| {
+            assert_eq!(events, [mpd::Subsystem::Options]);
+            Ok(ControlFlow::Break(mpd.status()?.random))
+        })
+        .unwrap();
+    assert!(random);
+}
+
+#[test]
+fn status_stream() {
+    let daemon = Daemon::start();
+    let mut stream = mpd::watcher::StatusStream::new(daemon.connect(), std::time::Duration::from_millis(100));
+    assert!(!stream.next().unwrap().unwrap().random);
+
+    let mut mpd = daemon.connect();
+    mpd.random(true).unwrap();
+
+    assert!(stream.next().unwrap().unwrap().random);
+}
+
+#[test]
+fn status_watcher() {
+    use mpd::watcher::{StatusChange, StatusStream, StatusWatcher};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let daemon = Daemon::start();
+    let mut watcher = StatusWatcher::new(StatusStream::new(daemon.connect(), std::time::Duration::from_millis(100))).unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let listener = seen.clone();
+    watcher.on_change(move |change| listener.borrow_mut().push(change.clone()));
+
+    let mut mpd = daemon.connect();
+    mpd.random(true).unwrap();
+
+    let changes = watcher.wait().unwrap();
+    assert_eq!(changes, [StatusChange::Options]);
+    assert_eq!(*seen.borrow(), changes);
+    assert!(watcher.status().random);
+}
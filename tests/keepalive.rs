@@ -0,0 +1,18 @@
+extern crate mpd;
+
+mod helpers;
+use helpers::Daemon;
+
+use mpd::keepalive::Keepalive;
+use std::thread::sleep;
+use std::time::Duration;
+
+#[test]
+fn keepalive() {
+    let daemon = Daemon::start();
+    let mpd = Keepalive::new(daemon.connect(), Duration::from_millis(50));
+    sleep(Duration::from_millis(200));
+    mpd.lock().volume(50).unwrap();
+    sleep(Duration::from_millis(200));
+    assert!(mpd.into_client().status().is_ok());
+}
@@ -0,0 +1,19 @@
+extern crate mpd;
+
+mod helpers;
+use helpers::connect;
+use mpd::library::LibraryCache;
+use mpd::Subsystem;
+
+#[test]
+fn library_cache() {
+    let mut mpd = connect();
+    let mut library = LibraryCache::new().artist_tag("Artist");
+    assert!(library.update(&mut mpd).unwrap());
+    assert!(library.is_valid());
+    assert_eq!(library.len() as u32, mpd.stats().unwrap().songs);
+
+    assert!(!library.update(&mut mpd).unwrap());
+    assert!(!library.handle(&mut mpd, &[Subsystem::Player]).unwrap());
+    assert!(library.handle(&mut mpd, &[Subsystem::Database]).unwrap());
+}
@@ -0,0 +1,75 @@
+extern crate mpd;
+
+mod helpers;
+use helpers::{connect, Daemon};
+
+use mpd::Channel;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn subscription() {
+    let mut mpd = connect();
+    let channel = Channel::new("test_subscription").unwrap();
+    {
+        let mut sub = mpd.subscription(&channel).unwrap();
+        assert!(sub.channels().unwrap().contains(&channel));
+    }
+    assert!(!mpd.channels().unwrap().contains(&channel));
+}
+
+#[test]
+fn readmessages_by_channel() {
+    let mut mpd = connect();
+    let one = Channel::new("test_one").unwrap();
+    let two = Channel::new("test_two").unwrap();
+    mpd.subscribe(&one).unwrap();
+    mpd.subscribe(&two).unwrap();
+
+    mpd.sendmessage(&one, "a").unwrap();
+    mpd.sendmessage(&two, "b").unwrap();
+    mpd.sendmessage(&one, "c").unwrap();
+
+    let grouped = mpd.readmessages_by_channel().unwrap();
+    let payloads: Vec<(Channel, Vec<String>)> =
+        grouped.into_iter().map(|(channel, messages)| (channel, messages.into_iter().map(|m| m.message).collect())).collect();
+    assert_eq!(payloads, vec![(one, vec!["a".to_owned(), "c".to_owned()]), (two, vec!["b".to_owned()])]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json() {
+    let mut mpd = connect();
+    let channel = Channel::new("test_json").unwrap();
+    mpd.subscribe(&channel).unwrap();
+
+    mpd.send_json(&channel, &vec![1, 2, 3]).unwrap();
+    mpd.sendmessage(&channel, "not json").unwrap();
+
+    let mut messages = mpd.read_json::<Vec<u32>>().unwrap().into_iter();
+    assert_eq!(messages.next().map(|(_, v)| v.unwrap()), Some(vec![1, 2, 3]));
+    assert!(messages.next().unwrap().1.is_err());
+}
+
+#[test]
+fn wait_for_message() {
+    let daemon = Daemon::start();
+    let mut mpd = daemon.connect();
+    let channel = Channel::new("test_wait").unwrap();
+
+    let mut sender = daemon.connect();
+    let sent = channel.clone();
+    let thread = thread::spawn(move || {
+        // Retry until the waiting client subscribes
+        while sender.sendmessage(&sent, "ping").is_err() {
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+
+    let messages = mpd.wait_for_message(&channel, Duration::from_secs(5)).unwrap();
+    thread.join().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].message, "ping");
+
+    assert!(mpd.wait_for_message(&channel, Duration::from_millis(50)).unwrap().is_empty());
+}
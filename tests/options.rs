@@ -2,7 +2,7 @@ extern crate mpd;
 
 mod helpers;
 use helpers::connect;
-use mpd::{Idle, Song, State, Subsystem};
+use mpd::{FadeSettings, Idle, ReplayGain, ReplayGainConfig, Song, State, Subsystem};
 use std::time::Duration;
 
 #[test]
@@ -83,3 +83,25 @@ fn crossfade() {
     mpd.crossfade(0).unwrap();
     assert_eq!(mpd.status().unwrap().crossfade, if mpd.version >= mpd::Version(0, 19, 0) { None } else { Some(Duration::from_secs(0)) });
 }
+
+#[test]
+fn replaygain_config() {
+    let mut mpd = connect();
+    let config = ReplayGainConfig { mode: ReplayGain::Album };
+    config.apply(&mut mpd).unwrap();
+    assert_eq!(ReplayGainConfig::get(&mut mpd).unwrap(), config);
+    assert_eq!(mpd.status().unwrap().replaygain, Some(ReplayGain::Album));
+}
+
+#[test]
+fn fade_settings() {
+    let mut mpd = connect();
+    let fade = FadeSettings { crossfade: Duration::from_secs(3), mixrampdb: -10.0, mixrampdelay: Some(Duration::from_secs(2)) };
+    fade.apply(&mut mpd).unwrap();
+    assert_eq!(FadeSettings::get(&mut mpd).unwrap(), fade);
+
+    let disabled = FadeSettings { mixrampdelay: None, ..fade };
+    disabled.apply(&mut mpd).unwrap();
+    assert_eq!(FadeSettings::get(&mut mpd).unwrap(), disabled);
+    FadeSettings::default().apply(&mut mpd).unwrap();
+}
@@ -2,6 +2,7 @@ extern crate mpd;
 
 mod helpers;
 use helpers::connect;
+use mpd::{OutputRef, Subsystem};
 
 #[test]
 fn outputs() {
@@ -30,3 +31,37 @@ fn out_toggle() {
 
     mpd.output(0, true).unwrap();
 }
+
+#[test]
+fn output_set() {
+    let mut mpd = connect();
+    // The null output doesn't have any runtime attributes
+    assert!(mpd.output_set(0, "dop", "1").is_err());
+    mpd.ping().unwrap();
+}
+
+#[test]
+fn out_toggle_listed() {
+    let mut mpd = connect();
+    let output = mpd.outputs().unwrap().remove(0);
+
+    mpd.out_toggle(&output).unwrap();
+    assert_eq!(mpd.outputs().unwrap()[0].enabled, !output.enabled);
+    mpd.out_toggle(&output).unwrap();
+    assert_eq!(mpd.outputs().unwrap()[0].enabled, output.enabled);
+}
+
+#[test]
+fn output_ref() {
+    let mut mpd = connect();
+    let mut null = OutputRef::new("null");
+
+    assert_eq!(null.id(&mut mpd).unwrap(), 0);
+    null.disable(&mut mpd).unwrap();
+    assert!(!mpd.outputs().unwrap()[0].enabled);
+    null.handle(&[Subsystem::Output]);
+    null.toggle(&mut mpd).unwrap();
+    assert!(mpd.outputs().unwrap()[0].enabled);
+
+    assert!(OutputRef::new("missing").enable(&mut mpd).is_err());
+}
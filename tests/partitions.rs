@@ -0,0 +1,52 @@
+extern crate mpd;
+
+mod helpers;
+use helpers::connect;
+
+#[test]
+fn partitions() {
+    let mut mpd = connect();
+    assert_eq!(mpd.partitions().unwrap(), vec!["default"]);
+
+    mpd.new_partition("zone").unwrap();
+    assert_eq!(mpd.partitions().unwrap(), vec!["default", "zone"]);
+
+    mpd.switch_partition("zone").unwrap();
+    mpd.switch_partition("default").unwrap();
+
+    mpd.delete_partition("zone").unwrap();
+    assert_eq!(mpd.partitions().unwrap(), vec!["default"]);
+}
+
+#[test]
+fn switch_missing_partition() {
+    let mut mpd = connect();
+    assert!(mpd.switch_partition("missing").is_err());
+    mpd.ping().unwrap();
+}
+
+#[test]
+fn status_partition() {
+    let mut mpd = connect();
+    mpd.new_partition("status").unwrap();
+    mpd.switch_partition("status").unwrap();
+    assert_eq!(mpd.status().unwrap().partition.as_deref(), Some("status"));
+
+    mpd.switch_partition("default").unwrap();
+    assert_eq!(mpd.status().unwrap().partition.as_deref(), Some("default"));
+    mpd.delete_partition("status").unwrap();
+}
+
+#[test]
+fn with_partition() {
+    let mut mpd = connect();
+    mpd.new_partition("guard").unwrap();
+
+    let name = mpd.with_partition("guard", |mpd| mpd.status().map(|s| s.partition)).unwrap();
+    assert_eq!(name.as_deref(), Some("guard"));
+    assert_eq!(mpd.status().unwrap().partition.as_deref(), Some("default"));
+
+    assert!(mpd.with_partition("guard", |mpd| mpd.switch(100)).is_err());
+    assert_eq!(mpd.status().unwrap().partition.as_deref(), Some("default"));
+    mpd.delete_partition("guard").unwrap();
+}
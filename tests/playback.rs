@@ -1,9 +1,26 @@
 extern crate mpd;
 
 mod helpers;
+use mpd::SeekMode;
+use std::time::Duration;
 
 #[test]
 fn playback() {
     let mut mpd = helpers::connect();
     mpd.play().unwrap();
 }
+
+#[test]
+fn seek_current() {
+    let mut mpd = helpers::connect();
+    mpd.clear().unwrap();
+    mpd.push(mpd::Song { file: "silence.flac".into(), ..Default::default() }).unwrap();
+    mpd.play().unwrap();
+    mpd.pause(true).unwrap();
+
+    mpd.seek_current(SeekMode::Absolute, 0.1).unwrap();
+    mpd.seek_current(SeekMode::Forward, 0.2).unwrap();
+    mpd.seek_current(SeekMode::Backward, 0.1).unwrap();
+    let elapsed = mpd.status().unwrap().elapsed.unwrap();
+    assert!(elapsed.abs_diff(Duration::from_millis(200)) < Duration::from_millis(50));
+}
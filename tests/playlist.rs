@@ -2,6 +2,7 @@ extern crate mpd;
 
 mod helpers;
 use helpers::connect;
+use mpd::EditAction;
 
 #[test]
 fn playlists() {
@@ -13,3 +14,25 @@ fn playlists() {
         println!("{}: {:?}", pl.name, mpd.playlist(&pl.name, Some(..)).unwrap());
     }
 }
+
+#[test]
+fn search_add_to_playlist() {
+    let mut mpd = connect();
+    let query = mpd::Query::from(mpd::search::Expression::new(mpd::Term::File, "silence.flac"));
+    mpd.search_add_to_playlist("searchaddpl", &query, None).unwrap();
+    mpd.search_add_to_playlist("searchaddpl", &query, Some(0)).unwrap();
+    assert_eq!(mpd.playlist("searchaddpl", None::<std::ops::RangeFull>).unwrap().len(), 2);
+    mpd.pl_remove("searchaddpl").unwrap();
+}
+
+#[test]
+fn pl_delete_range() {
+    let mut mpd = connect();
+    for _ in 0..4 {
+        mpd.pl_push("deleterange", "silence.flac".to_owned()).unwrap();
+    }
+    mpd.pl_delete_range("deleterange", 1..3).unwrap();
+    mpd.pl_edit(&[EditAction::DeleteRange("deleterange".into(), 0..1)]).unwrap();
+    assert_eq!(mpd.playlist("deleterange", None::<std::ops::RangeFull>).unwrap().len(), 1);
+    mpd.pl_remove("deleterange").unwrap();
+}
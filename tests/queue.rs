@@ -0,0 +1,263 @@
+extern crate mpd;
+
+mod helpers;
+use helpers::connect;
+use mpd::queue::{QueueEntry, QueueSync};
+use mpd::song::{PosIdChange, QueuePosition, Range};
+use mpd::{Id, Song, Subsystem};
+use std::time::Duration;
+
+fn silence() -> Song {
+    Song { file: "silence.flac".to_string(), ..Song::default() }
+}
+
+#[test]
+fn replace_queue() {
+    let mut mpd = connect();
+    mpd.push(silence()).unwrap();
+    mpd.push(silence()).unwrap();
+
+    let ids = mpd.replace_queue(&[silence()], false).unwrap();
+    assert_eq!(ids.len(), 1);
+
+    let queue = mpd.queue(None).unwrap();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].place.map(|p| p.id), Some(ids[0]));
+}
+
+#[test]
+fn reconcile_queue() {
+    let mut mpd = connect();
+    mpd.push(silence()).unwrap();
+
+    let issues = mpd.reconcile_queue(false).unwrap();
+    assert!(issues.is_empty());
+}
+
+#[test]
+fn changes() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let version = mpd.status().unwrap().queue_version;
+
+    let id = mpd.push(silence()).unwrap();
+    let changed = mpd.changes(version, None).unwrap();
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0].file, "silence.flac");
+    assert_eq!(changed[0].place.map(|p| p.id), Some(id));
+
+    let version = mpd.status().unwrap().queue_version;
+    assert!(mpd.changes(version, None).unwrap().is_empty());
+}
+
+#[test]
+fn changesposid() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let version = mpd.status().unwrap().queue_version;
+
+    let first = mpd.push(silence()).unwrap();
+    let second = mpd.push(silence()).unwrap();
+    let changed = mpd.changesposid(version, None).unwrap();
+    assert_eq!(changed, vec![PosIdChange { pos: 0, id: first }, PosIdChange { pos: 1, id: second }]);
+}
+
+#[test]
+fn queue_sync() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    mpd.push(silence()).unwrap();
+
+    let mut sync = QueueSync::new();
+    assert!(sync.update(&mut mpd).unwrap());
+    assert!(!sync.update(&mut mpd).unwrap());
+
+    mpd.push(silence()).unwrap();
+    mpd.push(silence()).unwrap();
+    assert!(sync.handle(&mut mpd, &[Subsystem::Queue]).unwrap());
+    assert_eq!(sync.songs(), &mpd.queue(None).unwrap()[..]);
+}
+
+#[test]
+fn song_by_id() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let id = mpd.push(silence()).unwrap();
+
+    let song = mpd.song_by_id(id).unwrap().expect("queued song");
+    assert_eq!(song.file, "silence.flac");
+    assert_eq!(mpd.queue_by_id().unwrap(), vec![song]);
+
+    mpd.clear().unwrap();
+    assert_eq!(mpd.song_by_id(id).unwrap(), None);
+}
+
+#[test]
+fn priority() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let first = mpd.push(silence()).unwrap();
+    mpd.push(silence()).unwrap();
+
+    mpd.priority(first, 10).unwrap();
+    mpd.priority(1..2, 20).unwrap();
+    let prios: Vec<u8> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().prio).collect();
+    assert_eq!(prios, vec![10, 20]);
+}
+
+#[test]
+fn range() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let id = mpd.push(silence()).unwrap();
+
+    mpd.range(id, Duration::from_millis(100)..Duration::from_millis(300)).unwrap();
+    let song = mpd.song_by_id(id).unwrap().unwrap();
+    assert_eq!(song.range, Some(Range(Duration::from_millis(100), Some(Duration::from_millis(300)))));
+
+    mpd.range(id, ..).unwrap();
+    let song = mpd.song_by_id(id).unwrap().unwrap();
+    assert_eq!(song.range, None);
+}
+
+#[test]
+fn enqueue() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    mpd.push(silence()).unwrap();
+
+    let entry = QueueEntry::new("silence.flac").position(0).range(Duration::from_millis(100)..).priority(5);
+    let id = mpd.enqueue(entry).unwrap();
+    let song = mpd.song_by_id(id).unwrap().unwrap();
+    assert_eq!(song.place.map(|p| (p.pos, p.prio)), Some((0, 5)));
+    assert_eq!(song.range, Some(Range(Duration::from_millis(100), None)));
+
+    // Local songs can't be tagged, so the entry is removed again
+    assert!(mpd.enqueue(QueueEntry::new("silence.flac").tag("Title", "Silence")).is_err());
+    assert_eq!(mpd.queue(None).unwrap().len(), 2);
+}
+
+#[test]
+fn insert_absolute() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let last = mpd.push(silence()).unwrap();
+    let first = mpd.insert(silence(), 0).unwrap();
+
+    let ids: Vec<usize> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().id.0 as usize).collect();
+    assert_eq!(ids, vec![first, last.0 as usize]);
+}
+
+#[test]
+fn insert_relative() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    mpd.push(silence()).unwrap();
+    let current = mpd.push(silence()).unwrap();
+    mpd.push(silence()).unwrap();
+    mpd.switch(current).unwrap();
+    mpd.pause(true).unwrap();
+
+    let after = mpd.insert(silence(), QueuePosition::AfterCurrent(0)).unwrap();
+    let before = mpd.insert_multiple(&[silence(), silence()], QueuePosition::BeforeCurrent(0)).unwrap();
+
+    let ids: Vec<usize> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().id.0 as usize).collect();
+    assert_eq!(&ids[1..5], &[before[0], before[1], current.0 as usize, after]);
+}
+
+#[test]
+fn queue_next() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let first = mpd.queue_next(silence()).unwrap();
+    let last = mpd.push(silence()).unwrap();
+    mpd.switch(first).unwrap();
+    mpd.pause(true).unwrap();
+
+    let next = mpd.queue_next(silence()).unwrap();
+    let ids: Vec<Id> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().id).collect();
+    assert_eq!(ids, vec![first, next, last]);
+
+    let now = mpd.play_now(silence()).unwrap();
+    assert_eq!(mpd.status().unwrap().song.map(|place| (place.id, place.pos)), Some((now, 1)));
+}
+
+#[test]
+fn delete_range() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let ids = mpd.push_multiple(&[silence(), silence(), silence(), silence(), silence()]).unwrap();
+
+    mpd.delete(1..=2).unwrap();
+    let queue: Vec<Id> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().id).collect();
+    assert_eq!(queue, vec![ids[0], ids[3], ids[4]]);
+
+    mpd.delete(1..).unwrap();
+    assert_eq!(mpd.queue(None).unwrap().len(), 1);
+}
+
+#[test]
+fn shift_range() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let ids = mpd.push_multiple(&[silence(), silence(), silence(), silence()]).unwrap();
+
+    mpd.shift(0..2, 2).unwrap();
+    let queue: Vec<Id> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().id).collect();
+    assert_eq!(queue, vec![ids[2], ids[3], ids[0], ids[1]]);
+
+    mpd.switch(ids[2]).unwrap();
+    mpd.pause(true).unwrap();
+    mpd.shift(ids[1], QueuePosition::AfterCurrent(0)).unwrap();
+    let queue: Vec<Id> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().id).collect();
+    assert_eq!(queue, vec![ids[2], ids[1], ids[3], ids[0]]);
+}
+
+#[test]
+fn shuffle_remaining() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let ids = mpd.push_multiple(&[silence(), silence(), silence(), silence()]).unwrap();
+    mpd.switch(ids[1]).unwrap();
+    mpd.pause(true).unwrap();
+
+    mpd.shuffle_remaining().unwrap();
+    let queue: Vec<Id> = mpd.queue(None).unwrap().iter().map(|s| s.place.unwrap().id).collect();
+    assert_eq!(&queue[..2], &ids[..2]);
+}
+
+#[test]
+fn queue_iter() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    for _ in 0..3 {
+        mpd.push(silence()).unwrap();
+    }
+
+    let songs: Vec<Song> = mpd.queue_iter().unwrap().collect::<Result<_, _>>().unwrap();
+    assert_eq!(songs, mpd.queue(None).unwrap());
+
+    // Dropping the iterator early leaves the connection usable
+    assert_eq!(mpd.queue_iter().unwrap().take(1).count(), 1);
+    mpd.ping().unwrap();
+}
+
+#[test]
+fn queue_watcher() {
+    use helpers::Daemon;
+    use mpd::watcher::{QueueChange, QueueWatcher};
+
+    let daemon = Daemon::start();
+    let mut mpd = daemon.connect();
+    let first = mpd.push(silence()).unwrap();
+    let mut watcher = QueueWatcher::new(daemon.connect()).unwrap();
+    assert_eq!(watcher.songs().len(), 1);
+
+    let second = mpd.push(silence()).unwrap();
+    let changes = watcher.wait().unwrap();
+    assert!(matches!(changes[..], [QueueChange::Inserted(1, ref song)] if song.place.unwrap().id == second));
+
+    mpd.delete(first).unwrap();
+    assert_eq!(watcher.wait().unwrap(), [QueueChange::Removed(0, first)]);
+    assert_eq!(watcher.songs().len(), 1);
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "net")]
+
+extern crate mpd;
+
+mod helpers;
+use helpers::Daemon;
+
+use mpd::reconnect::{IdleEvent, IdleWatcher, ReconnectingClient};
+use mpd::socket::{Address, Endpoint};
+use mpd::{ClientBuilder, Subsystem};
+
+#[test]
+fn reconnect_after_close() {
+    let daemon = Daemon::start();
+    let endpoint = Endpoint { address: Address::Unix(daemon.sock_path().to_owned()), password: None };
+    let mut mpd = ReconnectingClient::new(ClientBuilder::new(endpoint)).unwrap();
+    mpd.tagtypes(&["artist"]).unwrap();
+
+    // The server drops the connection right away, without a response
+    mpd.run(|client| {
+        let _ = client.close();
+        Ok(())
+    })
+    .unwrap();
+
+    assert!(mpd.run(|client| client.status()).is_ok());
+    assert!(mpd.is_connected());
+    assert_eq!(mpd.run(|client| client.tagtypes()).unwrap(), vec!["Artist"]);
+}
+
+#[test]
+fn idle_watcher() {
+    let daemon = Daemon::start();
+    let endpoint = Endpoint { address: Address::Unix(daemon.sock_path().to_owned()), password: None };
+    let mut watcher = IdleWatcher::new(ClientBuilder::new(endpoint), &[Subsystem::Options]).unwrap();
+
+    let mut mpd = daemon.connect();
+    mpd.random(true).unwrap();
+
+    assert_eq!(watcher.next_event().unwrap(), IdleEvent::Changed(vec![Subsystem::Options]));
+}
@@ -9,6 +9,15 @@ fn commands() {
     println!("{:?}", mpd.commands().unwrap());
 }
 
+#[test]
+fn notcommands() {
+    let mut mpd = connect();
+    let commands = mpd.commands().unwrap();
+    let notcommands = mpd.notcommands().unwrap();
+    assert!(commands.iter().any(|c| c == "status"));
+    assert!(notcommands.iter().all(|c| !commands.contains(c)));
+}
+
 #[test]
 fn urlhandlers() {
     let mut mpd = connect();
@@ -26,3 +35,50 @@ fn tagtypes() {
     let mut mpd = connect();
     println!("{:?}", mpd.tagtypes().unwrap());
 }
+
+#[test]
+fn protocol_features() {
+    use mpd::ProtocolFeature;
+
+    let mut mpd = connect();
+    if mpd.version < mpd::Version(0, 24, 0) {
+        return;
+    }
+    assert!(mpd.protocol_available().unwrap().contains(&ProtocolFeature::HidePlaylistsInRoot));
+    mpd.protocol_clear().unwrap();
+    assert!(mpd.protocol().unwrap().is_empty());
+    mpd.protocol_enable(&[ProtocolFeature::HidePlaylistsInRoot]).unwrap();
+    assert_eq!(mpd.protocol().unwrap(), vec![ProtocolFeature::HidePlaylistsInRoot]);
+    mpd.protocol_disable(&[ProtocolFeature::HidePlaylistsInRoot]).unwrap();
+    mpd.protocol_all().unwrap();
+}
+
+#[test]
+fn config() {
+    let daemon = helpers::Daemon::start();
+    let mut mpd = daemon.connect();
+    let config = mpd.config().unwrap();
+    assert_eq!(config.music_directory.as_deref().map(std::path::Path::new), Some(daemon.music_directory()));
+}
+
+#[test]
+fn capabilities() {
+    let mut mpd = connect();
+    let capabilities = mpd.capabilities().unwrap().clone();
+    assert!(capabilities.has_command("status"));
+    assert!(capabilities.has_tagtype("artist"));
+
+    mpd.tagtypes_clear().unwrap();
+    assert!(!mpd.capabilities().unwrap().has_tagtype("artist"));
+}
+
+#[test]
+fn command_raw() {
+    let mut mpd = connect();
+    let pairs = mpd.command_raw("status", ()).unwrap();
+    assert!(pairs.iter().any(|(key, _)| key == "volume"));
+
+    let status: mpd::Status = mpd.command_as("status", ()).unwrap();
+    assert_eq!(status.queue_len, 0);
+    assert!(mpd.command_raw("no_such_command", ("quoted arg", 1u32)).is_err());
+}
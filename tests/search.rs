@@ -2,6 +2,8 @@ extern crate mpd;
 
 mod helpers;
 use helpers::connect;
+use mpd::search::{AddOptions, Expression, Operation, Sort, SortKey};
+use mpd::song::QueuePosition;
 use mpd::Query;
 
 #[test]
@@ -13,3 +15,71 @@ fn search() {
     println!("{:?}", songs);
     assert!(songs.is_ok());
 }
+
+#[test]
+fn list_groups() {
+    let mut mpd = connect();
+    let tree = mpd.list_groups(&mpd::Term::File, &Query::new(), &["album", "albumartist"]).unwrap();
+    println!("{:?}", tree);
+    assert!(!tree.is_empty());
+}
+
+#[test]
+fn find_sorted() {
+    let mut mpd = connect();
+    let sort = Sort::desc(SortKey::LastModified);
+    let songs = mpd.find_sorted(Query::new().and_with_op(mpd::Term::File, Operation::Contains, "flac"), &sort, (0, 10)).unwrap();
+    assert!(songs.len() <= 10);
+    assert!(songs.windows(2).all(|pair| pair[0].last_mod >= pair[1].last_mod));
+}
+
+#[test]
+fn findadd_window() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let mut query = Query::new();
+    query.and_with_op(mpd::Term::File, Operation::Contains, "flac");
+
+    mpd.findadd_with(&query, &AddOptions::new().window((0, 1))).unwrap();
+    assert_eq!(mpd.queue(None).unwrap().len(), 1);
+    mpd.searchadd(&query).unwrap();
+    assert!(mpd.queue(None).unwrap().len() > 1);
+}
+
+#[test]
+fn count_raw() {
+    let mut mpd = connect();
+    let query = Query::from(Expression::raw("(file == 'silence.flac')"));
+    let count = mpd.count(&query).unwrap();
+    assert_eq!(count.songs, 1);
+    assert_eq!(mpd.find(&query, None).unwrap().len(), 1);
+}
+
+#[test]
+fn searchadd_at() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let first = mpd.push(mpd::Song { file: "silence.flac".into(), ..Default::default() }).unwrap();
+    mpd.push(mpd::Song { file: "silence.flac".into(), ..Default::default() }).unwrap();
+    mpd.switch(first).unwrap();
+    mpd.pause(true).unwrap();
+
+    let query = Query::from(Expression::new(mpd::Term::File, "silence.flac"));
+    mpd.searchadd_with(&query, &AddOptions::new().position(QueuePosition::AfterCurrent(0))).unwrap();
+    let queue = mpd.queue(None).unwrap();
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue[0].place.unwrap().id, first);
+}
+
+#[test]
+fn findadd_sorted() {
+    let mut mpd = connect();
+    mpd.clear().unwrap();
+    let mut query = Query::new();
+    query.and_with_op(mpd::Term::File, Operation::Contains, "flac");
+
+    mpd.findadd_with(&query, &AddOptions::new().sort(Sort::desc(SortKey::Added)).window((0, 1))).unwrap();
+    assert_eq!(mpd.queue(None).unwrap().len(), 1);
+    mpd.searchadd_with(&query, &AddOptions::new().sort(Sort::asc(SortKey::Tag("Title".into())))).unwrap();
+    assert!(mpd.queue(None).unwrap().len() > 1);
+}
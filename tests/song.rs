@@ -4,7 +4,7 @@ mod helpers;
 use std::time::Duration;
 
 use helpers::connect;
-use mpd::{Song, lsinfo::LsInfoEntry};
+use mpd::{Song, lsinfo::{ListAllEntry, LsInfoEntry}};
 
 #[test]
 fn currentsong() {
@@ -29,15 +29,42 @@ fn lsinfo() {
     let songs = mpd.lsinfo(Song { file: "silence.flac".into(), ..Default::default() }).unwrap();
     assert_eq!(songs.len(), 1);
 
-    if let LsInfoEntry::Song(song) = songs.get(0).unwrap() {
+    if let LsInfoEntry::Song(song) = songs.first().unwrap() {
         assert_eq!(song.file, "silence.flac");
         assert_eq!(song.duration.expect("song should have duration"), Duration::from_millis(500));
     }
 }
 
+#[test]
+fn listall() {
+    let mut mpd = connect();
+    let entries = mpd.listall("").unwrap();
+    assert!(entries.contains(&ListAllEntry::File("silence.flac".into())));
+}
+
+#[test]
+fn listallinfo_entries() {
+    let mut mpd = connect();
+    let entries = mpd.listallinfo_entries("").unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert!(entries.iter().any(|e| matches!(e, LsInfoEntry::Song(song) if song.file == "silence.flac")));
+
+    // Dropping the iterator early keeps the connection usable
+    let first = mpd.listallinfo_entries("").unwrap().next();
+    assert!(matches!(first, Some(Ok(_))));
+    mpd.ping().unwrap();
+}
+
 #[test]
 fn rescan_update() {
     let mut mpd = connect();
     println!("update: {:?}", mpd.update());
     println!("rescan: {:?}", mpd.rescan());
 }
+
+#[test]
+fn update_wait() {
+    let mut mpd = connect();
+    let job = mpd.rescan().unwrap();
+    job.wait(&mut mpd).unwrap();
+    assert!(job.is_done(&mut mpd).unwrap());
+}
@@ -2,6 +2,7 @@ extern crate mpd;
 
 mod helpers;
 use helpers::connect;
+use mpd::{Sticker, StickerOp};
 
 #[test]
 /// Creating a sticker and then getting that sticker returns the value that was set.
@@ -15,3 +16,43 @@ fn set_sticker() {
     let sticker = mpd.sticker("song", "silence.flac", "test_sticker").unwrap();
     assert_eq!(sticker, VALUE);
 }
+
+#[test]
+fn find_sticker_op() {
+    let mut mpd = connect();
+    mpd.set_sticker("song", "silence.flac", "rating", "8").unwrap();
+
+    let found = mpd.find_sticker_op("song", "", "rating", StickerOp::Gt, "7", None).unwrap();
+    assert_eq!(found, vec!["silence.flac"]);
+    let found = mpd.find_sticker_op("song", "", "rating", StickerOp::Lt, "7", None).unwrap();
+    assert!(found.is_empty());
+    let found = mpd.find_sticker_op("song", "", "rating", ">", "7", None).unwrap();
+    assert_eq!(found, vec!["silence.flac"]);
+}
+
+#[test]
+fn inc_dec_sticker() {
+    let mut mpd = connect();
+    if mpd.version < mpd::Version(0, 24, 0) {
+        return;
+    }
+
+    mpd.set_sticker("song", "silence.flac", "playcount", "1").unwrap();
+    mpd.sticker_inc("song", "silence.flac", "playcount", 2).unwrap();
+    assert_eq!(mpd.sticker("song", "silence.flac", "playcount").unwrap(), "3");
+    mpd.sticker_dec("song", "silence.flac", "playcount", 1).unwrap();
+    assert_eq!(mpd.sticker("song", "silence.flac", "playcount").unwrap(), "2");
+}
+
+#[test]
+fn sticker_list() {
+    let mut mpd = connect();
+    mpd.clear_stickers("song", "silence.flac").ok();
+    mpd.set_sticker("song", "silence.flac", "note", "a=b").unwrap();
+
+    let stickers = mpd.sticker_list("song", "silence.flac").unwrap();
+    assert_eq!(stickers, vec![Sticker { name: "note".into(), value: "a=b".into() }]);
+
+    let found = mpd.find_sticker("song", "", "note", None).unwrap();
+    assert_eq!(found, vec![("silence.flac".to_owned(), "a=b".to_owned())]);
+}
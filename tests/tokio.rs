@@ -0,0 +1,93 @@
+#![cfg(feature = "tokio")]
+
+mod helpers;
+use helpers::Daemon;
+
+use mpd::{Song, State, Subsystem};
+use tokio::runtime::Builder;
+
+fn run<F: std::future::Future>(future: F) -> F::Output {
+    Builder::new_current_thread().enable_io().build().unwrap().block_on(future)
+}
+
+#[test]
+fn status() {
+    let daemon = Daemon::start();
+    run(async {
+        let mut mpd = mpd::tokio::Client::connect_unix(daemon.sock_path()).await.unwrap();
+        let status = mpd.status().await.unwrap();
+        assert_eq!(status.state, State::Stop);
+        assert_eq!(mpd.currentsong().await.unwrap(), None);
+    });
+}
+
+#[test]
+fn queue() {
+    let daemon = Daemon::start();
+    run(async {
+        let mut mpd = mpd::tokio::Client::connect_unix(daemon.sock_path()).await.unwrap();
+        let id = mpd.push(Song { file: "silence.flac".into(), ..Default::default() }).await.unwrap();
+        let queue = mpd.queue(None).await.unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].place.unwrap().id, id);
+        mpd.clear().await.unwrap();
+        assert!(mpd.queue(None).await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn wait() {
+    let daemon = Daemon::start();
+    run(async {
+        let mut mpd = mpd::tokio::Client::connect_unix(daemon.sock_path()).await.unwrap();
+        let mut sender = daemon.connect();
+        let waiting = mpd.wait(&[Subsystem::Options]);
+        sender.consume(true).unwrap();
+        assert_eq!(waiting.await.unwrap(), vec![Subsystem::Options]);
+    });
+}
+
+#[test]
+fn idle_stream() {
+    use futures_util::StreamExt;
+
+    let daemon = Daemon::start();
+    run(async {
+        let mpd = mpd::tokio::Client::connect_unix(daemon.sock_path()).await.unwrap();
+        let mut events = mpd.into_idle_stream(&[Subsystem::Options, Subsystem::Mixer]);
+        let mut sender = daemon.connect();
+
+        sender.consume(true).unwrap();
+        assert_eq!(events.next().await.unwrap().unwrap(), Subsystem::Options);
+        sender.random(true).unwrap();
+        assert_eq!(events.next().await.unwrap().unwrap(), Subsystem::Options);
+    });
+}
+
+#[test]
+fn playlists() {
+    let daemon = Daemon::start();
+    run(async {
+        let mut mpd = mpd::tokio::Client::connect_unix(daemon.sock_path()).await.unwrap();
+        mpd.pl_push("tokio", "silence.flac".to_owned()).await.unwrap();
+        assert!(mpd.playlists().await.unwrap().iter().any(|pl| pl.name == "tokio"));
+        let songs = mpd.playlist("tokio", None::<std::ops::RangeFull>).await.unwrap();
+        assert_eq!(songs.len(), 1);
+        mpd.load("tokio", ..).await.unwrap();
+        assert_eq!(mpd.queue(None).await.unwrap().len(), 1);
+        mpd.pl_remove("tokio").await.unwrap();
+    });
+}
+
+#[test]
+fn find() {
+    let daemon = Daemon::start();
+    run(async {
+        let mut mpd = mpd::tokio::Client::connect_unix(daemon.sock_path()).await.unwrap();
+        let mut query = mpd::Query::new();
+        query.and(mpd::Term::File, "silence.flac");
+        assert_eq!(mpd.find(&query, None).await.unwrap().len(), 1);
+        mpd.findadd(&query).await.unwrap();
+        assert_eq!(mpd.queue(None).await.unwrap().len(), 1);
+    });
+}